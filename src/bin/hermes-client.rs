@@ -65,7 +65,7 @@ async fn main() -> std::io::Result<()> {
     let mut headers = Headers::new();
     let (_, uri) = Uri::parse(url)
         .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid url"))?;
-    headers.insert("Host", &[uri.authority.host.clone()]);
+    headers.insert("Host", std::slice::from_ref(&uri.authority.host));
 
     if let Some(values) = matches.get_many::<String>("header") {
         for h in values {
@@ -1,11 +1,15 @@
 //! Common data structures and helpers used across the crate.
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
-/// A type alias for a `HashMap` where the keys are `String`
+/// A type alias for an insertion-ordered map where the keys are `String`
 /// and the values are of a generic type `T`.
 ///
 /// This type can be used as a convenient shorthand for
-/// defining string-keyed hash maps with customizable value types.
+/// defining string-keyed maps with customizable value types. Iteration
+/// follows the insertion order, so values serialized from a
+/// [`Value::Dictionary`](crate::concepts::value::Value::Dictionary) keep
+/// their key order. Use `shift_remove` to remove an entry without
+/// reordering the others.
 ///
 /// # Examples
 ///
@@ -18,8 +22,9 @@ use std::collections::HashMap;
 ///
 /// assert_eq!(dict["key1"], 10);
 /// assert_eq!(dict["key2"], 20);
+/// assert_eq!(dict.keys().collect::<Vec<_>>(), ["key1", "key2"]);
 /// ```
-pub type Dictionary<T> = HashMap<String, T>;
+pub type Dictionary<T> = IndexMap<String, T>;
 
 #[cfg(doc)]
 use crate::http::{Message, Request, Response};
@@ -137,6 +142,8 @@ pub fn identifier(input: &str) -> IResult<&str, &str> {
 
 pub mod value;
 
+pub mod config;
+pub mod lifecycle;
 pub mod process;
 
 #[cfg(test)]
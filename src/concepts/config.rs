@@ -0,0 +1,678 @@
+//! Layered configuration loading.
+//!
+//! A [`ConfigLoader`] merges configuration layers into a [`Value`], in the
+//! order they are added, and deserializes the result into the configuration
+//! type of an application. Later layers override earlier ones: dictionaries
+//! are merged key by key with [`Value::merge`], other values are replaced.
+//!
+//! The layers are:
+//!
+//! - [`defaults`](ConfigLoader::defaults), given as a [`Value`];
+//! - [`file`](ConfigLoader::file)s parsed with a [`ValueFormatter`] chosen
+//!   from their extension (`.yaml`/`.yml`, `.json` or `.toml`);
+//! - [`profile`](ConfigLoader::profile) overlays such as `config/prod.yaml`;
+//! - [`env`](ConfigLoader::env)ironment variables with a prefix:
+//!   `APP_SERVER__PORT` sets `server.port`, `__` separating the levels.
+//!   Shorter paths are applied first, so `APP_SERVER__PORT` refines
+//!   `APP_SERVER`, and variables which are not valid Unicode are skipped;
+//! - command line [`overrides`](ConfigLoader::overrides) such as
+//!   `server.port=8080`.
+//!
+//! Values of environment variables and overrides are kept as strings, except
+//! JSON arrays and dictionaries (`[1, 2]`, `{"port": 80}`).
+//! [`load_as`](ConfigLoader::load_as) converts strings to the numbers and
+//! booleans expected by the configuration type, so `APP_SERVER__PORT=8080`
+//! fills a `u16` while `APP_DB__PASSWORD=123456` stays a `String`.
+//!
+//! ```
+//! use hermes::concepts::config::ConfigLoader;
+//! use hermes::concepts::value::Value;
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     server: Server,
+//! }
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//!     workers: usize,
+//! }
+//!
+//! let dir = std::env::temp_dir().join("hermes_config_doc");
+//! std::fs::create_dir_all(&dir).unwrap();
+//! std::fs::write(dir.join("app.yaml"), "name: shop\nserver:\n  host: 0.0.0.0\n  port: 80\n").unwrap();
+//! std::fs::write(dir.join("prod.json"), r#"{"server": {"port": 443}}"#).unwrap();
+//!
+//! let config: Config = ConfigLoader::new()
+//!     .defaults(Value::from([("server", Value::from([("workers", 4)]))]))
+//!     .file(dir.join("app.yaml"))
+//!     .profile(&dir, "prod")
+//!     .vars("SHOP", [("SHOP_SERVER__WORKERS".to_string(), "16".to_string())])
+//!     .overrides(["server.host=127.0.0.1"])
+//!     .load_as()
+//!     .unwrap();
+//! assert_eq!(config.name, "shop");
+//! assert_eq!(config.server.host, "127.0.0.1");
+//! assert_eq!(config.server.port, 443);
+//! assert_eq!(config.server.workers, 16);
+//! ```
+
+use crate::concepts::value::json::JsonFormatter;
+use crate::concepts::value::toml::TomlFormatter;
+use crate::concepts::value::yaml::YamlFormatter;
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use serde::de::value::{Error as DeError, MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, Error as _, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserializer};
+use std::ffi::OsString;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Extensions of the configuration files, tried in order by profiles.
+const EXTENSIONS: [&str; 4] = ["yaml", "yml", "json", "toml"];
+
+/// Failure to load a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A required file does not exist.
+    Missing(PathBuf),
+    /// A file cannot be read or parsed.
+    File(PathBuf, String),
+    /// An environment variable or an override cannot be applied.
+    Override(String, String),
+    /// The merged configuration does not fit the configuration type.
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing(path) => {
+                write!(f, "configuration file {} not found", path.display())
+            }
+            ConfigError::File(path, reason) => {
+                write!(f, "configuration file {}: {}", path.display(), reason)
+            }
+            ConfigError::Override(name, reason) => {
+                write!(f, "configuration override {}: {}", name, reason)
+            }
+            ConfigError::Invalid(reason) => write!(f, "invalid configuration: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+type SharedFormatter = Arc<dyn ValueFormatter + Send + Sync>;
+
+#[derive(Clone)]
+enum Layer {
+    Value(Value),
+    /// The first existing candidate is read.
+    File {
+        candidates: Vec<PathBuf>,
+        required: bool,
+        formatter: Option<SharedFormatter>,
+    },
+    /// Variables of the process when `None`.
+    Env {
+        prefix: String,
+        vars: Option<Vec<(String, String)>>,
+    },
+    Overrides(Vec<String>),
+}
+
+/// Builder merging configuration layers.
+#[derive(Clone, Default)]
+pub struct ConfigLoader {
+    layers: Vec<Layer>,
+}
+
+impl ConfigLoader {
+    /// Create a loader without layers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add default values.
+    pub fn defaults(mut self, defaults: impl Into<Value>) -> Self {
+        self.layers.push(Layer::Value(defaults.into()));
+        self
+    }
+
+    /// Add a required file, parsed according to its extension.
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.layers.push(Layer::File {
+            candidates: vec![path.into()],
+            required: true,
+            formatter: None,
+        });
+        self
+    }
+
+    /// Add a file read only if it exists.
+    pub fn optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.layers.push(Layer::File {
+            candidates: vec![path.into()],
+            required: false,
+            formatter: None,
+        });
+        self
+    }
+
+    /// Add a required file parsed with `formatter`.
+    pub fn file_with(
+        mut self,
+        path: impl Into<PathBuf>,
+        formatter: impl ValueFormatter + Send + Sync + 'static,
+    ) -> Self {
+        self.layers.push(Layer::File {
+            candidates: vec![path.into()],
+            required: true,
+            formatter: Some(Arc::new(formatter)),
+        });
+        self
+    }
+
+    /// Add the overlay of the `profile` (e.g. `prod`) stored in `dir`.
+    ///
+    /// The first existing `dir/<profile>.yaml`, `.yml`, `.json` or `.toml`
+    /// file is read; a profile without file is ignored.
+    pub fn profile(mut self, dir: impl AsRef<Path>, profile: &str) -> Self {
+        let candidates = EXTENSIONS
+            .iter()
+            .map(|extension| dir.as_ref().join(format!("{}.{}", profile, extension)))
+            .collect();
+        self.layers.push(Layer::File {
+            candidates,
+            required: false,
+            formatter: None,
+        });
+        self
+    }
+
+    /// Add the environment variables starting with `<prefix>_`, read when
+    /// loading.
+    pub fn env(mut self, prefix: &str) -> Self {
+        self.layers.push(Layer::Env {
+            prefix: prefix.to_string(),
+            vars: None,
+        });
+        self
+    }
+
+    /// Add the `vars` starting with `<prefix>_`, as environment variables.
+    pub fn vars(mut self, prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.layers.push(Layer::Env {
+            prefix: prefix.to_string(),
+            vars: Some(vars.into_iter().collect()),
+        });
+        self
+    }
+
+    /// Add `path=value` overrides, e.g. collected from the command line.
+    pub fn overrides<S: Into<String>>(mut self, overrides: impl IntoIterator<Item = S>) -> Self {
+        self.layers.push(Layer::Overrides(
+            overrides.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Merge the layers.
+    pub fn load(&self) -> Result<Value, ConfigError> {
+        let mut config = Value::Dictionary(Dictionary::new());
+        for layer in &self.layers {
+            match layer {
+                Layer::Value(value) => config.merge(value.clone()),
+                Layer::File {
+                    candidates,
+                    required,
+                    formatter,
+                } => match candidates.iter().find(|path| path.is_file()) {
+                    Some(path) => config.merge(read(path, formatter.as_deref())?),
+                    None if *required => return Err(ConfigError::Missing(candidates[0].clone())),
+                    None => {}
+                },
+                Layer::Env { prefix, vars } => {
+                    let vars = vars
+                        .clone()
+                        .unwrap_or_else(|| unicode_vars(std::env::vars_os()));
+                    let prefix = format!("{}_", prefix);
+                    let mut entries: Vec<_> = vars
+                        .into_iter()
+                        .filter_map(|(name, value)| {
+                            let key = name.strip_prefix(&prefix)?;
+                            let path = key.to_ascii_lowercase().replace("__", ".");
+                            Some((path, name, value))
+                        })
+                        .collect();
+                    entries.sort_by(|(a, ..), (b, ..)| {
+                        (a.split('.').count(), a).cmp(&(b.split('.').count(), b))
+                    });
+                    for (path, name, value) in entries {
+                        set(&mut config, &name, &path, &value)?;
+                    }
+                }
+                Layer::Overrides(overrides) => {
+                    for item in overrides {
+                        let (path, value) = item.split_once('=').ok_or_else(|| {
+                            ConfigError::Override(item.clone(), "expected path=value".to_string())
+                        })?;
+                        set(&mut config, item, path.trim(), value)?;
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Merge the layers and deserialize the result into `T`.
+    ///
+    /// Strings are parsed where `T` expects a number or a boolean.
+    pub fn load_as<T: DeserializeOwned>(&self) -> Result<T, ConfigError> {
+        T::deserialize(Coerce(self.load()?)).map_err(|e| ConfigError::Invalid(e.to_string()))
+    }
+}
+
+/// Keep the variables whose name and value are valid Unicode.
+fn unicode_vars(vars: impl IntoIterator<Item = (OsString, OsString)>) -> Vec<(String, String)> {
+    vars.into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// Read the dictionary of the file at `path`.
+fn read(
+    path: &Path,
+    formatter: Option<&(dyn ValueFormatter + Send + Sync)>,
+) -> Result<Value, ConfigError> {
+    let error = |reason: &str| ConfigError::File(path.to_path_buf(), reason.to_string());
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let formatter: &dyn ValueFormatter = match (formatter, extension.as_deref()) {
+        (Some(formatter), _) => formatter,
+        (None, Some("yaml" | "yml")) => &YamlFormatter,
+        (None, Some("json")) => &JsonFormatter,
+        (None, Some("toml")) => &TomlFormatter,
+        (None, _) => return Err(error("unsupported format")),
+    };
+    let bytes = fs::read(path).map_err(|e| error(&e.to_string()))?;
+    match formatter.parse_bytes(&bytes) {
+        Some(Value::Dictionary(entries)) => Ok(Value::Dictionary(entries)),
+        // An empty YAML document.
+        Some(Value::Null) => Ok(Value::Dictionary(Dictionary::new())),
+        Some(_) => Err(error("expected a dictionary")),
+        None => Err(error("invalid syntax")),
+    }
+}
+
+/// Set the dotted `path` of `config` to `value`, read as JSON if it is an
+/// array or a dictionary.
+fn set(config: &mut Value, name: &str, path: &str, value: &str) -> Result<(), ConfigError> {
+    if path.is_empty() || path.split('.').any(str::is_empty) {
+        return Err(ConfigError::Override(
+            name.to_string(),
+            "invalid path".to_string(),
+        ));
+    }
+    let value = match JsonFormatter.parse(value) {
+        Some(parsed @ (Value::Array(_) | Value::Dictionary(_))) => parsed,
+        _ => Value::String(value.to_string()),
+    };
+    config
+        .set_path(path, value)
+        .map(|_| ())
+        .map_err(|e| ConfigError::Override(name.to_string(), e.to_string()))
+}
+
+/// Deserializer of a configuration [`Value`] parsing strings into the
+/// scalars requested by the target type.
+struct Coerce(Value);
+
+impl Coerce {
+    fn parse<'de, T: std::str::FromStr, V: Visitor<'de>>(
+        self,
+        visitor: V,
+        visit: fn(V, T) -> Result<V::Value, DeError>,
+    ) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::String(s) => match s.trim().parse() {
+                Ok(parsed) => visit(visitor, parsed),
+                Err(_) => Err(DeError::invalid_value(
+                    serde::de::Unexpected::Str(&s),
+                    &visitor,
+                )),
+            },
+            value => Coerce(value).deserialize_any(visitor),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Coerce {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Coerce {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Int(i) => visitor.visit_i64(i),
+            Value::Number(n) => visitor.visit_f64(n),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter().map(Coerce));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Dictionary(entries) => {
+                let mut map =
+                    MapDeserializer::new(entries.into_iter().map(|(k, v)| (k, Coerce(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_bool)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_i64)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_i64)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_i64)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_i64)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_u64)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_u64)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_u64)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_u64)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_f64)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.parse(visitor, V::visit_f64)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(Coerce(value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Dictionary(entries) if entries.len() == 1 => {
+                visitor.visit_enum(MapAccessDeserializer::new(MapDeserializer::new(
+                    entries.into_iter().map(|(k, v)| (k, Coerce(v))),
+                )))
+            }
+            _ => Err(DeError::custom("expected an enum variant")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        debug: bool,
+        database: Database,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Database {
+        url: String,
+        pool_size: u32,
+        replicas: Vec<String>,
+    }
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_layers() {
+        let dir = dir("hermes_config_layers");
+        fs::write(
+            dir.join("app.toml"),
+            "debug = true\n[database]\nurl = \"postgres://db\"\npool_size = 5\nreplicas = []\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("prod.yml"),
+            "debug: false\ndatabase:\n  pool_size: 20\n",
+        )
+        .unwrap();
+        fs::write(dir.join("prod.json"), "{\"debug\": true}").unwrap();
+
+        let loader = ConfigLoader::new()
+            .file(dir.join("app.toml"))
+            .optional_file(dir.join("missing.yaml"))
+            .profile(&dir, "prod")
+            .profile(&dir, "staging")
+            .vars(
+                "APP",
+                [
+                    ("APP_DATABASE__REPLICAS", r#"["r1", "r2"]"#),
+                    ("APP_DATABASE__URL", "postgres://prod"),
+                    (
+                        "APP_DATABASE",
+                        r#"{"url": "postgres://env", "pool_size": 1}"#,
+                    ),
+                    ("OTHER_DEBUG", "true"),
+                ]
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+            )
+            .overrides(["database.pool_size = 30"]);
+        assert_eq!(
+            loader.load_as::<Config>().unwrap(),
+            Config {
+                debug: false,
+                database: Database {
+                    url: "postgres://prod".to_string(),
+                    pool_size: 30,
+                    replicas: vec!["r1".to_string(), "r2".to_string()],
+                },
+            }
+        );
+        let Value::Dictionary(root) = loader.load().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(root.keys().collect::<Vec<_>>(), ["debug", "database"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = dir("hermes_config_errors");
+        fs::write(dir.join("list.yaml"), "- 1\n").unwrap();
+        fs::write(dir.join("bad.json"), "{").unwrap();
+        fs::write(dir.join("app.ini"), "a=1").unwrap();
+        fs::write(dir.join("empty.yaml"), "").unwrap();
+        let error = |loader: ConfigLoader| loader.load().unwrap_err().to_string();
+
+        assert_eq!(
+            error(ConfigLoader::new().file(dir.join("none.yaml"))),
+            format!(
+                "configuration file {} not found",
+                dir.join("none.yaml").display()
+            )
+        );
+        assert!(error(ConfigLoader::new().file(dir.join("list.yaml")))
+            .ends_with("expected a dictionary"));
+        assert!(error(ConfigLoader::new().file(dir.join("bad.json"))).ends_with("invalid syntax"));
+        assert!(
+            error(ConfigLoader::new().file(dir.join("app.ini"))).ends_with("unsupported format")
+        );
+        assert_eq!(
+            ConfigLoader::new()
+                .file_with(
+                    dir.join("app.ini"),
+                    crate::concepts::value::form::FormFormatter
+                )
+                .load()
+                .unwrap(),
+            Value::from([("a", "1")])
+        );
+        assert_eq!(
+            error(ConfigLoader::new().overrides(["debug"])),
+            "configuration override debug: expected path=value"
+        );
+        assert_eq!(
+            error(ConfigLoader::new().overrides(["a=1", "a.b=2"])),
+            "configuration override a.b=2: path \"a.b\" cannot be reached"
+        );
+        assert_eq!(
+            error(ConfigLoader::new().vars("APP", [("APP___X".to_string(), "1".to_string())])),
+            "configuration override APP___X: invalid path"
+        );
+        let invalid = ConfigLoader::new()
+            .file(dir.join("empty.yaml"))
+            .load_as::<Config>()
+            .unwrap_err();
+        assert_eq!(
+            invalid.to_string(),
+            "invalid configuration: missing field `debug`"
+        );
+    }
+
+    #[test]
+    fn test_coerce_strings() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Db {
+            password: String,
+            port: u16,
+            ratio: f64,
+            tls: bool,
+            timeout: Option<i32>,
+            mode: Mode,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Primary,
+        }
+
+        let vars = [
+            ("APP_DB__PASSWORD", "123456"),
+            ("APP_DB__PORT", "5432"),
+            ("APP_DB__RATIO", "0.5"),
+            ("APP_DB__TLS", "true"),
+            ("APP_DB__TIMEOUT", "-1"),
+            ("APP_DB__MODE", "primary"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let loader = ConfigLoader::new().vars("APP", vars);
+        assert_eq!(
+            loader.load().unwrap().pointer("/db/password"),
+            Some(&Value::from("123456"))
+        );
+        assert_eq!(
+            loader.load_as::<HashMap<String, Db>>().unwrap()["db"],
+            Db {
+                password: "123456".to_string(),
+                port: 5432,
+                ratio: 0.5,
+                tls: true,
+                timeout: Some(-1),
+                mode: Mode::Primary,
+            }
+        );
+        let error = ConfigLoader::new()
+            .overrides(["port=http"])
+            .load_as::<HashMap<String, u16>>()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration: invalid value: string \"http\", expected u16"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_skips_non_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let vars = unicode_vars([
+            (OsString::from("APP_NAME"), OsString::from("hermes")),
+            (
+                OsString::from("APP_RAW"),
+                OsString::from_vec(b"\xff".to_vec()),
+            ),
+            (
+                OsString::from_vec(b"APP_\xff".to_vec()),
+                OsString::from("x"),
+            ),
+        ]);
+        assert_eq!(vars, [("APP_NAME".to_string(), "hermes".to_string())]);
+    }
+}
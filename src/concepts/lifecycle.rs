@@ -0,0 +1,571 @@
+//! Asynchronous application lifecycle.
+//!
+//! A [`Runtime`] owns the [`Service`]s of an application (servers, workers,
+//! connection pools...) and drives them through their lifecycle:
+//!
+//! 1. the services are [`start`](Service::start)ed one after the other, in
+//!    the order they were added;
+//! 2. they then [`run`](Service::run) concurrently until a shutdown is
+//!    requested: `SIGINT`/`SIGTERM` (or Ctrl-C), a [`RuntimeHandle::stop`]
+//!    call, a service failing or every service returning;
+//! 3. the shutdown is broadcast to the running services with a deadline,
+//!    the [`grace_period`](Runtime::grace_period), to stop accepting work and
+//!    drain the work in progress. Services still running at the deadline are
+//!    cancelled;
+//! 4. the started services are [`finalize`](Service::finalize)d in reverse
+//!    order.
+//!
+//! ```
+//! use hermes::concepts::lifecycle::{BoxFuture, Runtime, Service, Shutdown};
+//! use std::time::Duration;
+//!
+//! struct Worker;
+//!
+//! impl Service for Worker {
+//!     fn run(&mut self, mut shutdown: Shutdown) -> BoxFuture<'_, Result<(), String>> {
+//!         Box::pin(async move {
+//!             // Work until the shutdown is requested.
+//!             shutdown.wait().await;
+//!             Ok(())
+//!         })
+//!     }
+//! }
+//!
+//! # tokio_test::block_on(async {
+//! let runtime = Runtime::new()
+//!     .service(Worker)
+//!     .grace_period(Duration::from_secs(5));
+//! let handle = runtime.handle();
+//! handle.stop();
+//! assert!(runtime.run().await.is_ok());
+//! # })
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex, Notify};
+use tokio::task::{Id, JoinError, JoinSet};
+use tokio::time::{timeout_at, Instant};
+
+/// A boxed future returned by the [`Service`] hooks.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Component of an application managed by a [`Runtime`].
+///
+/// Only [`Service::run`] is required; the other hooks do nothing by default.
+pub trait Service: Send {
+    /// Name used in the [`LifecycleError`]s.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Acquire the resources of the service (bind a socket, open a pool...).
+    fn start(&mut self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Do the work of the service until `shutdown` is triggered, then drain
+    /// the work in progress before the [`Shutdown::deadline`].
+    fn run(&mut self, shutdown: Shutdown) -> BoxFuture<'_, Result<(), String>>;
+
+    /// Release the resources of the service, once every service stopped.
+    fn finalize(&mut self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Notification of a shutdown, carrying the deadline to stop before.
+///
+/// Clones observe the same shutdown.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<Option<Instant>>,
+}
+
+/// Sending side of a [`Shutdown`].
+#[derive(Debug, Clone)]
+pub struct ShutdownSender {
+    sender: Arc<watch::Sender<Option<Instant>>>,
+}
+
+impl Shutdown {
+    /// Create a shutdown and the sender triggering it.
+    ///
+    /// ```
+    /// use hermes::concepts::lifecycle::Shutdown;
+    /// use std::time::Duration;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let (sender, mut shutdown) = Shutdown::channel();
+    /// assert!(!shutdown.is_triggered());
+    /// sender.send(Duration::from_secs(1));
+    /// let deadline = shutdown.wait().await;
+    /// assert_eq!(shutdown.deadline(), Some(deadline));
+    /// # })
+    /// ```
+    pub fn channel() -> (ShutdownSender, Shutdown) {
+        let (sender, receiver) = watch::channel(None);
+        (
+            ShutdownSender {
+                sender: Arc::new(sender),
+            },
+            Shutdown { receiver },
+        )
+    }
+
+    /// A shutdown which is never triggered.
+    pub fn never() -> Self {
+        Self::channel().1
+    }
+
+    /// Whether the shutdown has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.deadline().is_some()
+    }
+
+    /// Deadline of the shutdown, once triggered.
+    pub fn deadline(&self) -> Option<Instant> {
+        *self.receiver.borrow()
+    }
+
+    /// Wait for the shutdown to be triggered and return its deadline.
+    ///
+    /// Never returns when every [`ShutdownSender`] is dropped first.
+    pub async fn wait(&mut self) -> Instant {
+        let deadline = self.receiver.wait_for(Option::is_some).await.map(|d| *d);
+        match deadline {
+            Ok(Some(deadline)) => deadline,
+            _ => std::future::pending().await,
+        }
+    }
+}
+
+impl ShutdownSender {
+    /// Trigger the shutdown, with a deadline `grace` from now.
+    ///
+    /// Returns the deadline, which is the one of the first call when the
+    /// shutdown was already triggered.
+    pub fn send(&self, grace: Duration) -> Instant {
+        let deadline = Instant::now() + grace;
+        self.sender.send_if_modified(|current| {
+            if current.is_none() {
+                *current = Some(deadline);
+                true
+            } else {
+                false
+            }
+        });
+        self.sender.borrow().unwrap_or(deadline)
+    }
+}
+
+/// Failure reported by [`Runtime::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleError {
+    /// A service failed to start: the services after it were not started.
+    Start(String, String),
+    /// A service failed while running, or panicked.
+    Run(String, String),
+    /// Services were still running at the end of the grace period and were
+    /// cancelled.
+    Drain(Vec<String>),
+    /// A service failed to finalize.
+    Finalize(String, String),
+    /// The signal handlers cannot be installed.
+    Signal(String),
+}
+
+impl Display for LifecycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LifecycleError::Start(name, reason) => {
+                write!(f, "service {} failed to start: {}", name, reason)
+            }
+            LifecycleError::Run(name, reason) => write!(f, "service {} failed: {}", name, reason),
+            LifecycleError::Drain(names) => write!(
+                f,
+                "services still running after the grace period: {}",
+                names.join(", ")
+            ),
+            LifecycleError::Finalize(name, reason) => {
+                write!(f, "service {} failed to finalize: {}", name, reason)
+            }
+            LifecycleError::Signal(reason) => {
+                write!(f, "cannot listen for signals: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LifecycleError {}
+
+/// Handle requesting the shutdown of a [`Runtime`].
+#[derive(Debug, Clone)]
+pub struct RuntimeHandle {
+    stop: Arc<Notify>,
+}
+
+impl RuntimeHandle {
+    /// Request the shutdown. The request is kept if the runtime is not
+    /// running yet.
+    pub fn stop(&self) {
+        self.stop.notify_one();
+    }
+}
+
+type SharedService = Arc<Mutex<Box<dyn Service>>>;
+
+/// Runs the [`Service`]s of an application.
+///
+/// See the [module documentation](self) for the lifecycle.
+pub struct Runtime {
+    services: Vec<(String, SharedService)>,
+    grace_period: Duration,
+    signals: bool,
+    stop: Arc<Notify>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Runtime {
+    /// Create a runtime without services, listening for signals and with a
+    /// grace period of 30 seconds.
+    pub fn new() -> Self {
+        Self {
+            services: Vec::new(),
+            grace_period: Duration::from_secs(30),
+            signals: true,
+            stop: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Add a service, started after the ones already added.
+    pub fn service(mut self, service: impl Service + 'static) -> Self {
+        let name = service.name().to_string();
+        self.services
+            .push((name, Arc::new(Mutex::new(Box::new(service)))));
+        self
+    }
+
+    /// Time given to the services to stop once the shutdown is requested.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Whether `SIGINT` and `SIGTERM` (Ctrl-C on other platforms) request
+    /// the shutdown.
+    pub fn signals(mut self, signals: bool) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// Handle to request the shutdown from elsewhere.
+    pub fn handle(&self) -> RuntimeHandle {
+        RuntimeHandle {
+            stop: self.stop.clone(),
+        }
+    }
+
+    /// Start, run, stop and finalize the services.
+    ///
+    /// Every started service is finalized, even when another one failed.
+    /// All the failures are returned.
+    pub async fn run(self) -> Result<(), Vec<LifecycleError>> {
+        let mut errors = Vec::new();
+        let mut started = 0;
+        for (name, service) in &self.services {
+            if let Err(reason) = service.lock().await.start().await {
+                errors.push(LifecycleError::Start(name.clone(), reason));
+                break;
+            }
+            started += 1;
+        }
+
+        if errors.is_empty() {
+            self.serve(&mut errors).await;
+        }
+
+        for (name, service) in self.services[..started].iter().rev() {
+            if let Err(reason) = service.lock().await.finalize().await {
+                errors.push(LifecycleError::Finalize(name.clone(), reason));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Run the started services until the shutdown, then drain them.
+    async fn serve(&self, errors: &mut Vec<LifecycleError>) {
+        let (sender, shutdown) = Shutdown::channel();
+        let mut tasks = JoinSet::new();
+        let mut names = Vec::new();
+        for (name, service) in &self.services {
+            let service = service.clone();
+            let shutdown = shutdown.clone();
+            let task = tasks.spawn(async move { service.lock().await.run(shutdown).await });
+            names.push((task.id(), name.clone()));
+        }
+        let signal = interrupted(self.signals);
+        tokio::pin!(signal);
+        loop {
+            tokio::select! {
+                _ = self.stop.notified() => break,
+                result = &mut signal => {
+                    if let Err(error) = result {
+                        errors.push(LifecycleError::Signal(error.to_string()));
+                    }
+                    break;
+                }
+                joined = tasks.join_next_with_id() => match joined {
+                    Some(joined) => {
+                        if record(joined, &mut names, errors) {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+            }
+        }
+
+        let deadline = sender.send(self.grace_period);
+        loop {
+            match timeout_at(deadline, tasks.join_next_with_id()).await {
+                Ok(Some(joined)) => {
+                    record(joined, &mut names, errors);
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    let running = names.into_iter().map(|(_, name)| name).collect();
+                    errors.push(LifecycleError::Drain(running));
+                    tasks.shutdown().await;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Remove a finished service task from the `running` ones and record its
+/// failure, returning whether it failed.
+fn record(
+    joined: Result<(Id, Result<(), String>), JoinError>,
+    running: &mut Vec<(Id, String)>,
+    errors: &mut Vec<LifecycleError>,
+) -> bool {
+    let (id, reason) = match joined {
+        Ok((id, Ok(()))) => (id, None),
+        Ok((id, Err(reason))) => (id, Some(reason)),
+        Err(error) => (error.id(), Some("panicked".to_string())),
+    };
+    let position = running.iter().position(|(task, _)| *task == id);
+    let name = position.map(|i| running.remove(i).1).unwrap_or_default();
+    match reason {
+        Some(reason) => {
+            errors.push(LifecycleError::Run(name, reason));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Wait for `SIGINT` or `SIGTERM`, forever when `enabled` is false.
+async fn interrupted(enabled: bool) -> std::io::Result<()> {
+    if !enabled {
+        return std::future::pending().await;
+    }
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    type Events = Arc<std::sync::Mutex<Vec<String>>>;
+
+    /// Service recording its hooks into `events`.
+    struct Probe {
+        name: String,
+        events: Events,
+        fail_start: bool,
+        /// `Some(reason)` to fail right away, `None` to wait for the shutdown.
+        fail_run: Option<String>,
+        /// Ignore the shutdown.
+        stuck: bool,
+        /// Signalled once running.
+        ready: Option<oneshot::Sender<()>>,
+    }
+
+    impl Probe {
+        fn new(name: &str, events: &Events) -> Self {
+            Self {
+                name: name.to_string(),
+                events: events.clone(),
+                fail_start: false,
+                fail_run: None,
+                stuck: false,
+                ready: None,
+            }
+        }
+
+        fn log(&self, event: &str) {
+            let event = format!("{} {}", event, self.name);
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    impl Service for Probe {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn start(&mut self) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(async move {
+                self.log("start");
+                if self.fail_start {
+                    return Err("boom".to_string());
+                }
+                Ok(())
+            })
+        }
+
+        fn run(&mut self, mut shutdown: Shutdown) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(async move {
+                if let Some(reason) = self.fail_run.clone() {
+                    return Err(reason);
+                }
+                if let Some(ready) = self.ready.take() {
+                    let _ = ready.send(());
+                }
+                shutdown.wait().await;
+                if self.stuck {
+                    std::future::pending::<()>().await;
+                }
+                self.log("drain");
+                Ok(())
+            })
+        }
+
+        fn finalize(&mut self) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(async move {
+                self.log("finalize");
+                Ok(())
+            })
+        }
+    }
+
+    fn taken(events: &Events) -> Vec<String> {
+        std::mem::take(&mut *events.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle() {
+        let events = Events::default();
+        let (ready, running) = oneshot::channel();
+        let mut last = Probe::new("b", &events);
+        last.ready = Some(ready);
+        let runtime = Runtime::new()
+            .signals(false)
+            .service(Probe::new("a", &events))
+            .service(last);
+        let handle = runtime.handle();
+        let run = tokio::spawn(runtime.run());
+        running.await.unwrap();
+        assert_eq!(taken(&events), ["start a", "start b"]);
+        handle.stop();
+        assert_eq!(run.await.unwrap(), Ok(()));
+        let mut events = taken(&events);
+        events[..2].sort();
+        assert_eq!(events, ["drain a", "drain b", "finalize b", "finalize a"]);
+    }
+
+    #[tokio::test]
+    async fn test_failures() {
+        // A failed start skips the next services and finalizes the started ones.
+        let events = Events::default();
+        let mut failing = Probe::new("b", &events);
+        failing.fail_start = true;
+        let runtime = Runtime::new()
+            .signals(false)
+            .service(Probe::new("a", &events))
+            .service(failing)
+            .service(Probe::new("c", &events));
+        assert_eq!(
+            runtime.run().await,
+            Err(vec![LifecycleError::Start("b".into(), "boom".into())])
+        );
+        assert_eq!(taken(&events), ["start a", "start b", "finalize a"]);
+
+        // A failed run shuts the other services down.
+        let mut failing = Probe::new("b", &events);
+        failing.fail_run = Some("lost".to_string());
+        let runtime = Runtime::new()
+            .signals(false)
+            .service(Probe::new("a", &events))
+            .service(failing);
+        let errors = runtime.run().await.unwrap_err();
+        assert_eq!(errors, [LifecycleError::Run("b".into(), "lost".into())]);
+        assert_eq!(errors[0].to_string(), "service b failed: lost");
+        assert_eq!(
+            taken(&events),
+            ["start a", "start b", "drain a", "finalize b", "finalize a"]
+        );
+
+        // Services ignoring the shutdown are cancelled at the deadline.
+        let mut stuck = Probe::new("b", &events);
+        stuck.stuck = true;
+        let runtime = Runtime::new()
+            .signals(false)
+            .grace_period(Duration::from_millis(50))
+            .service(Probe::new("a", &events))
+            .service(stuck);
+        runtime.handle().stop();
+        let errors = runtime.run().await.unwrap_err();
+        assert_eq!(errors, [LifecycleError::Drain(vec!["b".into()])]);
+        assert_eq!(
+            errors[0].to_string(),
+            "services still running after the grace period: b"
+        );
+        assert_eq!(
+            taken(&events),
+            ["start a", "start b", "drain a", "finalize b", "finalize a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let (sender, mut shutdown) = Shutdown::channel();
+        let copy = shutdown.clone();
+        let first = sender.send(Duration::from_secs(1));
+        assert_eq!(sender.send(Duration::from_secs(5)), first);
+        assert_eq!(shutdown.wait().await, first);
+        assert_eq!(copy.deadline(), Some(first));
+
+        let mut never = Shutdown::never();
+        let waited = tokio::time::timeout(Duration::from_millis(20), never.wait()).await;
+        assert!(waited.is_err());
+        assert!(!never.is_triggered());
+    }
+}
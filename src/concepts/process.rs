@@ -2,7 +2,17 @@
 //!
 //! The [`Process`] trait models a task that can be initialized, executed and
 //! finalized. [`Application`] is a simple wrapper holding configuration and a
-//! boxed kernel implementing [`Process`].
+//! boxed kernel implementing [`Process`]. The configuration can be loaded
+//! from layered sources with a [`ConfigLoader`].
+//!
+//! These hooks are synchronous; long-running applications made of
+//! asynchronous services (servers, workers...) are driven by the
+//! [`Runtime`](crate::concepts::lifecycle::Runtime) of the
+//! [`lifecycle`](crate::concepts::lifecycle) module, with signal handling
+//! and graceful shutdown.
+
+use crate::concepts::config::{ConfigError, ConfigLoader};
+use serde::de::DeserializeOwned;
 
 /// Basic process executed by the [`Application`] runtime.
 ///
@@ -57,14 +67,59 @@ pub struct Application<C, E> {
 
 impl<C, E> Application<C, E> {
     /// Create a new application wrapping the given kernel and configuration.
-    pub fn new(config: C, kernel: &Kernel<E>) -> Self
-    where
-        Kernel<E>: Clone,
-    {
-        Self {
-            config,
-            kernel: Box::new(kernel.clone()),
-        }
+    ///
+    /// ```
+    /// use hermes::concepts::process::{Application, Process};
+    ///
+    /// struct Kernel;
+    /// impl Process<()> for Kernel {
+    ///     fn execute(&mut self) -> Result<(), String> { Ok(()) }
+    /// }
+    ///
+    /// let mut app = Application::new("config", Box::new(Kernel));
+    /// assert!(app.run().is_ok());
+    /// ```
+    pub fn new(config: C, kernel: Box<Kernel<E>>) -> Self {
+        Self { config, kernel }
+    }
+}
+
+impl<C: DeserializeOwned, E> Application<C, E> {
+    /// Create an application running `kernel` with the configuration
+    /// loaded by `loader`.
+    ///
+    /// ```
+    /// use hermes::concepts::config::ConfigLoader;
+    /// use hermes::concepts::process::{Application, Process};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     port: u16,
+    /// }
+    ///
+    /// struct Kernel;
+    /// impl Process<()> for Kernel {
+    ///     fn execute(&mut self) -> Result<(), String> { Ok(()) }
+    /// }
+    ///
+    /// let loader = ConfigLoader::new().overrides(["port=8080"]);
+    /// let mut app: Application<Config, String> =
+    ///     Application::configure(&loader, Box::new(Kernel)).unwrap();
+    /// assert_eq!(app.config.port, 8080);
+    /// assert!(app.run().is_ok());
+    ///
+    /// let error = Application::<Config, String>::configure(&ConfigLoader::new(), Box::new(Kernel));
+    /// assert_eq!(
+    ///     error.err().unwrap().to_string(),
+    ///     "invalid configuration: missing field `port`"
+    /// );
+    /// ```
+    pub fn configure(loader: &ConfigLoader, kernel: Box<Kernel<E>>) -> Result<Self, ConfigError> {
+        Ok(Self {
+            config: loader.load_as()?,
+            kernel,
+        })
     }
 }
 
@@ -2,9 +2,33 @@
 //!
 //! The [`Value`] enum can model typical JSON or YAML compatible values. It is
 //! accompanied by the [`ValueFormatter`] trait which allows serialising a
-//! `Value` into various textual formats.
+//! `Value` into various textual ([`json`], [`yaml`], [`toml`], [`form`]) or
+//! binary ([`msgpack`], [`cbor`]) formats. Values also implement serde's
+//! `Serialize` and `Deserialize`; [`to_value`] and [`from_value`] convert
+//! typed data.
+//!
+//! Values are built from Rust data with `From` and read with the typed
+//! accessors (`as_str`, `as_i64`...). The [`path`] module addresses nested
+//! values and merges trees, and [`schema`] validates them against JSON
+//! Schemas.
+//!
+//! ```
+//! use hermes::concepts::value::Value;
+//!
+//! let user = Value::from([
+//!     ("name", Value::from("Ann")),
+//!     ("age", Value::from(36u8)),
+//!     ("email", Value::from(None::<String>)),
+//!     ("roles", Value::from(vec!["admin", "dev"])),
+//! ]);
+//! assert_eq!(user.path("name").and_then(Value::as_str), Some("Ann"));
+//! assert_eq!(user.path("age").and_then(Value::as_i64), Some(36));
+//! assert!(user.path("email").is_some_and(Value::is_null));
+//! assert_eq!(user.path("roles").and_then(Value::as_array).map(Vec::len), Some(2));
+//! ```
 
 use crate::concepts::Dictionary;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, PartialEq)]
 /// Represents a generic JSON-like value used when parsing parameters.
@@ -18,10 +42,265 @@ pub enum Value {
     Dictionary(Dictionary<Value>),
 }
 
+impl Value {
+    /// Returns `true` for [`Value::Null`].
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// The boolean, if this is a [`Value::Bool`].
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is a [`Value::Int`].
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The number, if this is a [`Value::Number`] or a [`Value::Int`].
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    /// The string, if this is a [`Value::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The items, if this is a [`Value::Array`].
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The mutable items, if this is a [`Value::Array`].
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The entries, if this is a [`Value::Dictionary`].
+    pub fn as_dictionary(&self) -> Option<&Dictionary<Value>> {
+        match self {
+            Value::Dictionary(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// The mutable entries, if this is a [`Value::Dictionary`].
+    pub fn as_dictionary_mut(&mut self) -> Option<&mut Dictionary<Value>> {
+        match self {
+            Value::Dictionary(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+macro_rules! int_into_value {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Value {
+            fn from(i: $t) -> Self {
+                Value::Int(i.into())
+            }
+        }
+    )*};
+}
+
+int_into_value!(i8, i16, i32, i64, u8, u16, u32);
+
+/// Integers above `i64::MAX` become [`Value::Number`]s.
+macro_rules! wide_int_into_value {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Value {
+            fn from(i: $t) -> Self {
+                i64::try_from(i).map_or(Value::Number(i as f64), Value::Int)
+            }
+        }
+    )*};
+}
+
+wide_int_into_value!(isize, u64, usize);
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Value::String(c.to_string())
+    }
+}
+
+/// `None` becomes [`Value::Null`].
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(items: &[T]) -> Self {
+        Value::Array(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// Pairs become the entries of a dictionary, in order.
+impl<K: Into<String>, T: Into<Value>, const N: usize> From<[(K, T); N]> for Value {
+    fn from(entries: [(K, T); N]) -> Self {
+        Value::Dictionary(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: Into<Value>> From<Dictionary<T>> for Value {
+    fn from(entries: Dictionary<T>) -> Self {
+        Value::Dictionary(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(entries: HashMap<String, T>) -> Self {
+        Value::Dictionary(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl<T: Into<Value>> From<BTreeMap<String, T>> for Value {
+    fn from(entries: BTreeMap<String, T>) -> Self {
+        Value::Dictionary(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+/// Serialises [`Value`]s into a format and parses them back.
+///
+/// Textual formats implement [`format`](Self::format) and
+/// [`parse`](Self::parse); the byte methods use their UTF-8 encoding.
+/// Binary formats override [`format_bytes`](Self::format_bytes) and
+/// [`parse_bytes`](Self::parse_bytes) and represent their output as base64
+/// text.
 pub trait ValueFormatter {
     fn format(&self, value: Value) -> String;
     fn parse(&self, input: &str) -> Option<Value>;
+
+    /// Serialise `value` into bytes.
+    fn format_bytes(&self, value: Value) -> Vec<u8> {
+        self.format(value).into_bytes()
+    }
+
+    /// Parse a value from bytes.
+    fn parse_bytes(&self, input: &[u8]) -> Option<Value> {
+        std::str::from_utf8(input).ok().and_then(|s| self.parse(s))
+    }
 }
 
+pub mod cbor;
+pub mod convert;
+pub mod form;
 pub mod json;
+pub mod msgpack;
+pub mod path;
+pub mod schema;
+pub mod toml;
 pub mod yaml;
+
+pub use convert::{from_value, to_value, ConvertError};
+pub use path::PathError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessors_and_conversions() {
+        assert_eq!(Value::from(u64::MAX), Value::Number(u64::MAX as f64));
+        assert_eq!(Value::from(7usize), Value::Int(7));
+        assert_eq!(Value::from(1.5f32).as_f64(), Some(1.5));
+        assert_eq!(Value::from(2).as_f64(), Some(2.0));
+        assert_eq!(Value::from(2.0).as_i64(), None);
+        assert_eq!(Value::from('x').as_str(), Some("x"));
+        assert_eq!(Value::from(Some(true)).as_bool(), Some(true));
+        assert!(Value::from(None::<i32>).is_null());
+        assert_eq!(
+            Value::from(&[1, 2][..]),
+            Value::Array(vec![Value::Int(1), Value::Int(2)])
+        );
+
+        let mut sorted = BTreeMap::new();
+        sorted.insert("b".to_string(), 2);
+        sorted.insert("a".to_string(), 1);
+        let mut value = Value::from(sorted);
+        assert_eq!(
+            value.as_dictionary().unwrap().keys().collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        value
+            .as_dictionary_mut()
+            .unwrap()
+            .insert("c".to_string(), Value::from(vec![Value::Null]));
+        assert_eq!(value.as_array(), None);
+        let mut items = value
+            .as_dictionary_mut()
+            .unwrap()
+            .shift_remove("c")
+            .unwrap();
+        items.as_array_mut().unwrap().push(Value::from("end"));
+        assert_eq!(items.as_array().map(Vec::len), Some(2));
+        assert_eq!(
+            Value::from(HashMap::from([("k".to_string(), "v")])),
+            Value::from([("k", "v")])
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! CBOR formatter for [`Value`].
+//!
+//! [`CborFormatter`] writes RFC 8949 documents: dictionaries become maps
+//! with text keys, in insertion order. When parsing, tags are ignored and
+//! scalar map keys become strings; other keys are skipped. The
+//! textual methods of [`ValueFormatter`] represent the document as standard
+//! base64.
+//!
+//! ```
+//! use hermes::concepts::value::cbor::CborFormatter;
+//! use hermes::concepts::value::{Value, ValueFormatter};
+//!
+//! let value = Value::Array(vec![Value::Int(1), Value::String("a".into())]);
+//! let bytes = CborFormatter.format_bytes(value.clone());
+//! assert_eq!(bytes, [0x82, 0x01, 0x61, b'a']);
+//! assert_eq!(CborFormatter.parse_bytes(&bytes), Some(value));
+//! ```
+
+use crate::concepts::value::{Value, ValueFormatter};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ciborium::Value as CborValue;
+
+/// CBOR formatter.
+#[derive(Debug, Clone)]
+pub struct CborFormatter;
+
+impl ValueFormatter for CborFormatter {
+    fn format(&self, value: Value) -> String {
+        STANDARD.encode(self.format_bytes(value))
+    }
+
+    fn parse(&self, input: &str) -> Option<Value> {
+        let bytes = STANDARD.decode(input.trim()).ok()?;
+        self.parse_bytes(&bytes)
+    }
+
+    fn format_bytes(&self, value: Value) -> Vec<u8> {
+        let mut out = Vec::new();
+        // Writing to a vector cannot fail, `Value` gives the length of its
+        // arrays and dictionaries and the encoder has no nesting limit.
+        ciborium::into_writer(&value, &mut out).expect("values are representable in CBOR");
+        out
+    }
+
+    fn parse_bytes(&self, mut input: &[u8]) -> Option<Value> {
+        let value = ciborium::from_reader(&mut input).ok()?;
+        input.is_empty().then(|| from_cbor(value))
+    }
+}
+
+fn from_cbor(value: CborValue) -> Value {
+    match value {
+        CborValue::Null => Value::Null,
+        CborValue::Bool(b) => Value::Bool(b),
+        CborValue::Integer(i) => {
+            let i = i128::from(i);
+            i64::try_from(i).map_or(Value::Number(i as f64), Value::Int)
+        }
+        CborValue::Float(f) => Value::Number(f),
+        CborValue::Text(s) => Value::String(s),
+        CborValue::Bytes(bytes) => {
+            Value::Array(bytes.into_iter().map(|b| Value::Int(b.into())).collect())
+        }
+        CborValue::Array(items) => Value::Array(items.into_iter().map(from_cbor).collect()),
+        CborValue::Map(entries) => Value::Dictionary(
+            entries
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let key = match from_cbor(key) {
+                        Value::String(s) => s,
+                        Value::Int(i) => i.to_string(),
+                        Value::Number(n) => n.to_string(),
+                        Value::Bool(b) => b.to_string(),
+                        _ => return None,
+                    };
+                    Some((key, from_cbor(value)))
+                })
+                .collect(),
+        ),
+        CborValue::Tag(_, inner) => from_cbor(*inner),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Dictionary;
+
+    #[test]
+    fn test_round_trip() {
+        let f = CborFormatter;
+        let mut d = Dictionary::new();
+        d.insert("z".to_string(), Value::Number(-0.25));
+        d.insert("a".to_string(), Value::Null);
+        d.insert(
+            "list".to_string(),
+            Value::Array(vec![Value::Int(i64::MIN), Value::Bool(false)]),
+        );
+        let value = Value::Dictionary(d);
+
+        let bytes = f.format_bytes(value.clone());
+        assert_eq!(bytes[..3], [0xa3, 0x61, b'z']);
+        assert_eq!(f.parse_bytes(&bytes), Some(value.clone()));
+        assert_eq!(f.parse(&f.format(value.clone())), Some(value));
+        assert_eq!(f.parse_bytes(&[bytes.as_slice(), &[0xf6]].concat()), None);
+        assert_eq!(f.parse_bytes(&[0x82, 0x01]), None);
+        // Tag 1 (epoch time) around an integer.
+        assert_eq!(f.parse_bytes(&[0xc1, 0x18, 0x64]), Some(Value::Int(100)));
+    }
+}
@@ -0,0 +1,238 @@
+//! serde integration for [`Value`].
+//!
+//! [`Value`] implements [`Serialize`] and [`Deserialize`], so it can be read
+//! from or written to any serde format. [`to_value`] converts a serializable
+//! type into a [`Value`] and [`from_value`] converts a [`Value`] back into a
+//! typed structure.
+//!
+//! ```
+//! use hermes::concepts::value::{from_value, to_value, Value};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Cart {
+//!     owner: String,
+//!     items: Vec<u32>,
+//!     coupon: Option<String>,
+//! }
+//!
+//! let cart = Cart { owner: "ann".into(), items: vec![3, 7], coupon: None };
+//! let value = to_value(&cart).unwrap();
+//! let Value::Dictionary(fields) = &value else { unreachable!() };
+//! assert_eq!(fields.keys().collect::<Vec<_>>(), ["owner", "items", "coupon"]);
+//! assert_eq!(fields["items"], Value::Array(vec![Value::Int(3), Value::Int(7)]));
+//! assert_eq!(fields["coupon"], Value::Null);
+//!
+//! let back: Cart = from_value(value).unwrap();
+//! assert_eq!(back, cart);
+//! assert!(from_value::<Cart>(Value::Int(1)).is_err());
+//! ```
+
+use crate::concepts::value::Value;
+use crate::concepts::Dictionary;
+use serde::de::{self, DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+
+/// Failure to convert between a [`Value`] and a Rust type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertError(String);
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<serde_json::Error> for ConvertError {
+    fn from(e: serde_json::Error) -> Self {
+        ConvertError(e.to_string())
+    }
+}
+
+/// Convert `value` into a [`Value`].
+///
+/// Non-finite numbers become [`Value::Null`]; map keys must be strings or
+/// numbers.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ConvertError> {
+    Ok(Value::deserialize(serde_json::to_value(value)?)?)
+}
+
+/// Convert `value` into a `T`.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ConvertError> {
+    Ok(serde_json::from_value(serde_json::to_value(value)?)?)
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Int(i) => serializer.serialize_i64(*i),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Dictionary(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(i64::try_from(v).map_or(Value::Number(v as f64), Value::Int))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Array(
+            v.iter().map(|b| Value::Int((*b).into())).collect(),
+        ))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Dictionary::new();
+        while let Some(key) = map.next_key::<MapKey>()? {
+            entries.insert(key.0, map.next_value()?);
+        }
+        Ok(Value::Dictionary(entries))
+    }
+}
+
+/// Dictionary key, read from strings or scalars.
+struct MapKey(String);
+
+impl<'de> Deserialize<'de> for MapKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => Ok(MapKey(s)),
+            Value::Int(i) => Ok(MapKey(i.to_string())),
+            Value::Number(n) => Ok(MapKey(n.to_string())),
+            Value::Bool(b) => Ok(MapKey(b.to_string())),
+            _ => Err(de::Error::custom("dictionary keys must be scalars")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "kind", rename_all = "lowercase")]
+    enum Shape {
+        Circle { radius: f64 },
+        Square { side: u64 },
+    }
+
+    #[test]
+    fn test_typed_round_trip() {
+        let shapes = vec![Shape::Circle { radius: 1.5 }, Shape::Square { side: 2 }];
+        let value = to_value(&shapes).unwrap();
+        let Value::Array(items) = &value else {
+            unreachable!()
+        };
+        let Value::Dictionary(circle) = &items[0] else {
+            unreachable!()
+        };
+        assert_eq!(circle["kind"], Value::String("circle".into()));
+        assert_eq!(circle["radius"], Value::Number(1.5));
+        assert_eq!(from_value::<Vec<Shape>>(value).unwrap(), shapes);
+
+        let mut by_id = BTreeMap::new();
+        by_id.insert(2u32, "b");
+        by_id.insert(1u32, "a");
+        let value = to_value(&by_id).unwrap();
+        let Value::Dictionary(entries) = &value else {
+            unreachable!()
+        };
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["1", "2"]);
+        assert_eq!(from_value::<BTreeMap<u32, String>>(value).unwrap()[&2], "b");
+
+        assert_eq!(to_value(&u64::MAX).unwrap(), Value::Number(u64::MAX as f64));
+        assert_eq!(to_value(&f64::NAN).unwrap(), Value::Null);
+        assert!(from_value::<u8>(Value::Int(300)).is_err());
+    }
+
+    #[test]
+    fn test_serde_formats() {
+        let mut entries = Dictionary::new();
+        entries.insert("z".to_string(), Value::Int(-1));
+        entries.insert("a".to_string(), Value::Array(vec![Value::Null]));
+        let value = Value::Dictionary(entries);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"z":-1,"a":[null]}"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        let yaml = serde_yaml::to_string(&value).unwrap();
+        assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), value);
+    }
+}
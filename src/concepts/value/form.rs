@@ -0,0 +1,129 @@
+//! `application/x-www-form-urlencoded` formatter for [`Value`].
+//!
+//! [`FormFormatter`] parses bodies into the tree built by
+//! [`Query::to_value`]: `a[b]=1` nests dictionaries, `a[]=1` appends to
+//! arrays and repeated keys collect their values. Every leaf is a string.
+//! Bodies exceeding the bounds of [`Query::try_to_value`] do not parse.
+//!
+//! Formatting flattens a dictionary into bracketed keys, in insertion order.
+//! Arrays of scalars use `a[]`, other arrays use indexes (`a[0][b]`), which
+//! parse back as dictionaries. Null is written as an empty value, empty
+//! arrays and dictionaries are omitted and values other than dictionaries
+//! format as an empty string.
+//!
+//! ```
+//! use hermes::concepts::value::form::FormFormatter;
+//! use hermes::concepts::value::{Value, ValueFormatter};
+//!
+//! let value = FormFormatter.parse("user[name]=Ann+Lee&tags[]=a&tags[]=b").unwrap();
+//! let Value::Dictionary(root) = &value else { unreachable!() };
+//! assert_eq!(
+//!     root["tags"],
+//!     Value::Array(vec![Value::String("a".into()), Value::String("b".into())])
+//! );
+//! assert_eq!(
+//!     FormFormatter.format(value),
+//!     "user%5Bname%5D=Ann+Lee&tags%5B%5D=a&tags%5B%5D=b"
+//! );
+//! ```
+
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::http::{form_urldecode, form_urlencode, Query};
+
+/// Form body formatter.
+#[derive(Debug, Clone)]
+pub struct FormFormatter;
+
+impl ValueFormatter for FormFormatter {
+    fn format(&self, value: Value) -> String {
+        let Value::Dictionary(entries) = value else {
+            return String::new();
+        };
+        let mut pairs = Vec::new();
+        for (key, value) in entries {
+            flatten(key, value, &mut pairs);
+        }
+        form_urlencode(pairs.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+
+    fn parse(&self, input: &str) -> Option<Value> {
+        let mut query = Query::new();
+        for (key, value) in form_urldecode(input) {
+            query.add(&key, &value);
+        }
+        query.try_to_value().ok()
+    }
+}
+
+/// Push the pairs of `value` under the key `prefix`.
+fn flatten(prefix: String, value: Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => pairs.push((prefix, String::new())),
+        Value::Bool(b) => pairs.push((prefix, b.to_string())),
+        Value::Int(i) => pairs.push((prefix, i.to_string())),
+        Value::Number(f) => pairs.push((prefix, f.to_string())),
+        Value::String(s) => pairs.push((prefix, s)),
+        Value::Array(items) => {
+            let nested = items
+                .iter()
+                .any(|item| matches!(item, Value::Array(_) | Value::Dictionary(_)));
+            for (i, item) in items.into_iter().enumerate() {
+                let index = if nested { i.to_string() } else { String::new() };
+                flatten(format!("{}[{}]", prefix, index), item, pairs);
+            }
+        }
+        Value::Dictionary(entries) => {
+            for (key, value) in entries {
+                flatten(format!("{}[{}]", prefix, key), value, pairs);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Dictionary;
+
+    #[test]
+    fn test_format_and_parse() {
+        let f = FormFormatter;
+        let mut filter = Dictionary::new();
+        filter.insert("status".to_string(), Value::String("open & new".into()));
+        filter.insert("min".to_string(), Value::Number(1.5));
+        let mut item = Dictionary::new();
+        item.insert("id".to_string(), Value::Int(3));
+        let mut root = Dictionary::new();
+        root.insert("q".to_string(), Value::Null);
+        root.insert("filter".to_string(), Value::Dictionary(filter));
+        root.insert(
+            "items".to_string(),
+            Value::Array(vec![Value::Dictionary(item)]),
+        );
+        root.insert("empty".to_string(), Value::Array(vec![]));
+        root.insert("on".to_string(), Value::Bool(true));
+
+        let body = f.format(Value::Dictionary(root));
+        assert_eq!(
+            body,
+            "q=&filter%5Bstatus%5D=open+%26+new&filter%5Bmin%5D=1.5&items%5B0%5D%5Bid%5D=3&on=true"
+        );
+        let Some(Value::Dictionary(parsed)) = f.parse(&body) else {
+            unreachable!()
+        };
+        assert_eq!(parsed["q"], Value::String(String::new()));
+        let Value::Dictionary(items) = &parsed["items"] else {
+            unreachable!()
+        };
+        let Value::Dictionary(first) = &items["0"] else {
+            unreachable!()
+        };
+        assert_eq!(first["id"], Value::String("3".into()));
+        assert_eq!(
+            parsed.keys().collect::<Vec<_>>(),
+            ["q", "filter", "items", "on"]
+        );
+        assert_eq!(f.format(Value::String("x".into())), "");
+        assert_eq!(f.parse(""), Some(Value::Dictionary(Dictionary::new())));
+    }
+}
@@ -1,80 +1,179 @@
 //! JSON formatter for [`Value`].
 //!
-//! This module defines [`JsonFormatter`], a basic formatter converting a
-//! [`Value`] into its JSON string representation.
+//! This module defines [`JsonFormatter`], converting a [`Value`] into compact
+//! RFC 8259 JSON, and [`PrettyJsonFormatter`], producing indented JSON.
+//!
+//! Both formatters escape control characters in strings, write non-finite
+//! numbers as `null` and write dictionary keys in insertion order, which
+//! parsing preserves. Floating point numbers keep a fractional part or
+//! an exponent and parse back as [`Value::Number`].
+//!
+//! ```
+//! use hermes::concepts::value::json::{JsonFormatter, PrettyJsonFormatter};
+//! use hermes::concepts::value::{Value, ValueFormatter};
+//!
+//! let value = Value::Dictionary(
+//!     [
+//!         ("b".to_string(), Value::Array(vec![])),
+//!         ("a".to_string(), Value::String("line\nbreak".into())),
+//!         ("c".to_string(), Value::Number(f64::NAN)),
+//!     ]
+//!     .into(),
+//! );
+//! assert_eq!(
+//!     JsonFormatter.format(value.clone()),
+//!     r#"{"b": [],"a": "line\nbreak","c": null}"#
+//! );
+//! assert_eq!(
+//!     PrettyJsonFormatter::default().format(value),
+//!     "{\n  \"b\": [],\n  \"a\": \"line\\nbreak\",\n  \"c\": null\n}"
+//! );
+//! ```
 
 use crate::concepts::value::{Value, ValueFormatter};
-use crate::concepts::Dictionary;
-use serde_json::Value as JsonValue;
 
+/// Compact JSON formatter.
 #[derive(Clone)]
 pub struct JsonFormatter;
 
 impl ValueFormatter for JsonFormatter {
     fn format(&self, value: Value) -> String {
-        match value {
-            Value::Null => "null".to_string(),
-            Value::Bool(b) => if b { "true" } else { "false" }.to_string(),
-            Value::Int(i) => i.to_string(),
-            Value::Number(f) => f.to_string(),
-            Value::String(s) => format!("\"{}\"", s.replace("\\", "\\\\").replace("\"", "\\\"")),
-            Value::Array(a) => {
-                let mut s = "[".to_string();
-                for v in a {
-                    s.push_str(&self.format(v));
-                    s.push(',');
-                }
-                s.pop();
-                s.push(']');
-                s
-            }
-            Value::Dictionary(d) => {
-                let mut s = "{".to_string();
-                for (k, v) in d {
-                    s.push_str(&format!("\"{}\": ", k));
-                    s.push_str(&self.format(v));
-                    s.push(',');
-                }
-                s.pop();
-                s.push('}');
-                s
-            }
-        }
+        let mut out = String::new();
+        write_value(&mut out, &value, None, 0);
+        out
+    }
+
+    fn parse(&self, input: &str) -> Option<Value> {
+        serde_json::from_str::<Value>(input).ok()
+    }
+}
+
+/// Indented JSON formatter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyJsonFormatter {
+    /// Number of spaces per nesting level.
+    pub indent: usize,
+}
+
+impl PrettyJsonFormatter {
+    /// Create a formatter indenting with `indent` spaces.
+    pub fn new(indent: usize) -> Self {
+        Self { indent }
+    }
+}
+
+impl Default for PrettyJsonFormatter {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl ValueFormatter for PrettyJsonFormatter {
+    fn format(&self, value: Value) -> String {
+        let mut out = String::new();
+        write_value(&mut out, &value, Some(self.indent), 0);
+        out
     }
 
     fn parse(&self, input: &str) -> Option<Value> {
-        serde_json::from_str::<JsonValue>(input).ok().map(from_json)
+        JsonFormatter.parse(input)
+    }
+}
+
+/// Write `value` at nesting `depth`, indenting with `indent` spaces if set.
+fn write_value(out: &mut String, value: &Value, indent: Option<usize>, depth: usize) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Int(i) => out.push_str(&i.to_string()),
+        Value::Number(f) => write_number(out, *f),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            write_container(out, ('[', ']'), items.iter(), indent, depth, |out, item| {
+                write_value(out, item, indent, depth + 1)
+            })
+        }
+        Value::Dictionary(entries) => write_container(
+            out,
+            ('{', '}'),
+            entries.iter(),
+            indent,
+            depth,
+            |out, (key, value)| {
+                write_string(out, key);
+                out.push_str(": ");
+                write_value(out, value, indent, depth + 1);
+            },
+        ),
     }
 }
 
-fn from_json(v: JsonValue) -> Value {
-    match v {
-        JsonValue::Null => Value::Null,
-        JsonValue::Bool(b) => Value::Bool(b),
-        JsonValue::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Value::Int(i)
-            } else {
-                Value::Number(n.as_f64().unwrap())
-            }
+/// Write the `items` of an array or a dictionary between `delimiters`.
+fn write_container<T>(
+    out: &mut String,
+    delimiters: (char, char),
+    items: impl Iterator<Item = T>,
+    indent: Option<usize>,
+    depth: usize,
+    mut write_item: impl FnMut(&mut String, T),
+) {
+    out.push(delimiters.0);
+    let mut empty = true;
+    for item in items {
+        if !empty {
+            out.push(',');
         }
-        JsonValue::String(s) => Value::String(s),
-        JsonValue::Array(a) => Value::Array(a.into_iter().map(from_json).collect()),
-        JsonValue::Object(o) => {
-            let mut d = Dictionary::new();
-            for (k, v) in o {
-                d.insert(k, from_json(v));
-            }
-            Value::Dictionary(d)
+        if let Some(indent) = indent {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent * (depth + 1)));
         }
+        write_item(out, item);
+        empty = false;
+    }
+    if let (Some(indent), false) = (indent, empty) {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent * depth));
+    }
+    out.push(delimiters.1);
+}
+
+/// Write a number, or `null` if it is not finite.
+fn write_number(out: &mut String, f: f64) {
+    if f.is_finite() {
+        // `Debug` keeps a fractional part or an exponent (`1.0`, `1e300`).
+        out.push_str(&format!("{:?}", f));
+    } else {
+        out.push_str("null");
     }
 }
 
+/// Write a quoted string, escaping quotes, backslashes and control
+/// characters.
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::concepts::Dictionary;
+    use proptest::prelude::*;
     use serde_json::json;
+    use serde_json::Value as JsonValue;
 
     #[test]
     fn test_basic_types() {
@@ -118,4 +217,72 @@ mod tests {
         let expected = json!({"outer": {"inner": {"a": 1, "b": 2}, "flag": true}, "count": 10});
         assert_eq!(parsed, expected);
     }
+
+    #[test]
+    fn test_escapes_and_edge_cases() {
+        let f = JsonFormatter;
+        assert_eq!(f.format(Value::Array(vec![])), "[]");
+        assert_eq!(f.format(Value::Dictionary(Dictionary::new())), "{}");
+        assert_eq!(
+            f.format(Value::String("q\"b\\n\n\u{1}\u{7f}é".into())),
+            "\"q\\\"b\\\\n\\n\\u0001\u{7f}é\""
+        );
+        assert_eq!(f.format(Value::Number(f64::INFINITY)), "null");
+        assert_eq!(f.format(Value::Number(f64::NEG_INFINITY)), "null");
+        assert_eq!(f.format(Value::Number(2.0)), "2.0");
+        assert_eq!(f.format(Value::Number(-0.5)), "-0.5");
+        assert_eq!(f.parse("2.0"), Some(Value::Number(2.0)));
+        assert_eq!(
+            f.parse(&f.format(Value::Number(1e300))),
+            Some(Value::Number(1e300))
+        );
+
+        let mut d = Dictionary::new();
+        d.insert("z\n".to_string(), Value::Array(vec![Value::Array(vec![])]));
+        d.insert("a".to_string(), Value::Dictionary(Dictionary::new()));
+        assert_eq!(
+            f.format(Value::Dictionary(d.clone())),
+            r#"{"z\n": [[]],"a": {}}"#
+        );
+        assert_eq!(
+            PrettyJsonFormatter::new(4).format(Value::Dictionary(d)),
+            "{\n    \"z\\n\": [\n        []\n    ],\n    \"a\": {}\n}"
+        );
+    }
+
+    fn value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(Value::Int),
+            any::<f64>()
+                .prop_filter("finite", |f| f.is_finite())
+                .prop_map(Value::Number),
+            any::<String>().prop_map(Value::String),
+        ];
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                prop::collection::vec((any::<String>(), inner), 0..6)
+                    .prop_map(|entries| Value::Dictionary(entries.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_compact_round_trip(value in value()) {
+            let json = JsonFormatter.format(value.clone());
+            prop_assert!(serde_json::from_str::<JsonValue>(&json).is_ok());
+            prop_assert_eq!(JsonFormatter.parse(&json), Some(value));
+        }
+
+        #[test]
+        fn prop_pretty_round_trip(value in value(), indent in 0usize..5) {
+            let formatter = PrettyJsonFormatter::new(indent);
+            let json = formatter.format(value.clone());
+            prop_assert_eq!(formatter.parse(&json), Some(value.clone()));
+            prop_assert_eq!(formatter.format(value), json);
+        }
+    }
 }
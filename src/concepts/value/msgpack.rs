@@ -0,0 +1,83 @@
+//! MessagePack formatter for [`Value`].
+//!
+//! [`MsgPackFormatter`] writes compact binary documents: dictionaries become
+//! maps with string keys, in insertion order. The textual methods of
+//! [`ValueFormatter`] represent the document as standard base64.
+//!
+//! ```
+//! use hermes::concepts::value::msgpack::MsgPackFormatter;
+//! use hermes::concepts::value::{Value, ValueFormatter};
+//!
+//! let value = Value::Dictionary([("n".to_string(), Value::Int(1))].into());
+//! let bytes = MsgPackFormatter.format_bytes(value.clone());
+//! assert_eq!(bytes, [0x81, 0xa1, b'n', 0x01]);
+//! assert_eq!(MsgPackFormatter.parse_bytes(&bytes), Some(value.clone()));
+//! assert_eq!(MsgPackFormatter.format(value), "gaFuAQ==");
+//! ```
+
+use crate::concepts::value::{Value, ValueFormatter};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// MessagePack formatter.
+#[derive(Debug, Clone)]
+pub struct MsgPackFormatter;
+
+impl ValueFormatter for MsgPackFormatter {
+    fn format(&self, value: Value) -> String {
+        STANDARD.encode(self.format_bytes(value))
+    }
+
+    fn parse(&self, input: &str) -> Option<Value> {
+        let bytes = STANDARD.decode(input.trim()).ok()?;
+        self.parse_bytes(&bytes)
+    }
+
+    fn format_bytes(&self, value: Value) -> Vec<u8> {
+        // Writing to a vector cannot fail and `Value` gives the length of its
+        // arrays and dictionaries, the encoder has no other error to report.
+        rmp_serde::to_vec(&value).expect("values are representable in MessagePack")
+    }
+
+    fn parse_bytes(&self, input: &[u8]) -> Option<Value> {
+        let mut deserializer = rmp_serde::Deserializer::new(input);
+        let value = serde::Deserialize::deserialize(&mut deserializer).ok()?;
+        deserializer.get_ref().is_empty().then_some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Dictionary;
+
+    #[test]
+    fn test_round_trip() {
+        let f = MsgPackFormatter;
+        let mut d = Dictionary::new();
+        d.insert("z".to_string(), Value::Number(1.5));
+        d.insert("a".to_string(), Value::Null);
+        d.insert(
+            "list".to_string(),
+            Value::Array(vec![
+                Value::Int(-3),
+                Value::Int(i64::MAX),
+                Value::Bool(true),
+            ]),
+        );
+        d.insert("text".to_string(), Value::String("é\n".to_string()));
+        let value = Value::Dictionary(d);
+
+        let bytes = f.format_bytes(value.clone());
+        assert_eq!(f.parse_bytes(&bytes), Some(value.clone()));
+        assert_eq!(f.parse(&f.format(value.clone())), Some(value));
+        assert_eq!(f.parse_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(f.parse_bytes(&[bytes.as_slice(), &[0xc0]].concat()), None);
+        assert_eq!(f.parse("not base64!"), None);
+        // `bin` data is read as an array of bytes.
+        assert_eq!(
+            f.parse_bytes(&[0xc4, 0x02, 0x07, 0xff]),
+            Some(Value::Array(vec![Value::Int(7), Value::Int(255)]))
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Path queries and merging of [`Value`] trees.
+//!
+//! Nested values are addressed with JSON Pointers (RFC 6901, `/a/0/b`) or
+//! dotted paths (`a.0.b`). Segments select dictionary entries by key and
+//! array items by index. Dotted paths are shorter but cannot address keys
+//! containing a dot; pointers escape `~` and `/` as `~0` and `~1`.
+//!
+//! Setting a path creates the missing dictionaries on the way, and the
+//! index of an array may be its length (or `-` in pointers) to append an
+//! item. A path that cannot be reached leaves the value unchanged. [`Value::merge`] deep merges dictionaries, which is how layered
+//! configurations are combined.
+//!
+//! ```
+//! use hermes::concepts::value::Value;
+//!
+//! let mut config = Value::from([("server", Value::from([("port", 80)]))]);
+//! assert_eq!(config.pointer("/server/port"), Some(&Value::Int(80)));
+//! assert_eq!(config.path("server.port").and_then(Value::as_i64), Some(80));
+//!
+//! config.set_path("server.hosts", vec!["a.example"]).unwrap();
+//! config.set_pointer("/server/hosts/-", "b.example").unwrap();
+//! assert_eq!(config.path("server.hosts.1"), Some(&Value::from("b.example")));
+//!
+//! config.merge(Value::from([("server", Value::from([("port", 8080)]))]));
+//! assert_eq!(config.pointer("/server/port"), Some(&Value::Int(8080)));
+//! assert!(config.path("server.hosts").is_some());
+//!
+//! assert_eq!(config.remove_path("server.port"), Some(Value::Int(8080)));
+//! assert_eq!(config.path("server.port"), None);
+//! ```
+
+use crate::concepts::value::Value;
+use crate::concepts::Dictionary;
+use std::fmt::{self, Display, Formatter};
+
+/// Failure to set a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    /// The pointer is not empty and does not start with `/`, or has an
+    /// invalid `~` escape.
+    Invalid(String),
+    /// A segment goes through a scalar or past the end of an array.
+    Unreachable(String),
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Invalid(path) => write!(f, "invalid JSON pointer \"{}\"", path),
+            PathError::Unreachable(path) => write!(f, "path \"{}\" cannot be reached", path),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Split a JSON Pointer into unescaped segments.
+fn pointer_segments(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|segment| {
+            let mut out = String::with_capacity(segment.len());
+            let mut chars = segment.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    out.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => out.push('~'),
+                    Some('1') => out.push('/'),
+                    _ => return None,
+                }
+            }
+            Some(out)
+        })
+        .collect()
+}
+
+/// Split a dotted path into segments, the empty path being the root.
+fn dotted_segments(path: &str) -> Vec<String> {
+    match path {
+        "" => Vec::new(),
+        path => path.split('.').map(str::to_string).collect(),
+    }
+}
+
+/// Index of `segment` in an array of `len` items, `len` meaning the end.
+fn index(segment: &str, len: usize) -> Option<usize> {
+    if segment == "-" {
+        return Some(len);
+    }
+    let valid = !segment.is_empty()
+        && segment.bytes().all(|b| b.is_ascii_digit())
+        && (segment == "0" || !segment.starts_with('0'));
+    valid.then(|| segment.parse().ok()).flatten()
+}
+
+impl Value {
+    /// Value at the JSON `pointer`, the empty pointer being the value itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        self.walk(&pointer_segments(pointer)?)
+    }
+
+    /// Mutable value at the JSON `pointer`.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        self.walk_mut(&pointer_segments(pointer)?)
+    }
+
+    /// Set the value at the JSON `pointer`, returning the previous one.
+    pub fn set_pointer(
+        &mut self,
+        pointer: &str,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, PathError> {
+        let segments =
+            pointer_segments(pointer).ok_or_else(|| PathError::Invalid(pointer.to_string()))?;
+        self.set_segments(&segments, value.into())
+            .ok_or_else(|| PathError::Unreachable(pointer.to_string()))
+    }
+
+    /// Remove the value at the JSON `pointer`.
+    pub fn remove_pointer(&mut self, pointer: &str) -> Option<Value> {
+        self.remove_segments(&pointer_segments(pointer)?)
+    }
+
+    /// Value at the dotted `path`, the empty path being the value itself.
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        self.walk(&dotted_segments(path))
+    }
+
+    /// Mutable value at the dotted `path`.
+    pub fn path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        self.walk_mut(&dotted_segments(path))
+    }
+
+    /// Set the value at the dotted `path`, returning the previous one.
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, PathError> {
+        self.set_segments(&dotted_segments(path), value.into())
+            .ok_or_else(|| PathError::Unreachable(path.to_string()))
+    }
+
+    /// Remove the value at the dotted `path`.
+    pub fn remove_path(&mut self, path: &str) -> Option<Value> {
+        self.remove_segments(&dotted_segments(path))
+    }
+
+    /// Deep merge `other` into this value.
+    ///
+    /// Entries of two dictionaries are merged recursively, new keys being
+    /// appended. In any other case, `other` replaces this value: arrays are
+    /// not concatenated and a null `other` replaces the value with null.
+    pub fn merge(&mut self, other: Value) {
+        match (self, other) {
+            (Value::Dictionary(entries), Value::Dictionary(other)) => {
+                for (key, value) in other {
+                    match entries.get_mut(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => {
+                            entries.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (this, other) => *this = other,
+        }
+    }
+
+    fn child(&self, segment: &str) -> Option<&Value> {
+        match self {
+            Value::Dictionary(entries) => entries.get(segment),
+            Value::Array(items) => items.get(index(segment, items.len())?),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, segment: &str) -> Option<&mut Value> {
+        match self {
+            Value::Dictionary(entries) => entries.get_mut(segment),
+            Value::Array(items) => {
+                let i = index(segment, items.len())?;
+                items.get_mut(i)
+            }
+            _ => None,
+        }
+    }
+
+    fn walk(&self, segments: &[String]) -> Option<&Value> {
+        segments
+            .iter()
+            .try_fold(self, |value, segment| value.child(segment))
+    }
+
+    fn walk_mut(&mut self, segments: &[String]) -> Option<&mut Value> {
+        segments
+            .iter()
+            .try_fold(self, |value, segment| value.child_mut(segment))
+    }
+
+    /// Check that `set_segments` can reach `segments`, before it creates
+    /// anything on the way.
+    fn reachable(&self, segments: &[String]) -> bool {
+        let Some((last, parents)) = segments.split_last() else {
+            return true;
+        };
+        let mut node = self;
+        for segment in parents {
+            node = match node {
+                // The rest of the path is created.
+                Value::Dictionary(entries) => match entries.get(segment) {
+                    Some(child) => child,
+                    None => return true,
+                },
+                Value::Array(items) => match index(segment, items.len()) {
+                    Some(i) if i < items.len() => &items[i],
+                    Some(i) => return i == items.len(),
+                    None => return false,
+                },
+                _ => return false,
+            };
+        }
+        match node {
+            Value::Dictionary(_) => true,
+            Value::Array(items) => index(last, items.len()).is_some_and(|i| i <= items.len()),
+            _ => false,
+        }
+    }
+
+    /// Set the value at `segments`, or `None` if it cannot be reached.
+    fn set_segments(&mut self, segments: &[String], value: Value) -> Option<Option<Value>> {
+        if !self.reachable(segments) {
+            return None;
+        }
+        let Some((last, parents)) = segments.split_last() else {
+            return Some(Some(std::mem::replace(self, value)));
+        };
+        let mut node = self;
+        for segment in parents {
+            node = match node {
+                Value::Dictionary(entries) => entries
+                    .entry(segment.clone())
+                    .or_insert_with(|| Value::Dictionary(Dictionary::new())),
+                Value::Array(items) => {
+                    let i = index(segment, items.len())?;
+                    if i == items.len() {
+                        items.push(Value::Dictionary(Dictionary::new()));
+                    }
+                    items.get_mut(i)?
+                }
+                _ => return None,
+            };
+        }
+        match node {
+            Value::Dictionary(entries) => Some(entries.insert(last.clone(), value)),
+            Value::Array(items) => {
+                let i = index(last, items.len())?;
+                if i == items.len() {
+                    items.push(value);
+                    Some(None)
+                } else {
+                    Some(Some(std::mem::replace(items.get_mut(i)?, value)))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn remove_segments(&mut self, segments: &[String]) -> Option<Value> {
+        let (last, parents) = segments.split_last()?;
+        match self.walk_mut(parents)? {
+            Value::Dictionary(entries) => entries.shift_remove(last),
+            Value::Array(items) => {
+                let i = index(last, items.len()).filter(|i| *i < items.len())?;
+                Some(items.remove(i))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        Value::from([
+            ("a/b", Value::Int(1)),
+            ("m~n", Value::Int(2)),
+            (
+                "list",
+                Value::from(vec![Value::from([("x", true)]), Value::Null]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_pointer() {
+        let mut value = sample();
+        assert_eq!(value.pointer(""), Some(&value.clone()));
+        assert_eq!(value.pointer("/a~1b"), Some(&Value::Int(1)));
+        assert_eq!(value.pointer("/m~0n"), Some(&Value::Int(2)));
+        assert_eq!(value.pointer("/list/0/x"), Some(&Value::Bool(true)));
+        assert_eq!(value.pointer("/list/01"), None);
+        assert_eq!(value.pointer("/list/-"), None);
+        assert_eq!(value.pointer("/list/2"), None);
+        assert_eq!(value.pointer("list"), None);
+        assert_eq!(value.pointer("/m~2n"), None);
+
+        *value.pointer_mut("/list/1").unwrap() = Value::Int(5);
+        assert_eq!(value.pointer("/list/1"), Some(&Value::Int(5)));
+        assert_eq!(value.set_pointer("/list/-", 6), Ok(None));
+        assert_eq!(value.set_pointer("/list/2", 7), Ok(Some(Value::Int(6))));
+        assert_eq!(
+            value.set_pointer("/list/9", 0),
+            Err(PathError::Unreachable("/list/9".to_string()))
+        );
+        assert_eq!(
+            value.set_pointer("/a~1b/c", 0),
+            Err(PathError::Unreachable("/a~1b/c".to_string()))
+        );
+        assert_eq!(
+            value.set_pointer("x", 0),
+            Err(PathError::Invalid("x".to_string()))
+        );
+        assert_eq!(value.remove_pointer("/list/0/x"), Some(Value::Bool(true)));
+        assert_eq!(value.remove_pointer("/list/3"), None);
+        assert_eq!(value.remove_pointer("/list/2"), Some(Value::Int(7)));
+        assert_eq!(value.remove_pointer(""), None);
+    }
+
+    #[test]
+    fn test_dotted_path() {
+        let mut value = Value::Null;
+        assert_eq!(
+            value.set_path("db.primary.host", "localhost"),
+            Err(PathError::Unreachable("db.primary.host".to_string()))
+        );
+        assert_eq!(
+            value.set_path("", Value::Dictionary(Dictionary::new())),
+            Ok(Some(Value::Null))
+        );
+        value.set_path("db.primary.host", "localhost").unwrap();
+        value.set_path("db.primary.host.port", 1).unwrap_err();
+        value.set_path("db.replicas", Vec::<Value>::new()).unwrap();
+        value.set_path("db.replicas.0.host", "r1").unwrap();
+        assert_eq!(
+            value.path("db.primary.host").and_then(Value::as_str),
+            Some("localhost")
+        );
+        assert_eq!(
+            value.path("db.replicas.0.host").and_then(Value::as_str),
+            Some("r1")
+        );
+        assert_eq!(value.path("db.missing"), None);
+        *value.path_mut("db.primary.host").unwrap() = Value::from("db1");
+        assert_eq!(
+            value.remove_path("db.primary"),
+            Some(Value::from([("host", "db1")]))
+        );
+        let Value::Dictionary(db) = value.path("db").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(db.keys().collect::<Vec<_>>(), ["replicas"]);
+    }
+
+    #[test]
+    fn test_failed_set_leaves_value_unchanged() {
+        let original = Value::from([
+            ("name", Value::from("app")),
+            ("list", Value::from(vec![Value::from([("x", 1)])])),
+        ]);
+        let mut value = original.clone();
+        for path in ["name.a.b", "list.2.a", "list.x.a", "list.0.x.a", "list.3"] {
+            assert_eq!(
+                value.set_path(path, 1),
+                Err(PathError::Unreachable(path.to_string()))
+            );
+            assert_eq!(value, original, "{}", path);
+        }
+        assert_eq!(value.set_pointer("/list/-/y/z", 2), Ok(None));
+        assert_eq!(value.pointer("/list/1/y/z"), Some(&Value::Int(2)));
+        assert_eq!(value.set_path("a.b", 3), Ok(None));
+        assert_eq!(value.path("a"), Some(&Value::from([("b", 3)])));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = Value::from([
+            ("name", Value::from("app")),
+            (
+                "server",
+                Value::from([("host", Value::from("0.0.0.0")), ("port", Value::from(80))]),
+            ),
+            ("tags", Value::from(vec!["a", "b"])),
+        ]);
+        base.merge(Value::from([
+            (
+                "server",
+                Value::from([("port", Value::from(8080)), ("tls", Value::from(true))]),
+            ),
+            ("tags", Value::from(vec!["c"])),
+            ("debug", Value::from(true)),
+        ]));
+        assert_eq!(
+            base,
+            Value::from([
+                ("name", Value::from("app")),
+                (
+                    "server",
+                    Value::from([
+                        ("host", Value::from("0.0.0.0")),
+                        ("port", Value::from(8080)),
+                        ("tls", Value::from(true)),
+                    ])
+                ),
+                ("tags", Value::from(vec!["c"])),
+                ("debug", Value::from(true)),
+            ])
+        );
+        base.merge(Value::Int(1));
+        assert_eq!(base, Value::Int(1));
+    }
+}
@@ -0,0 +1,956 @@
+//! JSON Schema validation of [`Value`]s.
+//!
+//! [`Schema`] checks values against a JSON Schema written for draft
+//! 2020-12, loaded from a [`Value`] or parsed with any [`ValueFormatter`]
+//! (typically JSON or YAML). Validation reports every [`Violation`], located
+//! by JSON Pointers in the instance and in the schema.
+//!
+//! The supported subset covers:
+//!
+//! - `type`, `enum` and `const`;
+//! - `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum` and
+//!   `multipleOf`;
+//! - `minLength`, `maxLength`, `pattern` and `format` (`email`, `uri`,
+//!   `uuid`, `date`, `time`, `date-time`, `ipv4` and `ipv6`; other formats
+//!   are annotations);
+//! - `items`, `prefixItems`, `minItems`, `maxItems`, `uniqueItems`,
+//!   `contains`, `minContains` and `maxContains`;
+//! - `properties`, `patternProperties`, `additionalProperties`, `required`,
+//!   `dependentRequired`, `propertyNames`, `minProperties` and
+//!   `maxProperties`;
+//! - `allOf`, `anyOf`, `oneOf`, `not` and `if`/`then`/`else`;
+//! - `$ref` to the schema itself (`#`) or its parts (`#/$defs/name`).
+//!
+//! Other keywords are ignored. Patterns are not anchored, as in JSON Schema.
+//!
+//! ```
+//! use hermes::concepts::value::json::JsonFormatter;
+//! use hermes::concepts::value::schema::Schema;
+//! use hermes::concepts::value::ValueFormatter;
+//!
+//! let schema = Schema::load(&JsonFormatter, r##"{
+//!     "type": "object",
+//!     "required": ["name", "tags"],
+//!     "properties": {
+//!         "name": {"type": "string", "minLength": 1},
+//!         "age": {"type": "integer", "minimum": 0},
+//!         "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+//!     },
+//!     "additionalProperties": false,
+//!     "$defs": {"tag": {"type": "string", "pattern": "^[a-z]+$"}}
+//! }"##).unwrap();
+//!
+//! let user = JsonFormatter.parse(r#"{"age": -1, "tags": ["ok", "No"], "admin": true}"#).unwrap();
+//! let violations = schema.validate(&user).unwrap_err();
+//! let found: Vec<_> = violations
+//!     .iter()
+//!     .map(|v| (v.instance.as_str(), v.message.as_str()))
+//!     .collect();
+//! assert_eq!(found, [
+//!     ("/name", "is required"),
+//!     ("/age", "must be at least 0"),
+//!     ("/tags/1", "must match the pattern ^[a-z]+$"),
+//!     ("/admin", "is not allowed"),
+//! ]);
+//! assert_eq!(violations[2].keyword, "/properties/tags/items/$ref/pattern");
+//! ```
+
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+
+/// Depth of `$ref` resolution past which a schema is deemed recursive.
+const MAX_REF_DEPTH: usize = 64;
+
+/// Invalid schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// JSON Pointer of the invalid part of the schema.
+    pub location: String,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid schema at \"{}\": {}",
+            self.location, self.message
+        )
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Failed constraint of a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON Pointer of the invalid value; missing properties point to
+    /// where they are expected.
+    pub instance: String,
+    /// JSON Pointer of the failed keyword in the schema, through `$ref`s.
+    pub keyword: String,
+    /// Description of the failure.
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.instance.as_str() {
+            "" => write!(f, "{}", self.message),
+            instance => write!(f, "{}: {}", instance, self.message),
+        }
+    }
+}
+
+/// Compiled JSON Schema.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Value,
+    patterns: HashMap<String, Regex>,
+}
+
+impl Schema {
+    /// Check and compile `schema`.
+    pub fn new(schema: Value) -> Result<Self, SchemaError> {
+        let mut patterns = HashMap::new();
+        let mut references = Vec::new();
+        check(&schema, &schema, "", &mut patterns, &mut references)?;
+        // Referenced parts may lie under keywords that are not checked.
+        let mut checked = Vec::new();
+        while let Some(pointer) = references.pop() {
+            if !checked.contains(&pointer) {
+                let target = schema.pointer(&pointer).expect("checked reference");
+                check(&schema, target, &pointer, &mut patterns, &mut references)?;
+                checked.push(pointer);
+            }
+        }
+        Ok(Self {
+            root: schema,
+            patterns,
+        })
+    }
+
+    /// Parse `input` with `formatter` and compile the schema.
+    pub fn load(formatter: &impl ValueFormatter, input: &str) -> Result<Self, SchemaError> {
+        let schema = formatter.parse(input).ok_or_else(|| SchemaError {
+            location: String::new(),
+            message: "the document cannot be parsed".to_string(),
+        })?;
+        Self::new(schema)
+    }
+
+    /// The schema document.
+    pub fn value(&self) -> &Value {
+        &self.root
+    }
+
+    /// Check `instance`, reporting every violation.
+    pub fn validate(&self, instance: &Value) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        self.apply(&self.root, "", instance, "", 0, &mut violations);
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+
+    /// Returns `true` if `instance` is valid.
+    pub fn is_valid(&self, instance: &Value) -> bool {
+        self.validate(instance).is_ok()
+    }
+
+    /// Returns `true` if `instance` matches the sub-`schema`.
+    fn matches(&self, schema: &Value, instance: &Value, depth: usize) -> bool {
+        let mut violations = Vec::new();
+        self.apply(schema, "", instance, "", depth, &mut violations);
+        violations.is_empty()
+    }
+
+    /// Check `instance`, at `pointer`, against `schema`, at `location`.
+    fn apply(
+        &self,
+        schema: &Value,
+        location: &str,
+        instance: &Value,
+        pointer: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let keywords = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                return out.push(violation(pointer, location, "is not allowed".to_string()))
+            }
+            Value::Dictionary(keywords) => keywords,
+            _ => return,
+        };
+        let fail = |out: &mut Vec<Violation>, keyword: &str, message: String| {
+            out.push(violation(
+                pointer,
+                &format!("{}/{}", location, keyword),
+                message,
+            ))
+        };
+
+        if let Some(Value::String(reference)) = keywords.get("$ref") {
+            let target = self.root.pointer(&reference[1..]);
+            match (target, depth < MAX_REF_DEPTH) {
+                (Some(target), true) => self.apply(
+                    target,
+                    &format!("{}/$ref", location),
+                    instance,
+                    pointer,
+                    depth + 1,
+                    out,
+                ),
+                _ => out.push(violation(
+                    pointer,
+                    &format!("{}/$ref", location),
+                    "cannot be checked: the schema is too deeply recursive".to_string(),
+                )),
+            }
+        }
+
+        // Generic keywords.
+        if let Some(types) = keywords.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(name) => vec![name],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.iter().any(|name| has_type(instance, name)) {
+                fail(
+                    out,
+                    "type",
+                    format!("must be of type {}", types.join(" or ")),
+                );
+            }
+        }
+        if let Some(Value::Array(allowed)) = keywords.get("enum") {
+            if !allowed.iter().any(|value| equal(value, instance)) {
+                fail(out, "enum", "must be one of the allowed values".to_string());
+            }
+        }
+        if let Some(expected) = keywords.get("const") {
+            if !equal(expected, instance) {
+                fail(out, "const", "must be the constant value".to_string());
+            }
+        }
+
+        // Numbers.
+        if let Some(n) = number(instance) {
+            let bound = |keyword: &str| keywords.get(keyword).and_then(number);
+            if let Some(min) = bound("minimum").filter(|min| n < *min) {
+                fail(out, "minimum", format!("must be at least {}", min));
+            }
+            if let Some(max) = bound("maximum").filter(|max| n > *max) {
+                fail(out, "maximum", format!("must be at most {}", max));
+            }
+            if let Some(min) = bound("exclusiveMinimum").filter(|min| n <= *min) {
+                fail(
+                    out,
+                    "exclusiveMinimum",
+                    format!("must be greater than {}", min),
+                );
+            }
+            if let Some(max) = bound("exclusiveMaximum").filter(|max| n >= *max) {
+                fail(
+                    out,
+                    "exclusiveMaximum",
+                    format!("must be less than {}", max),
+                );
+            }
+            if let Some(factor) = bound("multipleOf") {
+                let quotient = n / factor;
+                if (quotient - quotient.round()).abs() > 1e-9 {
+                    fail(
+                        out,
+                        "multipleOf",
+                        format!("must be a multiple of {}", factor),
+                    );
+                }
+            }
+        }
+
+        // Strings.
+        if let Value::String(s) = instance {
+            let length = s.chars().count();
+            if let Some(min) = count(keywords, "minLength").filter(|min| length < *min) {
+                fail(
+                    out,
+                    "minLength",
+                    format!("must have at least {} characters", min),
+                );
+            }
+            if let Some(max) = count(keywords, "maxLength").filter(|max| length > *max) {
+                fail(
+                    out,
+                    "maxLength",
+                    format!("must have at most {} characters", max),
+                );
+            }
+            if let Some(Value::String(pattern)) = keywords.get("pattern") {
+                if !self.patterns[pattern].is_match(s) {
+                    fail(
+                        out,
+                        "pattern",
+                        format!("must match the pattern {}", pattern),
+                    );
+                }
+            }
+            if let Some(Value::String(format)) = keywords.get("format") {
+                if !has_format(s, format) {
+                    fail(out, "format", format!("must be a valid {}", format));
+                }
+            }
+        }
+
+        // Arrays.
+        if let Value::Array(items) = instance {
+            if let Some(min) = count(keywords, "minItems").filter(|min| items.len() < *min) {
+                fail(out, "minItems", format!("must have at least {} items", min));
+            }
+            if let Some(max) = count(keywords, "maxItems").filter(|max| items.len() > *max) {
+                fail(out, "maxItems", format!("must have at most {} items", max));
+            }
+            if keywords.get("uniqueItems") == Some(&Value::Bool(true)) {
+                let mut seen = HashSet::new();
+                let duplicate = items
+                    .iter()
+                    .filter_map(canonical)
+                    .any(|item| !seen.insert(item));
+                if duplicate {
+                    fail(
+                        out,
+                        "uniqueItems",
+                        "must not contain duplicates".to_string(),
+                    );
+                }
+            }
+            let prefix = match keywords.get("prefixItems") {
+                Some(Value::Array(schemas)) => schemas.as_slice(),
+                _ => &[],
+            };
+            for (i, (schema, item)) in prefix.iter().zip(items).enumerate() {
+                let location = format!("{}/prefixItems/{}", location, i);
+                self.apply(
+                    schema,
+                    &location,
+                    item,
+                    &format!("{}/{}", pointer, i),
+                    depth,
+                    out,
+                );
+            }
+            if let Some(schema) = keywords.get("items") {
+                let location = format!("{}/items", location);
+                for (i, item) in items.iter().enumerate().skip(prefix.len()) {
+                    self.apply(
+                        schema,
+                        &location,
+                        item,
+                        &format!("{}/{}", pointer, i),
+                        depth,
+                        out,
+                    );
+                }
+            }
+            if let Some(schema) = keywords.get("contains") {
+                let found = items
+                    .iter()
+                    .filter(|item| self.matches(schema, item, depth))
+                    .count();
+                let min = count(keywords, "minContains").unwrap_or(1);
+                if found < min {
+                    let message = format!("must contain at least {} matching item(s)", min);
+                    fail(out, "contains", message);
+                }
+                if let Some(max) = count(keywords, "maxContains").filter(|max| found > *max) {
+                    let message = format!("must contain at most {} matching item(s)", max);
+                    fail(out, "maxContains", message);
+                }
+            }
+        }
+
+        // Objects.
+        if let Value::Dictionary(entries) = instance {
+            if let Some(min) = count(keywords, "minProperties").filter(|min| entries.len() < *min) {
+                fail(
+                    out,
+                    "minProperties",
+                    format!("must have at least {} properties", min),
+                );
+            }
+            if let Some(max) = count(keywords, "maxProperties").filter(|max| entries.len() > *max) {
+                fail(
+                    out,
+                    "maxProperties",
+                    format!("must have at most {} properties", max),
+                );
+            }
+            let member = |key: &str| format!("{}/{}", pointer, escape(key));
+            if let Some(Value::Array(required)) = keywords.get("required") {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !entries.contains_key(key) {
+                        let keyword = format!("{}/required", location);
+                        out.push(violation(&member(key), &keyword, "is required".to_string()));
+                    }
+                }
+            }
+            if let Some(Value::Dictionary(dependencies)) = keywords.get("dependentRequired") {
+                for (key, required) in dependencies {
+                    let Value::Array(required) = required else {
+                        continue;
+                    };
+                    if !entries.contains_key(key) {
+                        continue;
+                    }
+                    for name in required.iter().filter_map(Value::as_str) {
+                        if !entries.contains_key(name) {
+                            let keyword = format!("{}/dependentRequired/{}", location, escape(key));
+                            let message = format!("is required with {}", key);
+                            out.push(violation(&member(name), &keyword, message));
+                        }
+                    }
+                }
+            }
+            let properties = keywords.get("properties").and_then(Value::as_dictionary);
+            let patterns = keywords
+                .get("patternProperties")
+                .and_then(Value::as_dictionary);
+            for (key, value) in entries {
+                let mut evaluated = false;
+                if let Some(schema) = properties.and_then(|properties| properties.get(key)) {
+                    let location = format!("{}/properties/{}", location, escape(key));
+                    self.apply(schema, &location, value, &member(key), depth, out);
+                    evaluated = true;
+                }
+                for (pattern, schema) in patterns.into_iter().flatten() {
+                    if self.patterns[pattern].is_match(key) {
+                        let location =
+                            format!("{}/patternProperties/{}", location, escape(pattern));
+                        self.apply(schema, &location, value, &member(key), depth, out);
+                        evaluated = true;
+                    }
+                }
+                if let Some(schema) = keywords.get("additionalProperties").filter(|_| !evaluated) {
+                    let location = format!("{}/additionalProperties", location);
+                    self.apply(schema, &location, value, &member(key), depth, out);
+                }
+                if let Some(schema) = keywords.get("propertyNames") {
+                    let location = format!("{}/propertyNames", location);
+                    let name = Value::String(key.clone());
+                    self.apply(schema, &location, &name, &member(key), depth, out);
+                }
+            }
+        }
+
+        // Combinations.
+        let subschemas = |keyword: &str| match keywords.get(keyword) {
+            Some(Value::Array(schemas)) => schemas.as_slice(),
+            _ => &[],
+        };
+        for (i, schema) in subschemas("allOf").iter().enumerate() {
+            let location = format!("{}/allOf/{}", location, i);
+            self.apply(schema, &location, instance, pointer, depth, out);
+        }
+        let any_of = subschemas("anyOf");
+        if !any_of.is_empty() && !any_of.iter().any(|s| self.matches(s, instance, depth)) {
+            let message = "must match at least one of the schemas".to_string();
+            fail(out, "anyOf", message);
+        }
+        let one_of = subschemas("oneOf");
+        if !one_of.is_empty() {
+            let matched = one_of
+                .iter()
+                .filter(|s| self.matches(s, instance, depth))
+                .count();
+            if matched != 1 {
+                let message = format!("must match exactly one of the schemas, not {}", matched);
+                fail(out, "oneOf", message);
+            }
+        }
+        if let Some(schema) = keywords.get("not") {
+            if self.matches(schema, instance, depth) {
+                let message = "must not match the schema".to_string();
+                fail(out, "not", message);
+            }
+        }
+        if let Some(condition) = keywords.get("if") {
+            let branch = match self.matches(condition, instance, depth) {
+                true => "then",
+                false => "else",
+            };
+            if let Some(schema) = keywords.get(branch) {
+                let location = format!("{}/{}", location, branch);
+                self.apply(schema, &location, instance, pointer, depth, out);
+            }
+        }
+    }
+}
+
+fn violation(instance: &str, keyword: &str, message: String) -> Violation {
+    Violation {
+        instance: instance.to_string(),
+        keyword: keyword.to_string(),
+        message,
+    }
+}
+
+/// Escape `key` as a JSON Pointer segment.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Non-negative integer value of `keyword`.
+fn count(keywords: &Dictionary<Value>, keyword: &str) -> Option<usize> {
+    match keywords.get(keyword)? {
+        Value::Int(i) => usize::try_from(*i).ok(),
+        Value::Number(n) if n.fract() == 0.0 && *n >= 0.0 => Some(*n as usize),
+        _ => None,
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match (name, value) {
+        ("null", Value::Null) | ("boolean", Value::Bool(_)) => true,
+        ("integer" | "number", Value::Int(_)) => true,
+        ("integer", Value::Number(n)) => n.fract() == 0.0,
+        ("number", Value::Number(_)) | ("string", Value::String(_)) => true,
+        ("array", Value::Array(_)) | ("object", Value::Dictionary(_)) => true,
+        _ => false,
+    }
+}
+
+/// JSON equality: `1` equals `1.0` and key order does not matter.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Dictionary(a), Value::Dictionary(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| equal(a, b)))
+        }
+        (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+            number(a) == number(b)
+        }
+        _ => a == b,
+    }
+}
+
+/// Text equal for values which are [`equal`], or `None` for values holding
+/// `NaN`, which equal nothing.
+fn canonical(value: &Value) -> Option<String> {
+    let mut out = String::new();
+    write_canonical(value, &mut out).then_some(out)
+}
+
+fn write_canonical(value: &Value, out: &mut String) -> bool {
+    match value {
+        Value::Null => out.push('n'),
+        Value::Bool(b) => out.push(if *b { 't' } else { 'f' }),
+        Value::Int(_) | Value::Number(_) => {
+            let n = number(value).unwrap_or_default();
+            if n.is_nan() {
+                return false;
+            }
+            // Adding zero turns `-0.0` into `0.0`, which it equals.
+            out.push_str(&format!("#{:?}", n + 0.0));
+        }
+        Value::String(s) => out.push_str(&format!("{:?}", s)),
+        Value::Array(items) => {
+            out.push('[');
+            for item in items {
+                if !write_canonical(item, out) {
+                    return false;
+                }
+                out.push(',');
+            }
+            out.push(']');
+        }
+        Value::Dictionary(entries) => {
+            let mut entries: Vec<_> = entries.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (key, value) in entries {
+                out.push_str(&format!("{:?}:", key));
+                if !write_canonical(value, out) {
+                    return false;
+                }
+                out.push(',');
+            }
+            out.push('}');
+        }
+    }
+    true
+}
+
+fn has_format(s: &str, format: &str) -> bool {
+    static PATTERNS: OnceLock<HashMap<&str, Regex>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        let time =
+            r"([01]\d|2[0-3]):[0-5]\d:([0-5]\d|60)(\.\d+)?([zZ]|[+-]([01]\d|2[0-3]):[0-5]\d)";
+        let date = r"\d{4}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01])";
+        [
+            ("email", r"^[^@\s]+@[^@\s.]+(\.[^@\s.]+)+$".to_string()),
+            ("uri", r"^[a-zA-Z][a-zA-Z0-9+.-]*:[^\s]*$".to_string()),
+            (
+                "uuid",
+                r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$"
+                    .to_string(),
+            ),
+            ("date", format!("^{}$", date)),
+            ("time", format!("^{}$", time)),
+            ("date-time", format!("^{}[tT ]{}$", date, time)),
+        ]
+        .into_iter()
+        .map(|(name, pattern)| (name, Regex::new(&pattern).expect("valid format pattern")))
+        .collect()
+    });
+    match format {
+        "ipv4" => s.parse::<Ipv4Addr>().is_ok(),
+        "ipv6" => s.parse::<Ipv6Addr>().is_ok(),
+        format => patterns
+            .get(format)
+            .is_none_or(|pattern| pattern.is_match(s)),
+    }
+}
+
+/// Check the `schema` found at `location` of `root`, compiling its patterns
+/// and collecting the pointers of its `references`.
+fn check(
+    root: &Value,
+    schema: &Value,
+    location: &str,
+    patterns: &mut HashMap<String, Regex>,
+    references: &mut Vec<String>,
+) -> Result<(), SchemaError> {
+    let keywords = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Dictionary(keywords) => keywords,
+        _ => return Err(invalid(location, "a schema must be an object or a boolean")),
+    };
+    for (keyword, value) in keywords {
+        let at = format!("{}/{}", location, escape(keyword));
+        match keyword.as_str() {
+            "type" => {
+                let names = match value {
+                    Value::String(name) => vec![name.as_str()],
+                    Value::Array(names) => names
+                        .iter()
+                        .map(|name| {
+                            name.as_str()
+                                .ok_or_else(|| invalid(&at, "type names must be strings"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                    _ => return Err(invalid(&at, "must be a string or an array")),
+                };
+                let known = [
+                    "null", "boolean", "integer", "number", "string", "array", "object",
+                ];
+                if let Some(name) = names.iter().find(|name| !known.contains(name)) {
+                    return Err(invalid(&at, &format!("unknown type {}", name)));
+                }
+            }
+            "enum" if !matches!(value, Value::Array(_)) => {
+                return Err(invalid(&at, "must be an array"))
+            }
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum"
+                if number(value).is_none() =>
+            {
+                return Err(invalid(&at, "must be a number"))
+            }
+            "multipleOf" if !number(value).is_some_and(|n| n > 0.0) => {
+                return Err(invalid(&at, "must be a number greater than 0"))
+            }
+            "minLength" | "maxLength" | "minItems" | "maxItems" | "minContains" | "maxContains"
+            | "minProperties" | "maxProperties"
+                if count(keywords, keyword).is_none() =>
+            {
+                return Err(invalid(&at, "must be a non-negative integer"))
+            }
+            "pattern" => {
+                let Value::String(pattern) = value else {
+                    return Err(invalid(&at, "must be a string"));
+                };
+                compile(pattern, &at, patterns)?;
+            }
+            "required" if !is_names(value) => {
+                return Err(invalid(&at, "must be an array of strings"))
+            }
+            "dependentRequired" => {
+                let valid = value
+                    .as_dictionary()
+                    .is_some_and(|dependencies| dependencies.values().all(is_names));
+                if !valid {
+                    return Err(invalid(&at, "must map names to arrays of strings"));
+                }
+            }
+            "uniqueItems" if !matches!(value, Value::Bool(_)) => {
+                return Err(invalid(&at, "must be a boolean"))
+            }
+            "$ref" => {
+                let pointer = value
+                    .as_str()
+                    .and_then(|reference| reference.strip_prefix('#'))
+                    .filter(|pointer| root.pointer(pointer).is_some())
+                    .ok_or_else(|| invalid(&at, "must reference a part of the schema (#/...)"))?;
+                references.push(pointer.to_string());
+            }
+            "items"
+            | "contains"
+            | "additionalProperties"
+            | "propertyNames"
+            | "not"
+            | "if"
+            | "then"
+            | "else" => check(root, value, &at, patterns, references)?,
+            "prefixItems" | "allOf" | "anyOf" | "oneOf" => {
+                let Value::Array(schemas) = value else {
+                    return Err(invalid(&at, "must be an array of schemas"));
+                };
+                if schemas.is_empty() && keyword != "prefixItems" {
+                    return Err(invalid(&at, "must not be empty"));
+                }
+                for (i, schema) in schemas.iter().enumerate() {
+                    check(root, schema, &format!("{}/{}", at, i), patterns, references)?;
+                }
+            }
+            "properties" | "patternProperties" | "$defs" => {
+                let Value::Dictionary(schemas) = value else {
+                    return Err(invalid(&at, "must be an object of schemas"));
+                };
+                for (name, schema) in schemas {
+                    let at = format!("{}/{}", at, escape(name));
+                    if keyword == "patternProperties" {
+                        compile(name, &at, patterns)?;
+                    }
+                    check(root, schema, &at, patterns, references)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` for an array of strings.
+fn is_names(value: &Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|names| names.iter().all(|name| name.as_str().is_some()))
+}
+
+fn compile(
+    pattern: &str,
+    location: &str,
+    patterns: &mut HashMap<String, Regex>,
+) -> Result<(), SchemaError> {
+    if !patterns.contains_key(pattern) {
+        let regex = Regex::new(pattern).map_err(|e| invalid(location, &e.to_string()))?;
+        patterns.insert(pattern.to_string(), regex);
+    }
+    Ok(())
+}
+
+fn invalid(location: &str, message: &str) -> SchemaError {
+    SchemaError {
+        location: location.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::value::json::JsonFormatter;
+    use crate::concepts::value::yaml::YamlFormatter;
+
+    fn json(input: &str) -> Value {
+        JsonFormatter.parse(input).unwrap()
+    }
+
+    fn messages(schema: &Schema, instance: &str) -> Vec<String> {
+        match schema.validate(&json(instance)) {
+            Ok(()) => Vec::new(),
+            Err(violations) => violations.iter().map(Violation::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_scalars() {
+        let schema = Schema::new(json(
+            r#"{"type": ["integer", "string"], "minimum": 2, "exclusiveMaximum": 10,
+                "multipleOf": 2, "maxLength": 3, "format": "ipv4", "enum": [2, 4.0, "1.2.3.4"]}"#,
+        ))
+        .unwrap();
+        assert!(schema.is_valid(&Value::Int(4)));
+        assert!(schema.is_valid(&Value::Number(2.0)));
+        assert_eq!(
+            messages(&schema, "10"),
+            ["must be one of the allowed values", "must be less than 10"]
+        );
+        assert_eq!(
+            messages(&schema, "3"),
+            [
+                "must be one of the allowed values",
+                "must be a multiple of 2"
+            ]
+        );
+        assert_eq!(
+            messages(&schema, "true"),
+            [
+                "must be of type integer or string",
+                "must be one of the allowed values"
+            ]
+        );
+        assert_eq!(
+            messages(&schema, r#""abcd""#),
+            [
+                "must be one of the allowed values",
+                "must have at most 3 characters",
+                "must be a valid ipv4"
+            ]
+        );
+        assert!(!schema.is_valid(&Value::String("1.2.3.4".into())));
+
+        let formats = [
+            ("email", "a@b.co", "a@b"),
+            ("uri", "urn:isbn:123", "no scheme"),
+            ("uuid", "67e55044-10b1-426f-9247-bb680e5fe0c8", "67e55044"),
+            ("date", "2024-02-29", "2024-13-01"),
+            ("time", "23:59:60Z", "24:00:00Z"),
+            (
+                "date-time",
+                "2024-01-01T10:00:00.5+01:00",
+                "2024-01-01T10:00:00",
+            ),
+            ("ipv6", "::1", "1.2.3.4"),
+            ("hostname", "anything", "goes"),
+        ];
+        for (format, valid, invalid) in formats {
+            let schema = Schema::new(Value::from([("format", format)])).unwrap();
+            assert!(schema.is_valid(&Value::from(valid)), "{}", format);
+            assert_eq!(schema.is_valid(&Value::from(invalid)), format == "hostname");
+        }
+    }
+
+    #[test]
+    fn test_arrays_and_objects() {
+        let schema = Schema::load(
+            &YamlFormatter,
+            "type: object\n\
+             properties:\n  point:\n    prefixItems: [{type: number}, {type: number}]\n    items: false\n\
+             \x20 tags:\n    uniqueItems: true\n    contains: {const: x}\n    maxContains: 1\n\
+             patternProperties:\n  '^x-': {type: string}\n\
+             additionalProperties: {type: integer}\n\
+             propertyNames: {maxLength: 5}\n\
+             dependentRequired:\n  card: [cvc]\n\
+             maxProperties: 4\n",
+        )
+        .unwrap();
+        assert!(schema.is_valid(&json(r#"{"point": [1, 2.5], "x-a": "b", "n": 1}"#)));
+        assert_eq!(
+            messages(
+                &schema,
+                r#"{"point": [1, "a", 3], "tags": [1, 1.0], "x-a": 1, "card": 2, "longname": 1}"#
+            ),
+            [
+                "must have at most 4 properties",
+                "/cvc: is required with card",
+                "/point/1: must be of type number",
+                "/point/2: is not allowed",
+                "/tags: must not contain duplicates",
+                "/tags: must contain at least 1 matching item(s)",
+                "/x-a: must be of type string",
+                "/longname: must have at most 5 characters",
+            ]
+        );
+
+        let unique = Schema::new(json(r#"{"uniqueItems": true}"#)).unwrap();
+        assert!(!unique.is_valid(&json(r#"[{"a": [0], "b": "x"}, {"b": "x", "a": [-0.0]}]"#)));
+        assert!(unique.is_valid(&json(r#"[{"a": 1}, {"a": "1"}, [1], "1", 1.5]"#)));
+        let items = (0..100_000).map(Value::Int).collect::<Vec<_>>();
+        assert!(unique.is_valid(&Value::Array(items)));
+    }
+
+    #[test]
+    fn test_combinations_and_refs() {
+        let schema = Schema::new(json(
+            r##"{
+                "$defs": {
+                    "node": {
+                        "type": "object",
+                        "required": ["value"],
+                        "properties": {"next": {"$ref": "#/$defs/node"}}
+                    }
+                },
+                "oneOf": [{"$ref": "#/$defs/node"}, {"type": "null"}],
+                "not": {"const": {"value": 0}},
+                "if": {"type": "object"},
+                "then": {"properties": {"value": {"anyOf": [{"type": "integer"}, {"type": "string"}]}}},
+                "else": {"type": "null"}
+            }"##,
+        ))
+        .unwrap();
+        assert!(schema.is_valid(&json(r#"{"value": 1, "next": {"value": "a"}}"#)));
+        assert!(schema.is_valid(&Value::Null));
+        let violations = schema
+            .validate(&json(r#"{"value": 1, "next": {"next": {"value": 1}}}"#))
+            .unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].instance, "");
+        assert_eq!(violations[0].keyword, "/oneOf");
+        let violations = schema.validate(&json(r#"{"value": 0.0}"#)).unwrap_err();
+        assert_eq!(violations[0].keyword, "/not");
+        assert_eq!(
+            messages(&schema, r#"{"value": [1]}"#),
+            ["/value: must match at least one of the schemas"]
+        );
+
+        let looping = Schema::new(json(r##"{"$ref": "#"}"##)).unwrap();
+        assert_eq!(
+            looping.validate(&Value::Null).unwrap_err()[0].message,
+            "cannot be checked: the schema is too deeply recursive"
+        );
+    }
+
+    #[test]
+    fn test_invalid_schemas() {
+        let error = |input: &str| Schema::new(json(input)).unwrap_err();
+        assert_eq!(error(r#"{"type": "int"}"#).message, "unknown type int");
+        assert_eq!(error(r#"{"items": 1}"#).location, "/items");
+        assert_eq!(
+            error(r#"{"properties": {"a/b": {"pattern": "("}}}"#).location,
+            "/properties/a~1b/pattern"
+        );
+        assert_eq!(error(r##"{"$ref": "#/$defs/missing"}"##).location, "/$ref");
+        assert_eq!(
+            error(r#"{"$ref": "http://example.com/s"}"#).location,
+            "/$ref"
+        );
+        assert_eq!(error(r#"{"minLength": -1}"#).location, "/minLength");
+        assert_eq!(error(r#"{"anyOf": []}"#).location, "/anyOf");
+        assert!(Schema::load(&JsonFormatter, "{").is_err());
+        assert!(Schema::new(Value::Bool(false))
+            .unwrap()
+            .validate(&Value::Null)
+            .is_err());
+    }
+}
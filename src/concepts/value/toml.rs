@@ -0,0 +1,139 @@
+//! TOML formatter for [`Value`].
+//!
+//! TOML documents are tables, so [`TomlFormatter`] formats dictionaries and
+//! returns an empty string for other values. TOML has no null: null entries
+//! and array items are omitted. Keys keep their insertion order, except that
+//! TOML writes the plain values of a table before its sub-tables. Date-times
+//! parse as strings.
+//!
+//! ```
+//! use hermes::concepts::value::toml::TomlFormatter;
+//! use hermes::concepts::value::{Value, ValueFormatter};
+//!
+//! let config = TomlFormatter
+//!     .parse("name = \"hermes\"\n\n[server]\nport = 8080\nhosts = [\"a\", \"b\"]\n")
+//!     .unwrap();
+//! let Value::Dictionary(root) = &config else { unreachable!() };
+//! let Value::Dictionary(server) = &root["server"] else { unreachable!() };
+//! assert_eq!(server["port"], Value::Int(8080));
+//! assert_eq!(TomlFormatter.parse(&TomlFormatter.format(config.clone())), Some(config));
+//! ```
+
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use ::toml::{Table, Value as TomlValue};
+
+/// TOML formatter.
+#[derive(Debug, Clone)]
+pub struct TomlFormatter;
+
+impl ValueFormatter for TomlFormatter {
+    fn format(&self, value: Value) -> String {
+        match to_toml(value) {
+            // Tables built by `to_toml` have string keys, no missing values
+            // and no date-times: the serializer has no error left to report.
+            Some(TomlValue::Table(table)) => {
+                ::toml::to_string(&table).expect("TOML tables always serialize")
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn parse(&self, input: &str) -> Option<Value> {
+        input
+            .parse::<Table>()
+            .ok()
+            .map(|table| from_toml(TomlValue::Table(table)))
+    }
+}
+
+/// Convert `value`, or `None` if it is null.
+fn to_toml(value: Value) -> Option<TomlValue> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => TomlValue::Boolean(b),
+        Value::Int(i) => TomlValue::Integer(i),
+        Value::Number(f) => TomlValue::Float(f),
+        Value::String(s) => TomlValue::String(s),
+        Value::Array(items) => TomlValue::Array(items.into_iter().filter_map(to_toml).collect()),
+        Value::Dictionary(entries) => TomlValue::Table(
+            entries
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, to_toml(value)?)))
+                .collect(),
+        ),
+    })
+}
+
+fn from_toml(value: TomlValue) -> Value {
+    match value {
+        TomlValue::Boolean(b) => Value::Bool(b),
+        TomlValue::Integer(i) => Value::Int(i),
+        TomlValue::Float(f) => Value::Number(f),
+        TomlValue::String(s) => Value::String(s),
+        TomlValue::Datetime(dt) => Value::String(dt.to_string()),
+        TomlValue::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+        TomlValue::Table(table) => Value::Dictionary(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, from_toml(value)))
+                .collect::<Dictionary<_>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let f = TomlFormatter;
+        let mut server = Dictionary::new();
+        server.insert("port".to_string(), Value::Int(80));
+        server.insert("ratio".to_string(), Value::Number(0.5));
+        let mut user = Dictionary::new();
+        user.insert("name".to_string(), Value::String("ann \"a\"".to_string()));
+        let mut root = Dictionary::new();
+        root.insert("server".to_string(), Value::Dictionary(server));
+        root.insert("debug".to_string(), Value::Bool(true));
+        root.insert("missing".to_string(), Value::Null);
+        root.insert(
+            "users".to_string(),
+            Value::Array(vec![Value::Dictionary(user), Value::Null]),
+        );
+        let value = Value::Dictionary(root);
+
+        let out = f.format(value);
+        assert_eq!(
+            out,
+            "debug = true\n\n[server]\nport = 80\nratio = 0.5\n\n[[users]]\nname = 'ann \"a\"'\n"
+        );
+        let Some(Value::Dictionary(parsed)) = f.parse(&out) else {
+            unreachable!()
+        };
+        assert_eq!(
+            parsed.keys().collect::<Vec<_>>(),
+            ["debug", "server", "users"]
+        );
+        assert!(!parsed.contains_key("missing"));
+        assert_eq!(f.format(Value::Int(1)), "");
+    }
+
+    #[test]
+    fn test_parse() {
+        let f = TomlFormatter;
+        let Some(Value::Dictionary(root)) = f.parse("b = 1979-05-27T07:32:00Z\na = [1, 2.5]")
+        else {
+            unreachable!()
+        };
+        assert_eq!(root.keys().collect::<Vec<_>>(), ["b", "a"]);
+        assert_eq!(root["b"], Value::String("1979-05-27T07:32:00Z".to_string()));
+        assert_eq!(
+            root["a"],
+            Value::Array(vec![Value::Int(1), Value::Number(2.5)])
+        );
+        assert_eq!(f.parse("a = "), None);
+        assert_eq!(f.parse(""), Some(Value::Dictionary(Dictionary::new())));
+    }
+}
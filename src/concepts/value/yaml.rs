@@ -147,4 +147,22 @@ mod tests {
                 .unwrap();
         assert_eq!(parsed, expected);
     }
+
+    #[test]
+    fn test_key_order() {
+        let f = YamlFormatter;
+        let mut inner = Dictionary::new();
+        inner.insert("y".to_string(), Value::Int(1));
+        inner.insert("b".to_string(), Value::Int(2));
+        let mut d = Dictionary::new();
+        d.insert("z".to_string(), Value::Dictionary(inner));
+        d.insert("a".to_string(), Value::Bool(true));
+        let out = f.format(Value::Dictionary(d.clone()));
+        assert_eq!(out, "z: \n  y: 1\n  b: 2\na: true");
+        assert_eq!(f.parse(&out), Some(Value::Dictionary(d.clone())));
+        let Some(Value::Dictionary(parsed)) = f.parse(&out) else {
+            unreachable!()
+        };
+        assert_eq!(parsed.keys().collect::<Vec<_>>(), ["z", "a"]);
+    }
 }
@@ -48,7 +48,7 @@ impl Container {
     pub fn register_named<T: Any + Send + Sync>(&mut self, name: impl Into<String>, service: T) {
         self.services
             .entry(TypeId::of::<T>())
-            .or_insert_with(HashMap::new)
+            .or_default()
             .insert(name.into(), Arc::new(service));
     }
 
@@ -13,8 +13,13 @@ pub use cycle::uri::*;
 
 pub mod cookie;
 pub mod error;
+pub mod extract;
+pub mod multipart;
+pub mod problem;
 pub mod routing;
+pub mod security;
 pub mod services;
 pub mod session;
+pub mod validation;
 
 pub use error::*;
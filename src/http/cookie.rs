@@ -50,7 +50,7 @@ impl CookieJar {
 
     /// Remove a cookie from the jar.
     pub fn remove(&mut self, name: &str) {
-        self.cookies.remove(name);
+        self.cookies.shift_remove(name);
     }
 
     /// Parse cookies from a `Cookie` header string.
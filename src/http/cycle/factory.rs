@@ -1,4 +1,7 @@
 //! Factories for building HTTP requests and responses.
+use crate::concepts::value::json::JsonFormatter;
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
 use crate::http::{
     Headers, Message, MessageTrait, Method, Request, Response, Status, Uri, Version,
 };
@@ -79,6 +82,8 @@ impl RequestFactory {
                 headers: self.default_headers.merge_with(&headers),
                 body: body.to_string(),
             },
+            attributes: Dictionary::new(),
+            raw_body: None,
         }
     }
 
@@ -279,25 +284,18 @@ pub struct WWWAuthenticate {
 
 impl Display for WWWAuthenticate {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {}{}",
-            self.scheme,
-            if let Some(realm) = &self.realm {
-                format!("realm={}", realm)
-            } else {
-                "".to_string()
-            },
-            if let Some(charset) = &self.charset {
-                format!(
-                    "{}charset={}",
-                    if self.realm.is_some() { ", " } else { "" },
-                    charset
-                )
-            } else {
-                "".to_string()
-            }
-        )
+        let mut params = Vec::new();
+        if let Some(realm) = &self.realm {
+            params.push(format!("realm=\"{}\"", realm.replace('"', "\\\"")));
+        }
+        if let Some(charset) = &self.charset {
+            params.push(format!("charset=\"{}\"", charset));
+        }
+        if params.is_empty() {
+            write!(f, "{}", self.scheme)
+        } else {
+            write!(f, "{} {}", self.scheme, params.join(", "))
+        }
     }
 }
 
@@ -366,7 +364,7 @@ impl ResponseFactory {
     /// Build a redirection response with `Location` header.
     ///
     /// ```
-    /// use hermes::http::{Authority, Path, Query, ResponseFactory, Uri, Version, ResponseTrait};
+    /// use hermes::http::{Authority, MessageTrait, Path, Query, ResponseFactory, Uri, Version, ResponseTrait};
     ///
     /// let factory = ResponseFactory::version(Version::Http1_1);
     /// let uri = Uri::new(
@@ -378,11 +376,10 @@ impl ResponseFactory {
     /// );
     /// let resp = factory.moved_permanently(uri);
     /// assert_eq!(resp.code(), 301);
+    /// assert_eq!(resp.get_header_line("Location"), Some("http://example.com".to_string()));
     /// ```
     pub fn redirect(&self, redirection: Redirection) -> Response {
-        let mut headers = Headers::new();
-        let (status, target) = redirection.to_pair();
-        headers.insert("Location", &[target.to_string()]);
+        let (status, headers) = redirection.to_pair();
         self.with_status(status, headers)
     }
     /// Convenience helper to return a 200 response.
@@ -397,6 +394,26 @@ impl ResponseFactory {
     pub fn ok(&self, headers: Headers, body: String) -> Response {
         self.with_status(Status::OK, headers).with_body(&body)
     }
+    /// Build a response with `value` as JSON body.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::{ResponseFactory, Status, Version, MessageTrait, ResponseTrait};
+    ///
+    /// let fac = ResponseFactory::version(Version::Http1_1);
+    /// let resp = fac.json(Status::Created, Value::Array(vec![Value::Int(1)]));
+    /// assert_eq!(resp.code(), 201);
+    /// assert_eq!(resp.get_header_line("Content-Type"), Some("application/json".to_string()));
+    /// assert_eq!(resp.get_header_line("Content-Length"), Some("3".to_string()));
+    /// assert_eq!(resp.body(), "[1]");
+    /// ```
+    pub fn json(&self, status: Status, value: Value) -> Response {
+        let body = JsonFormatter.format(value);
+        let mut headers = Headers::new();
+        headers.set("Content-Type", &["application/json"]);
+        headers.set("Content-Length", &[&body.len().to_string()]);
+        self.with_status(status, headers).with_body(&body)
+    }
     /// Generate a 204-like empty response.
     ///
     /// ```
@@ -631,6 +648,26 @@ mod tests {
         };
         let resp = factory.unauthorized(www, Headers::new());
         assert_eq!(resp.status, Status::Unauthorized);
+        assert_eq!(
+            resp.get_header_line("WWW-Authenticate"),
+            Some("Basic realm=\"r\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_www_authenticate_display() {
+        let www = WWWAuthenticate {
+            scheme: AuthenticationScheme::Bearer,
+            realm: None,
+            charset: None,
+        };
+        assert_eq!(www.to_string(), "Bearer");
+        let www = WWWAuthenticate {
+            scheme: AuthenticationScheme::Basic,
+            realm: Some("api".to_string()),
+            charset: Some("UTF-8".to_string()),
+        };
+        assert_eq!(www.to_string(), "Basic realm=\"api\", charset=\"UTF-8\"");
     }
 
     #[test]
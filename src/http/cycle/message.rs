@@ -72,12 +72,53 @@ impl Parsable for Version {
     }
 }
 
+impl Version {
+    /// Parse the version token at the start of `input`, a suffix of `source`.
+    ///
+    /// Well-formed but unknown versions raise
+    /// [`ParseError::InvalidHttpVersion`], other inputs a
+    /// [`ParseError::Syntax`] error.
+    pub(crate) fn parse_at<'a>(
+        source: &str,
+        input: &'a str,
+    ) -> Result<(&'a str, Self), ParseError> {
+        let end = input
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(input.len());
+        let token = &input[..end];
+        match Version::parse(token) {
+            Ok(("", version)) => Ok((&input[end..], version)),
+            Err(nom::Err::Failure(_)) => Err(ParseError::InvalidHttpVersion(token.to_string())),
+            _ => Err(ParseError::syntax(source, input, "an HTTP version")),
+        }
+    }
+}
+
+/// Consume the spaces at the start of `input`, a suffix of `source`,
+/// requiring at least one.
+pub(crate) fn expect_space<'a>(source: &str, input: &'a str) -> Result<&'a str, ParseError> {
+    let rest = input.trim_start_matches(' ');
+    if rest.len() == input.len() {
+        return Err(ParseError::syntax(source, input, "a space"));
+    }
+    Ok(rest)
+}
+
+/// Consume the `CRLF` ending a start line at the start of `input`, a suffix
+/// of `source`, after optional trailing spaces.
+pub(crate) fn expect_line_end<'a>(source: &str, input: &'a str) -> Result<&'a str, ParseError> {
+    let rest = input.trim_start_matches(' ');
+    rest.strip_prefix("\r\n")
+        .ok_or_else(|| ParseError::syntax(source, rest, "the end of the line"))
+}
+
 #[derive(Debug, Default, Clone)]
 /// Collection of HTTP header fields.
 ///
 /// The structure stores a mapping between header names and the associated
 /// values.  Individual helper methods are provided to insert and retrieve
-/// headers in a convenient way.
+/// headers in a convenient way. Header names are case-insensitive
+/// (RFC 9110 §5.1): lookups match any case and the name first stored is kept.
 ///
 /// # Examples
 ///
@@ -88,6 +129,7 @@ impl Parsable for Version {
 /// headers.add("Content-Type", "text/plain");
 /// headers.add("Content-Type", "charset=utf8");
 /// assert_eq!(headers.get_line("Content-Type"), Some("text/plain,charset=utf8".to_string()));
+/// assert_eq!(headers.get_line("content-type"), headers.get_line("Content-Type"));
 /// ```
 pub struct Headers {
     data: HashMap<String, Vec<String>>,
@@ -107,6 +149,72 @@ impl Headers {
             Err(ParseError::InvalidHeaderFormat(input.to_string()))
         }
     }
+    /// Maximum number of header fields accepted by the parsers.
+    pub const MAX_COUNT: usize = 100;
+    /// Maximum length of a header section accepted by the parsers, in bytes.
+    pub const MAX_SIZE: usize = 64 * 1024;
+
+    /// Parse the header section at the start of `input`, a suffix of
+    /// `source`, up to and including the empty line ending it.
+    ///
+    /// Unlike [`Headers::parse`], names must be non-empty tokens and errors
+    /// locate the offending line. At most `max_count` fields and `max_size`
+    /// bytes are read.
+    pub(crate) fn parse_at<'a>(
+        source: &str,
+        input: &'a str,
+        max_count: usize,
+        max_size: usize,
+    ) -> Result<(&'a str, Self), ParseError> {
+        let mut headers = Self::new();
+        let mut count = 0;
+        let mut rest = input;
+        while !rest.is_empty() {
+            let (line, next) = match rest.find("\r\n") {
+                Some(end) => (&rest[..end], &rest[end + 2..]),
+                None => (rest, ""),
+            };
+            if line.is_empty() {
+                return Ok((next, headers));
+            }
+            count += 1;
+            if count > max_count {
+                return Err(ParseError::TooManyHeaders(max_count));
+            }
+            if input.len() - next.len() > max_size {
+                return Err(ParseError::HeadersTooLarge(max_size));
+            }
+            let invalid = || ParseError::syntax(source, rest, "a header field");
+            let (name, _) = line.split_once(':').ok_or_else(invalid)?;
+            if name.is_empty()
+                || name.contains(|c: char| c.is_ascii_whitespace() || c.is_ascii_control())
+            {
+                return Err(invalid());
+            }
+            let (_, (name, values)) = Self::parse_header(line)?;
+            if !headers.push_field(name, values) {
+                return Err(ParseError::syntax(
+                    source,
+                    rest,
+                    "a single Content-Length value",
+                ));
+            }
+            rest = next;
+        }
+        Ok(("", headers))
+    }
+
+    /// Append the values of a parsed field, returning `false` for a second
+    /// or list `Content-Length`: a message framed by several lengths could
+    /// be read differently by each recipient.
+    fn push_field(&mut self, name: String, values: Vec<String>) -> bool {
+        if name.eq_ignore_ascii_case("Content-Length") && (values.len() > 1 || self.contains(&name))
+        {
+            return false;
+        }
+        self.insert(&name, &values);
+        true
+    }
     /// Create an empty `Headers` map.
     pub fn new() -> Self {
         Self {
@@ -115,13 +223,28 @@ impl Headers {
     }
     /// Build a `Headers` collection from a slice of key/value pairs.
     pub fn from(headers: &[(&str, &[&str])]) -> Self {
-        Self {
-            data: headers
-                .iter()
-                .map(|(s, v)| (s.to_string(), v.iter().map(|s| s.to_string()).collect()))
-                .collect(),
+        let mut result = Self::new();
+        for (name, values) in headers {
+            result.set(name, values);
+        }
+        result
+    }
+    /// Name under which `key` is stored, ignoring the case.
+    fn stored_key(&self, key: &str) -> Option<&String> {
+        match self.data.get_key_value(key) {
+            Some((stored, _)) => Some(stored),
+            None => self.data.keys().find(|k| k.eq_ignore_ascii_case(key)),
         }
     }
+    fn get_mut(&mut self, key: &str) -> Option<&mut Vec<String>> {
+        let stored = self.stored_key(key)?.clone();
+        self.data.get_mut(&stored)
+    }
+    /// Replace the values of `key`, whatever the case it is stored with.
+    fn replace(&mut self, key: String, values: Vec<String>) {
+        self.remove(&key);
+        self.data.insert(key, values);
+    }
     /// Return a new `Headers` containing values from `self` and `other`.
     pub fn merge_with(&self, other: &Self) -> Self {
         let mut headers = self.clone();
@@ -148,7 +271,7 @@ impl Headers {
     }
     /// Add a new header value without removing existing ones.
     pub fn add(&mut self, key: &str, value: &str) {
-        if let Some(values) = self.data.get_mut(key) {
+        if let Some(values) = self.get_mut(key) {
             values.push(value.to_string());
             return;
         }
@@ -156,7 +279,7 @@ impl Headers {
     }
     /// Insert several values for the specified header key.
     pub fn insert(&mut self, key: &str, values: &[String]) {
-        if let Some(v) = self.data.get_mut(key) {
+        if let Some(v) = self.get_mut(key) {
             v.extend_from_slice(values);
             return;
         }
@@ -164,15 +287,23 @@ impl Headers {
     }
     /// Replace the header with a new set of values.
     pub fn set(&mut self, key: &str, values: &[&str]) {
-        self.data.remove(key);
-        self.data.insert(
+        self.replace(
             key.to_string(),
             values.iter().map(|s| s.to_string()).collect(),
         );
     }
+    /// Remove the header `key` and return its values.
+    pub fn remove(&mut self, key: &str) -> Option<Vec<String>> {
+        let stored = self.stored_key(key)?.clone();
+        self.data.remove(&stored)
+    }
+    /// Check whether the header `key` is present.
+    pub fn contains(&self, key: &str) -> bool {
+        self.stored_key(key).is_some()
+    }
     /// Retrieve the stored values for `key` if present.
     pub fn get(&self, key: &str) -> Option<&Vec<String>> {
-        self.data.get(key)
+        self.data.get(self.stored_key(key)?)
     }
     /// Return the values joined by commas as one line.
     pub fn get_line(&self, key: &str) -> Option<String> {
@@ -209,17 +340,22 @@ impl Parsable for Headers {
         } else {
             input = "";
         }
-        let mut headers = HashMap::new();
-        for line in header_lines.split("\r\n") {
-            if line.is_empty() {
-                continue;
+        let fail = |at| nom::Err::Error(nom::error::Error::new(at, nom::error::ErrorKind::Fail));
+        if header_lines.len() > Self::MAX_SIZE {
+            return Err(fail(header_lines));
+        }
+        let mut headers = Self::new();
+        for (count, line) in header_lines
+            .split("\r\n")
+            .filter(|line| !line.is_empty())
+            .enumerate()
+        {
+            let (_, (name, value)) = Self::parse_header(line).map_err(|_| fail(line))?;
+            if count >= Self::MAX_COUNT || !headers.push_field(name, value) {
+                return Err(fail(line));
             }
-            let (_, (name, value)) = Self::parse_header(line).map_err(|_| {
-                nom::Err::Error(nom::error::Error::new(line, nom::error::ErrorKind::Fail))
-            })?;
-            headers.insert(name, value);
         }
-        Ok((input, Self { data: headers }))
+        Ok((input, headers))
     }
 }
 
@@ -362,7 +498,7 @@ impl MessageTrait for Message {
         &mut self.headers
     }
     fn has_header(&self, key: &str) -> bool {
-        self.headers.data.contains_key(key)
+        self.headers.contains(key)
     }
 
     fn with_headers(self, headers: Headers) -> Self
@@ -394,7 +530,7 @@ impl MessageTrait for Message {
         Self: Sized,
     {
         let mut headers = self.headers;
-        headers.data.remove(key);
+        headers.remove(key);
         Self {
             version: self.version,
             headers,
@@ -462,6 +598,55 @@ mod tests {
         assert_eq!(h.get_line("A"), Some("x".to_string()));
     }
 
+    #[test]
+    fn test_headers_case_insensitive() {
+        let mut h = Headers::new();
+        h.add("Content-Length", "1");
+        h.add("content-length", "2");
+        assert_eq!(h.len(), 1);
+        assert_eq!(h.get_line("CONTENT-LENGTH"), Some("1,2".to_string()));
+        assert!(h.contains("content-LENGTH"));
+        h.set("content-length", &["3"]);
+        assert_eq!(h.len(), 1);
+        assert_eq!(h.to_string(), "content-length: 3");
+        assert_eq!(h.remove("Content-Length"), Some(vec!["3".to_string()]));
+        assert!(h.is_empty());
+
+        let (_, parsed) = Headers::parse("host: a\r\nHOST: b\r\n\r\n").unwrap();
+        assert_eq!(parsed.get_line("Host"), Some("a,b".to_string()));
+        let msg = Message::v1_1(Headers::from(&[("x-token", &["t"])]), String::new());
+        assert!(msg.has_header("X-Token"));
+        assert!(!msg.without_header("X-TOKEN").has_header("x-token"));
+    }
+
+    #[test]
+    fn test_headers_parse_bounds() {
+        let source = "Via: a\r\nVia: b\r\n\r\nbody";
+        let (body, parsed) = Headers::parse_at(source, source, 2, 64).unwrap();
+        assert_eq!(body, "body");
+        assert_eq!(parsed.get_line("via"), Some("a,b".to_string()));
+        assert_eq!(
+            Headers::parse_at(source, source, 1, 64).unwrap_err(),
+            ParseError::TooManyHeaders(1)
+        );
+        assert_eq!(
+            Headers::parse_at(source, source, 2, 10).unwrap_err(),
+            ParseError::HeadersTooLarge(10)
+        );
+
+        let smuggled = "Content-Length: 0\r\ncontent-length: 50\r\n\r\n";
+        assert_eq!(
+            Headers::parse_at(smuggled, smuggled, 2, 64)
+                .unwrap_err()
+                .offset(),
+            Some(19)
+        );
+        assert!(Headers::parse(smuggled).is_err());
+        assert!(Headers::parse("Content-Length: 1, 1\r\n\r\n").is_err());
+        let many = "A: 1\r\n".repeat(Headers::MAX_COUNT + 1) + "\r\n";
+        assert!(Headers::parse(&many).is_err());
+    }
+
     #[test]
     fn test_headers_merge_and_display_iter() {
         let h1 = sample_headers();
@@ -1,11 +1,16 @@
 //! Structures and utilities for HTTP requests.
+use crate::concepts::value::Value;
 use crate::concepts::{Dictionary, Parsable};
-use crate::http::{cookie::CookieJar, Headers, Message, MessageTrait, Uri, Version};
-use nom::bytes::complete::{tag, take_until, take_while1};
-use nom::character::complete::{space0, space1};
+use crate::http::cycle::message::{expect_line_end, expect_space};
+use crate::http::{
+    cookie::CookieJar, form_urldecode, form_urlencode, Headers, Message, MessageTrait, ParseError,
+    Uri, Version,
+};
+use indexmap::IndexMap;
+use nom::bytes::complete::take_while1;
 use nom::IResult;
-use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 /// Standard HTTP request methods.
@@ -51,7 +56,7 @@ impl Display for Method {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{:?}",
+            "{}",
             match self {
                 Method::Get => "GET",
                 Method::Post => "POST",
@@ -95,10 +100,10 @@ impl Method {
 
     /// Checks whether the method is defined as safe by the HTTP specification.
     pub fn is_safe(&self) -> bool {
-        match self {
-            Method::Get | Method::Head | Method::Options | Method::Trace => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            Method::Get | Method::Head | Method::Options | Method::Trace
+        )
     }
     /// Indicates if repeated requests using this method are idempotent.
     pub fn is_idempotent(&self) -> bool {
@@ -110,25 +115,26 @@ impl Method {
     }
     /// Indicates if responses to this method can be cached.
     pub fn is_cacheable(&self) -> bool {
-        match self {
-            Method::Get | Method::Head | Method::Post | Method::Patch => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            Method::Get | Method::Head | Method::Post | Method::Patch
+        )
     }
     /// Returns `true` if browsers commonly use this method in HTML forms.
     pub fn is_html_compatible(&self) -> bool {
-        match self {
-            Method::Get | Method::Post => true,
-            _ => false,
-        }
+        matches!(self, Method::Get | Method::Post)
     }
 }
 
 #[derive(Debug, Default, Clone)]
 /// Representation of a URI query string.
 ///
-/// The structure behaves like a simple map of key/value pairs and offers
-/// helpers to manipulate its contents.
+/// The structure maps each key to one or more values, kept in the order they
+/// first appeared. Keys and values are stored decoded; parsing and
+/// formatting use the `application/x-www-form-urlencoded` rules, so the
+/// same type also reads form bodies. [`Query::to_value`] builds a [`Value`]
+/// tree from nested keys such as `filter[status]` or `ids[]`;
+/// [`Query::try_to_value`] bounds it for untrusted input.
 ///
 /// # Examples
 /// ```
@@ -139,11 +145,13 @@ impl Method {
 /// q.add("a", "1");
 /// assert_eq!(q.get_line("a"), Some("1".to_string()));
 ///
-/// let (_, parsed) = Query::parse("foo=bar").unwrap();
+/// let (_, parsed) = Query::parse("foo=bar&tag=a+b&tag=c%26d").unwrap();
 /// assert_eq!(parsed.get("foo"), Some(&"bar".to_string()));
+/// assert_eq!(parsed.get_all("tag"), ["a b", "c&d"]);
+/// assert_eq!(parsed.to_string(), "foo=bar&tag=a+b&tag=c%26d");
 /// ```
 pub struct Query {
-    data: Dictionary<String>,
+    data: IndexMap<String, Vec<String>>,
 }
 
 impl Parsable for Query {
@@ -151,52 +159,62 @@ impl Parsable for Query {
     where
         Self: Sized,
     {
-        let mut map = Dictionary::new();
-        let mut query = input;
-        let mut input = input;
-        if input.contains('#') {
-            let (i, q) = take_until("#")(input)?;
-            input = i;
-            query = q;
-        } else {
-            input = "";
-        }
-        for pair in query.split('&') {
-            let mut parts = pair.splitn(2, '=');
-            let key = parts.next().unwrap_or_default();
-            let value = parts.next().unwrap_or_default();
-            map.insert(key.to_string(), value.to_string());
+        let (query, input) = match input.find('#') {
+            Some(end) => input.split_at(end),
+            None => (input, ""),
+        };
+        let mut parsed = Self::new();
+        for (key, value) in form_urldecode(query) {
+            parsed.add(&key, &value);
         }
-
-        Ok((input, Self { data: map }))
+        Ok((input, parsed))
     }
 }
 
 impl Query {
+    /// Maximum number of parameters accepted by [`Query::try_to_value`].
+    pub const MAX_PARAMETERS: usize = 1000;
+    /// Maximum nesting of a key accepted by [`Query::try_to_value`]: `a[b][]`
+    /// is nested twice.
+    pub const MAX_DEPTH: usize = 32;
+
     /// Create an empty query string representation.
     pub fn new() -> Self {
         Self {
-            data: HashMap::new(),
+            data: IndexMap::new(),
         }
     }
-    /// Append a key/value pair to the query.
+    /// Append a value for `key`, keeping the existing ones.
     pub fn add(&mut self, key: &str, value: &str) {
-        self.data.insert(key.to_string(), value.to_string());
+        match self.data.get_mut(key) {
+            Some(values) => values.push(value.to_string()),
+            None => {
+                self.data.insert(key.to_string(), vec![value.to_string()]);
+            }
+        }
     }
     /// Replace any existing value for `key` with `value`.
     pub fn set(&mut self, key: &str, value: &str) {
-        self.data.remove(key);
-        self.data.insert(key.to_string(), value.to_string());
+        match self.data.get_mut(key) {
+            Some(values) => *values = vec![value.to_string()],
+            None => {
+                self.data.insert(key.to_string(), vec![value.to_string()]);
+            }
+        }
     }
-    /// Remove the value associated with `key`.
+    /// Remove the values associated with `key`.
     pub fn remove(&mut self, key: &str) {
-        self.data.remove(key);
+        self.data.shift_remove(key);
     }
-    /// Get a reference to the stored value for `key` if any.
+    /// Get a reference to the last value stored for `key` if any.
     pub fn get(&self, key: &str) -> Option<&String> {
-        self.data.get(key)
+        self.get_all(key).last()
     }
-    /// Return the value for `key` as an owned string.
+    /// Get all the values stored for `key`, in order.
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.data.get(key).map(Vec::as_slice).unwrap_or_default()
+    }
+    /// Return the last value for `key` as an owned string.
     pub fn get_line(&self, key: &str) -> Option<String> {
         self.get(key).map(|v| v.to_string())
     }
@@ -204,15 +222,149 @@ impl Query {
     pub fn has(&self, key: &str) -> bool {
         self.data.contains_key(key)
     }
+    /// Returns `true` if the query has no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+    /// Iterate over the keys, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.data.keys().map(String::as_str)
+    }
+    /// Iterate over the key/value pairs, grouped by key, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.data
+            .iter()
+            .flat_map(|(k, values)| values.iter().map(move |v| (k.as_str(), v.as_str())))
+    }
+
+    /// Build a [`Value`] tree from the parameters.
+    ///
+    /// `a[b]=1` sets the `b` entry of the `a` dictionary and `a[]=1` appends
+    /// to the `a` array; both can be nested. Repeated keys collect their
+    /// values into an array. Keys not following these conventions, or nested
+    /// deeper than [`Query::MAX_DEPTH`], are used as is.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::concepts::Parsable;
+    /// use hermes::http::Query;
+    ///
+    /// let (_, query) = Query::parse("filter[status]=open&ids[]=1&ids[]=2&q=x").unwrap();
+    /// let Value::Dictionary(root) = query.to_value() else { unreachable!() };
+    /// let Some(Value::Dictionary(filter)) = root.get("filter") else { unreachable!() };
+    /// assert_eq!(filter.get("status"), Some(&Value::String("open".into())));
+    /// assert_eq!(
+    ///     root.get("ids"),
+    ///     Some(&Value::Array(vec![Value::String("1".into()), Value::String("2".into())]))
+    /// );
+    /// assert_eq!(root.get("q"), Some(&Value::String("x".into())));
+    /// ```
+    pub fn to_value(&self) -> Value {
+        let mut root = Value::Dictionary(Dictionary::new());
+        for (key, value) in self.iter() {
+            let mut path = Self::key_path(key);
+            if path.len() > Self::MAX_DEPTH + 1 {
+                path = vec![key];
+            }
+            Self::insert(&mut root, &path, value);
+        }
+        root
+    }
+
+    /// Like [`Query::to_value`], for untrusted input: fails with more than
+    /// [`Query::MAX_PARAMETERS`] values or keys nested deeper than
+    /// [`Query::MAX_DEPTH`].
+    ///
+    /// ```
+    /// use hermes::concepts::Parsable;
+    /// use hermes::http::{ParseError, Query};
+    ///
+    /// let (_, query) = Query::parse("a[b][c]=1").unwrap();
+    /// assert_eq!(query.try_to_value(), Ok(query.to_value()));
+    ///
+    /// let (_, query) = Query::parse(&format!("a{}=1", "[]".repeat(33))).unwrap();
+    /// assert_eq!(query.try_to_value(), Err(ParseError::NestingTooDeep(32)));
+    /// ```
+    pub fn try_to_value(&self) -> Result<Value, ParseError> {
+        if self.iter().nth(Self::MAX_PARAMETERS).is_some() {
+            return Err(ParseError::TooManyParameters(Self::MAX_PARAMETERS));
+        }
+        if self
+            .keys()
+            .any(|key| Self::key_path(key).len() > Self::MAX_DEPTH + 1)
+        {
+            return Err(ParseError::NestingTooDeep(Self::MAX_DEPTH));
+        }
+        Ok(self.to_value())
+    }
+
+    /// Split `a[b][]` into `["a", "b", ""]`.
+    fn key_path(key: &str) -> Vec<&str> {
+        let Some(open) = key.find('[').filter(|open| *open > 0) else {
+            return vec![key];
+        };
+        let mut path = vec![&key[..open]];
+        let mut rest = &key[open..];
+        while let Some(inner) = rest.strip_prefix('[') {
+            let Some(close) = inner.find(']') else {
+                return vec![key];
+            };
+            path.push(&inner[..close]);
+            rest = &inner[close + 1..];
+        }
+        if rest.is_empty() {
+            path
+        } else {
+            vec![key]
+        }
+    }
+
+    fn insert(node: &mut Value, path: &[&str], value: &str) {
+        let (segment, rest) = (path[0], &path[1..]);
+        let container = |rest: &[&str]| match rest.first() {
+            Some(&"") => Value::Array(Vec::new()),
+            _ => Value::Dictionary(Dictionary::new()),
+        };
+        let leaf = Value::String(value.to_string());
+        let fits = match node {
+            Value::Array(_) => segment.is_empty(),
+            Value::Dictionary(_) => !segment.is_empty(),
+            _ => false,
+        };
+        if !fits {
+            *node = container(path);
+        }
+        match node {
+            Value::Array(items) if rest.is_empty() => items.push(leaf),
+            Value::Array(items) => {
+                let mut child = container(rest);
+                Self::insert(&mut child, rest, value);
+                items.push(child);
+            }
+            Value::Dictionary(map) if rest.is_empty() => match map.get_mut(segment) {
+                Some(Value::Array(values)) => values.push(leaf),
+                Some(existing @ Value::String(_)) => {
+                    let previous = std::mem::replace(existing, Value::Null);
+                    *existing = Value::Array(vec![previous, leaf]);
+                }
+                _ => {
+                    map.insert(segment.to_string(), leaf);
+                }
+            },
+            Value::Dictionary(map) => {
+                let child = map
+                    .entry(segment.to_string())
+                    .or_insert_with(|| container(rest));
+                Self::insert(child, rest, value);
+            }
+            _ => unreachable!("node replaced by a container"),
+        }
+    }
 }
 
 impl Display for Query {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut strings = Vec::new();
-        for (key, value) in self.data.iter() {
-            strings.push(format!("{}={}", key, value));
-        }
-        write!(f, "{}", strings.join("&"))
+        write!(f, "{}", form_urlencode(self.iter()))
     }
 }
 
@@ -230,39 +382,93 @@ pub trait RequestTrait: MessageTrait {
     fn with_uri(self, uri: Uri, preserve_host: bool) -> Self
     where
         Self: Sized;
+    /// Store the parameters captured by the router for the matched route.
+    ///
+    /// The default implementation discards them.
+    fn set_route_params(&mut self, _params: Dictionary<String>) {}
 }
 
 #[derive(Debug, Clone)]
 /// Representation of an HTTP request message.
+///
+/// Besides the data read from the wire, a request carries `attributes`:
+/// values attached by the server side (middleware, router) and exposed to
+/// controllers. They are never parsed from nor written to the message.
+///
+/// A body which is not valid UTF-8, such as a binary upload, is kept as
+/// received in `raw_body`, the message holding a lossy copy; use
+/// [`Request::body_bytes`] to read it.
 pub struct Request {
     pub method: Method,
     pub target: Uri,
     pub message: Message,
+    pub attributes: Dictionary<Value>,
+    pub raw_body: Option<Vec<u8>>,
 }
 
 impl Parsable for Request {
+    /// Parse a complete request with [`Request::from_str`], reporting the
+    /// location of its [`ParseError`] as a `nom` error.
     fn parse(input: &str) -> IResult<&str, Self>
     where
         Self: Sized,
     {
-        let (input, method) = Method::parse(input)?;
-        let (input, _) = space1(input)?;
-        let (input, uri) = take_until(" ")(input)?;
-        let (_, target) = Uri::parse(uri)?;
-        let (input, _) = space1(input)?;
-        let (input, version) = Version::parse(input)?;
-        let (input, _) = space0(input)?;
-        let (input, _) = tag("\r\n")(input)?;
-        let (_, mut message) = Message::parse(input)?;
-        message = message.with_protocol_version(version);
-        Ok((
-            "",
-            Self {
-                method,
-                target,
-                message,
+        let request = Self::from_str(input).map_err(|e| e.into_nom(input))?;
+        Ok(("", request))
+    }
+}
+
+impl FromStr for Request {
+    type Err = ParseError;
+
+    /// Parse a complete request, locating the offending input on errors.
+    ///
+    /// The header names must be tokens and the request line must end with
+    /// `CRLF`. At most [`Headers::MAX_COUNT`] header fields and
+    /// [`Headers::MAX_SIZE`] bytes of headers are read, and a message with
+    /// several `Content-Length` values is rejected. The remaining input is
+    /// the body.
+    fn from_str(source: &str) -> Result<Self, ParseError> {
+        Self::parse_bounded(source, Headers::MAX_COUNT, Headers::MAX_SIZE)
+    }
+}
+
+impl Request {
+    /// Like [`Request::from_str`], reading at most `max_count` header fields
+    /// and `max_size` bytes of headers.
+    pub(crate) fn parse_bounded(
+        source: &str,
+        max_count: usize,
+        max_size: usize,
+    ) -> Result<Self, ParseError> {
+        let (input, method) = Method::parse(source)
+            .map_err(|_| ParseError::syntax(source, source, "a request method"))?;
+        let input = expect_space(source, input)?;
+        let line_end = input.find("\r\n").unwrap_or(input.len());
+        let end = input[..line_end]
+            .find(' ')
+            .ok_or_else(|| ParseError::syntax(source, &input[line_end..], "a space"))?;
+        if end == 0 {
+            return Err(ParseError::syntax(source, input, "a request target"));
+        }
+        let target = input[..end]
+            .parse::<Uri>()
+            .map_err(|e| e.shifted(source.len() - input.len()))?;
+        let input = expect_space(source, &input[end..])?;
+        let (input, version) = Version::parse_at(source, input)?;
+        let input = expect_line_end(source, input)?;
+        let (body, headers) = Headers::parse_at(source, input, max_count, max_size)?;
+        Ok(Self {
+            method,
+            target,
+            message: Message {
+                version,
+                headers,
+                body: body.to_string(),
             },
-        ))
+            attributes: Dictionary::new(),
+            raw_body: None,
+        })
     }
 }
 
@@ -279,6 +485,8 @@ impl MessageTrait for Request {
             method: self.method,
             target: self.target,
             message: self.message.with_protocol_version(version),
+            attributes: self.attributes,
+            raw_body: self.raw_body,
         }
     }
 
@@ -302,6 +510,8 @@ impl MessageTrait for Request {
             method: self.method,
             target: self.target,
             message: self.message.with_headers(headers),
+            attributes: self.attributes,
+            raw_body: self.raw_body,
         }
     }
 
@@ -313,6 +523,8 @@ impl MessageTrait for Request {
             method: self.method,
             target: self.target,
             message: self.message.with_added_header(key, value),
+            attributes: self.attributes,
+            raw_body: self.raw_body,
         }
     }
 
@@ -324,6 +536,8 @@ impl MessageTrait for Request {
             method: self.method,
             target: self.target,
             message: self.message.without_header(key),
+            attributes: self.attributes,
+            raw_body: self.raw_body,
         }
     }
 
@@ -339,6 +553,8 @@ impl MessageTrait for Request {
             method: self.method,
             target: self.target,
             message: self.message.with_body(body),
+            attributes: self.attributes,
+            raw_body: None,
         }
     }
 }
@@ -357,6 +573,8 @@ impl RequestTrait for Request {
             method,
             target: self.target,
             message: self.message,
+            attributes: self.attributes,
+            raw_body: self.raw_body,
         }
     }
 
@@ -378,11 +596,32 @@ impl RequestTrait for Request {
             method: self.method,
             target: uri,
             message: self.message.with_headers(headers),
+            attributes: self.attributes,
+            raw_body: self.raw_body,
         }
     }
+
+    /// Store `params` as a dictionary under [`Request::PARAMS_ATTRIBUTE`].
+    fn set_route_params(&mut self, params: Dictionary<String>) {
+        let params = params
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect();
+        self.set_attribute(Self::PARAMS_ATTRIBUTE, Value::Dictionary(params));
+    }
 }
 
 impl Request {
+    /// Attribute holding the parameters of the matched route.
+    pub const PARAMS_ATTRIBUTE: &'static str = "params";
+    /// Attribute holding the address of the client, as seen by the server.
+    ///
+    /// Parsing never sets it: the [`Server`](crate::http::services::server::Server)
+    /// does when reading a request, and code accepting connections itself
+    /// must, for instance for
+    /// [`KeyBy::Ip`](crate::http::security::rate_limit::KeyBy::Ip).
+    pub const REMOTE_ADDR_ATTRIBUTE: &'static str = "remote_addr";
+
     /// Parse cookies from the `Cookie` header if present.
     pub fn cookies(&self) -> CookieJar {
         if let Some(line) = self.get_header_line("Cookie") {
@@ -391,6 +630,100 @@ impl Request {
             CookieJar::new()
         }
     }
+
+    /// Retrieve the attribute stored under `name`, if any.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::{Headers, Method, RequestFactory, Uri, Version};
+    ///
+    /// let req = RequestFactory::version(Version::Http1_1)
+    ///     .get(Uri::default(), Headers::new())
+    ///     .with_attribute("user", Value::String("alice".into()));
+    /// assert_eq!(req.attribute("user"), Some(&Value::String("alice".into())));
+    /// assert!(req.attribute("missing").is_none());
+    /// ```
+    pub fn attribute(&self, name: &str) -> Option<&Value> {
+        self.attributes.get(name)
+    }
+
+    /// Store `value` under `name`, replacing any previous attribute.
+    pub fn set_attribute(&mut self, name: impl Into<String>, value: Value) {
+        self.attributes.insert(name.into(), value);
+    }
+
+    /// Remove the attribute stored under `name` and return it.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<Value> {
+        self.attributes.shift_remove(name)
+    }
+
+    /// Return a new request with the attribute `name` set to `value`.
+    pub fn with_attribute(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.set_attribute(name, value);
+        self
+    }
+
+    /// The body as received: [`Request::raw_body`] when set, the text body
+    /// otherwise.
+    ///
+    /// ```
+    /// use hermes::http::{Headers, MessageTrait, RequestFactory, Uri, Version};
+    ///
+    /// let req = RequestFactory::version(Version::Http1_1)
+    ///     .post(Uri::default(), Headers::new(), "")
+    ///     .with_body_bytes(vec![0x89, b'P', b'N', b'G']);
+    /// assert_eq!(req.body_bytes(), [0x89, b'P', b'N', b'G']);
+    /// assert_eq!(req.body(), "\u{FFFD}PNG");
+    ///
+    /// let req = req.with_body("text");
+    /// assert_eq!(req.body_bytes(), b"text");
+    /// ```
+    pub fn body_bytes(&self) -> &[u8] {
+        match &self.raw_body {
+            Some(bytes) => bytes,
+            None => self.message.body.as_bytes(),
+        }
+    }
+
+    /// Return a new request with the body `bytes`, kept in
+    /// [`Request::raw_body`] when they are not valid UTF-8.
+    pub fn with_body_bytes(mut self, bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => {
+                self.message.body = text;
+                self.raw_body = None;
+            }
+            Err(e) => {
+                let bytes = e.into_bytes();
+                self.message.body = String::from_utf8_lossy(&bytes).to_string();
+                self.raw_body = Some(bytes);
+            }
+        }
+        self
+    }
+
+    /// Retrieve the route parameter `name` captured by the router.
+    ///
+    /// ```
+    /// use hermes::concepts::Dictionary;
+    /// use hermes::http::{Headers, RequestFactory, RequestTrait, Uri, Version};
+    ///
+    /// let mut req = RequestFactory::version(Version::Http1_1).get(Uri::default(), Headers::new());
+    /// let mut params = Dictionary::new();
+    /// params.insert("id".to_string(), "42".to_string());
+    /// req.set_route_params(params);
+    /// assert_eq!(req.param("id"), Some("42"));
+    /// assert_eq!(req.param("missing"), None);
+    /// ```
+    pub fn param(&self, name: &str) -> Option<&str> {
+        match self.attribute(Self::PARAMS_ATTRIBUTE) {
+            Some(Value::Dictionary(params)) => match params.get(name) {
+                Some(Value::String(value)) => Some(value),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
 impl Display for Request {
@@ -460,7 +793,7 @@ mod tests {
     fn test_method_properties() {
         for (s, m) in [("GET", Method::Get), ("POST", Method::Post)] {
             assert_eq!(Method::parse(s).unwrap().1, m);
-            assert!(m.to_string().contains(s));
+            assert_eq!(m.to_string(), s);
         }
         assert!(Method::Post.request_has_body());
         assert!(Method::Get.response_has_body());
@@ -510,6 +843,8 @@ mod tests {
             method: Method::Get,
             target: uri.clone(),
             message: Message::v1_1(Headers::from(&[("Host", &["host"])]), String::new()),
+            attributes: Dictionary::new(),
+            raw_body: None,
         };
         assert!(req.get_target().starts_with("http://host"));
         assert_eq!(req.get_method(), Method::Get);
@@ -547,9 +882,140 @@ mod tests {
             method: Method::Get,
             target: uri,
             message: Message::v1_1(headers, String::new()),
+            attributes: Dictionary::new(),
+            raw_body: None,
         };
         let cookies = req.cookies();
         assert_eq!(cookies.get("a"), Some(&"1".to_string()));
         assert_eq!(cookies.get("b"), Some(&"2".to_string()));
     }
+
+    #[test]
+    fn test_request_from_str() {
+        let request = "POST /a?b=c HTTP/1.1\r\nHost: x\r\n\r\nbody"
+            .parse::<Request>()
+            .unwrap();
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.target.query().get("b"), Some(&"c".to_string()));
+        assert_eq!(request.get_header_line("Host"), Some("x".to_string()));
+        assert_eq!(request.body(), "body");
+
+        let syntax = |input: &str| match input.parse::<Request>().unwrap_err() {
+            ParseError::Syntax {
+                expected,
+                offset,
+                found,
+            } => (expected, offset, found),
+            other => panic!("unexpected error: {:?}", other),
+        };
+        assert_eq!(
+            syntax("(GET / HTTP/1.1\r\n\r\n"),
+            ("a request method".into(), 0, "(GET / HTTP/1.1".into())
+        );
+        assert_eq!(
+            syntax("GET /a\tb HTTP/1.1\r\n\r\n"),
+            ("a URI character".into(), 6, "\tb".into())
+        );
+        assert_eq!(
+            syntax("GET / HTTQ/1.1\r\n\r\n"),
+            ("an HTTP version".into(), 6, "HTTQ/1.1".into())
+        );
+        assert_eq!(
+            syntax("GET / HTTP/1.1 x\r\n\r\n"),
+            ("the end of the line".into(), 15, "x".into())
+        );
+        assert_eq!(
+            syntax("GET / HTTP/1.1\r\nHost: x\r\nBad Header: y\r\n\r\n"),
+            ("a header field".into(), 25, "Bad Header: y".into())
+        );
+        assert_eq!(
+            "GET / HTTP/1.7\r\n\r\n".parse::<Request>().unwrap_err(),
+            ParseError::InvalidHttpVersion("HTTP/1.7".into())
+        );
+    }
+
+    #[test]
+    fn test_query_multi_values() {
+        let (_, mut query) = Query::parse("tag=a&x=%2B1&tag=b+c&flag").unwrap();
+        assert_eq!(query.get_all("tag"), ["a", "b c"]);
+        assert_eq!(query.get("tag"), Some(&"b c".to_string()));
+        assert_eq!(query.get("x"), Some(&"+1".to_string()));
+        assert_eq!(query.get("flag"), Some(&"".to_string()));
+        assert!(query.get_all("missing").is_empty());
+        assert_eq!(query.keys().collect::<Vec<_>>(), ["tag", "x", "flag"]);
+        assert_eq!(
+            query.iter().collect::<Vec<_>>(),
+            [("tag", "a"), ("tag", "b c"), ("x", "+1"), ("flag", "")]
+        );
+        assert_eq!(query.to_string(), "tag=a&tag=b+c&x=%2B1&flag=");
+        query.set("tag", "d");
+        query.remove("flag");
+        assert_eq!(query.to_string(), "tag=d&x=%2B1");
+    }
+
+    #[test]
+    fn test_query_to_value() {
+        let (_, query) = Query::parse(
+            "user[name]=Ann&user[roles][]=admin&user[roles][]=dev&tag=a&tag=b\
+             &items[][id]=1&items[][id]=2&odd]=1&[x]=2&bad[a=3",
+        )
+        .unwrap();
+        let s = |v: &str| Value::String(v.to_string());
+        let dict = |pairs: &[(&str, Value)]| {
+            Value::Dictionary(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect(),
+            )
+        };
+        let expected = dict(&[
+            (
+                "user",
+                dict(&[
+                    ("name", s("Ann")),
+                    ("roles", Value::Array(vec![s("admin"), s("dev")])),
+                ]),
+            ),
+            ("tag", Value::Array(vec![s("a"), s("b")])),
+            (
+                "items",
+                Value::Array(vec![dict(&[("id", s("1"))]), dict(&[("id", s("2"))])]),
+            ),
+            ("odd]", s("1")),
+            ("[x]", s("2")),
+            ("bad[a", s("3")),
+        ]);
+        assert_eq!(query.to_value(), expected);
+
+        // later conflicting shapes replace earlier ones
+        let (_, query) = Query::parse("a=1&a[b]=2").unwrap();
+        assert_eq!(query.to_value(), dict(&[("a", dict(&[("b", s("2"))]))]));
+    }
+
+    #[test]
+    fn test_query_bounds() {
+        // A huge nesting is refused, or kept as a plain key.
+        let key = format!("a{}", "[]".repeat(100_000));
+        let (_, query) = Query::parse(&format!("{}=1", key)).unwrap();
+        assert_eq!(query.try_to_value(), Err(ParseError::NestingTooDeep(32)));
+        let Value::Dictionary(root) = query.to_value() else {
+            unreachable!()
+        };
+        assert_eq!(root.get(&key), Some(&Value::String("1".into())));
+        let (_, query) = Query::parse(&format!("a{}=1", "[]".repeat(32))).unwrap();
+        assert!(query.try_to_value().is_ok());
+
+        let many = (0..=Query::MAX_PARAMETERS)
+            .map(|i| format!("k{}=1", i))
+            .collect::<Vec<_>>()
+            .join("&");
+        let (_, query) = Query::parse(&many).unwrap();
+        assert_eq!(query.keys().count(), Query::MAX_PARAMETERS + 1);
+        assert_eq!(query.get("k1000"), Some(&"1".to_string()));
+        assert_eq!(
+            query.try_to_value(),
+            Err(ParseError::TooManyParameters(Query::MAX_PARAMETERS))
+        );
+    }
 }
@@ -1,13 +1,15 @@
 //! Structures and helpers for HTTP responses.
 use crate::concepts::Parsable;
 use crate::http::cookie::Cookie;
+use crate::http::cycle::message::{expect_line_end, expect_space};
 use crate::http::Headers;
-use crate::http::{Message, MessageTrait, Version};
+use crate::http::{Message, MessageTrait, ParseError, Version};
 use nom::bytes::complete::take_until;
-use nom::character::complete::{digit1, space0, space1};
+use nom::character::complete::{digit1, space0};
 use nom::combinator::opt;
 use nom::IResult;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 /// HTTP status codes and reasons recognized by the library.
@@ -527,16 +529,49 @@ pub struct Response {
 }
 
 impl Parsable for Response {
+    /// Parse a complete response with [`Response::from_str`], reporting the
+    /// location of its [`ParseError`] as a `nom` error.
     fn parse(input: &str) -> IResult<&str, Self>
     where
         Self: Sized,
     {
-        let (input, version) = Version::parse(input)?;
-        let (input, _) = space1(input)?;
-        let (input, status) = Status::parse(input)?;
-        let (_, mut message) = Message::parse(input)?;
-        message = message.with_protocol_version(version);
-        Ok((input, Self { status, message }))
+        let response = Self::from_str(input).map_err(|e| e.into_nom(input))?;
+        Ok(("", response))
+    }
+}
+
+impl FromStr for Response {
+    type Err = ParseError;
+
+    /// Parse a complete response, locating the offending input on errors.
+    fn from_str(source: &str) -> Result<Self, ParseError> {
+        let (input, version) = Version::parse_at(source, source)?;
+        let input = expect_space(source, input)?;
+        let digits = input.bytes().take_while(u8::is_ascii_digit).count();
+        if digits != 3 {
+            return Err(ParseError::syntax(
+                source,
+                input,
+                "a three-digit status code",
+            ));
+        }
+        let line_end = input.find("\r\n").unwrap_or(input.len());
+        if line_end > 3 && !input[3..].starts_with(' ') {
+            return Err(ParseError::syntax(source, &input[3..], "a space"));
+        }
+        let (_, status) = Status::parse(&input[..line_end])
+            .map_err(|e| ParseError::from_nom(source, e, "a status line"))?;
+        let input = expect_line_end(source, &input[line_end..])?;
+        let (body, headers) =
+            Headers::parse_at(source, input, Headers::MAX_COUNT, Headers::MAX_SIZE)?;
+        Ok(Self {
+            status,
+            message: Message {
+                version,
+                headers,
+                body: body.to_string(),
+            },
+        })
     }
 }
 
@@ -689,4 +724,30 @@ mod tests {
             .with_cookie(Cookie::new("a", "1"));
         assert_eq!(resp.get_header_line("Set-Cookie"), Some("a=1".to_string()));
     }
+
+    #[test]
+    fn test_response_from_str() {
+        let response = "HTTP/1.1 404 Not Found\r\nA: b\r\n\r\nmissing"
+            .parse::<Response>()
+            .unwrap();
+        assert_eq!(response.status, Status::NotFound);
+        assert_eq!(response.get_header_line("A"), Some("b".to_string()));
+        assert_eq!(response.body(), "missing");
+
+        let err = "HTTP/1.1 20 OK\r\n\r\n".parse::<Response>().unwrap_err();
+        assert_eq!(err.offset(), Some(9));
+        assert_eq!(
+            err.to_string(),
+            "Expected a three-digit status code at byte 9, found \"20 OK\""
+        );
+        let err = "HTTP/1.1 2000\r\n\r\n".parse::<Response>().unwrap_err();
+        assert_eq!(err.offset(), Some(9));
+        let err = "HTTP/1.1 200OK\r\n\r\n".parse::<Response>().unwrap_err();
+        assert_eq!(err.offset(), Some(12));
+        let err = "HTTP/1.1 200 OK".parse::<Response>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected the end of the line at byte 15, found end of line"
+        );
+    }
 }
@@ -1,6 +1,36 @@
 //! Error types for HTTP parsing operations.
+use crate::http::Status;
 use std::fmt::{self, Display};
 
+/// Errors raised while parsing HTTP messages and their components.
+///
+/// The [`FromStr`](std::str::FromStr) implementations of
+/// [`Request`](crate::http::Request), [`Response`](crate::http::Response) and
+/// [`Uri`](crate::http::Uri) report [`ParseError::Syntax`] errors locating
+/// the offending input. Their [`Parsable`](crate::concepts::Parsable)
+/// implementations delegate to them and report the same location as a `nom`
+/// error. [`ParseError::status`] gives the status a server should answer
+/// with.
+///
+/// # Examples
+///
+/// ```
+/// use hermes::http::{ParseError, Request, Status};
+///
+/// let err = "GET /\r\n\r\n".parse::<Request>().unwrap_err();
+/// assert_eq!(
+///     err,
+///     ParseError::Syntax {
+///         expected: "a space".to_string(),
+///         offset: 5,
+///         found: "".to_string(),
+///     }
+/// );
+/// assert_eq!(err.status(), Status::BadRequest);
+///
+/// let err = "GET / HTTP/4.2\r\n\r\n".parse::<Request>().unwrap_err();
+/// assert_eq!(err.status(), Status::HTTPVersionNotSupported);
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
     /// An invalid HTTP version was encountered.
@@ -11,6 +41,116 @@ pub enum ParseError {
     InvalidPort(String),
     /// A header line could not be parsed.
     InvalidHeaderFormat(String),
+    /// The input does not match the expected grammar.
+    Syntax {
+        /// Description of what was expected.
+        expected: String,
+        /// Byte offset of the offending input.
+        offset: usize,
+        /// Excerpt of the offending input, up to the end of its line.
+        found: String,
+    },
+    /// The request line is longer than the given limit, in bytes.
+    RequestLineTooLong(usize),
+    /// The header section is larger than the allowed limits.
+    HeadersTooLarge(usize),
+    /// There are more header fields than the given limit.
+    TooManyHeaders(usize),
+    /// A query string or a form has more parameters than the given limit.
+    TooManyParameters(usize),
+    /// A parameter key is nested deeper than the given limit.
+    NestingTooDeep(usize),
+}
+
+impl ParseError {
+    /// Maximum length of [`ParseError::Syntax::found`] excerpts.
+    const EXCERPT_LENGTH: usize = 32;
+
+    /// Build a [`ParseError::Syntax`] error for the position of `at` within
+    /// `source`, where `at` is a suffix of `source`.
+    pub(crate) fn syntax(source: &str, at: &str, expected: &str) -> Self {
+        let line = at.split(['\r', '\n']).next().unwrap_or_default();
+        let mut end = line.len().min(Self::EXCERPT_LENGTH);
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        Self::Syntax {
+            expected: expected.to_string(),
+            offset: source.len() - at.len(),
+            found: line[..end].to_string(),
+        }
+    }
+
+    /// Build a [`ParseError::Syntax`] error from a `nom` error raised while
+    /// parsing `source`.
+    pub(crate) fn from_nom(
+        source: &str,
+        err: nom::Err<nom::error::Error<&str>>,
+        expected: &str,
+    ) -> Self {
+        let offset = match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                (e.input.as_ptr() as usize).saturating_sub(source.as_ptr() as usize)
+            }
+            nom::Err::Incomplete(_) => source.len(),
+        };
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        Self::syntax(source, &source[offset..], expected)
+    }
+
+    /// Shift the offset of a [`ParseError::Syntax`] error by `delta` bytes,
+    /// for errors raised while parsing a slice of a larger input.
+    pub(crate) fn shifted(self, delta: usize) -> Self {
+        match self {
+            Self::Syntax {
+                expected,
+                offset,
+                found,
+            } => Self::Syntax {
+                expected,
+                offset: offset + delta,
+                found,
+            },
+            other => other,
+        }
+    }
+
+    /// Convert the error into a `nom` error pointing into `source`, for
+    /// [`Parsable`](crate::concepts::Parsable) implementations delegating
+    /// to [`FromStr`](std::str::FromStr).
+    pub(crate) fn into_nom(self, source: &str) -> nom::Err<nom::error::Error<&str>> {
+        let mut offset = self.offset().unwrap_or_default().min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        nom::Err::Error(nom::error::Error::new(
+            &source[offset..],
+            nom::error::ErrorKind::Fail,
+        ))
+    }
+
+    /// Byte offset of the error in the input, when known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Syntax { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Status a server should answer a request failing with this error.
+    pub fn status(&self) -> Status {
+        match self {
+            Self::InvalidHttpVersion(_) => Status::HTTPVersionNotSupported,
+            Self::RequestLineTooLong(_) => Status::RequestURITooLong,
+            Self::HeadersTooLarge(_) | Self::TooManyHeaders(_) => {
+                Status::RequestHeaderFieldsTooLarge
+            }
+            _ => Status::BadRequest,
+        }
+    }
 }
 
 impl Display for ParseError {
@@ -24,8 +164,114 @@ impl Display for ParseError {
             ParseError::InvalidHeaderFormat(line) => {
                 write!(f, "Invalid header format: {}", line)
             }
+            ParseError::Syntax {
+                expected,
+                offset,
+                found,
+            } if found.is_empty() => {
+                write!(
+                    f,
+                    "Expected {} at byte {}, found end of line",
+                    expected, offset
+                )
+            }
+            ParseError::Syntax {
+                expected,
+                offset,
+                found,
+            } => write!(
+                f,
+                "Expected {} at byte {}, found {:?}",
+                expected, offset, found
+            ),
+            ParseError::RequestLineTooLong(limit) => {
+                write!(f, "Request line longer than {} bytes", limit)
+            }
+            ParseError::HeadersTooLarge(limit) => {
+                write!(f, "Header fields larger than {} bytes", limit)
+            }
+            ParseError::TooManyHeaders(limit) => {
+                write!(f, "More than {} header fields", limit)
+            }
+            ParseError::TooManyParameters(limit) => {
+                write!(f, "More than {} parameters", limit)
+            }
+            ParseError::NestingTooDeep(limit) => {
+                write!(f, "Parameter nested deeper than {} levels", limit)
+            }
         }
     }
 }
 
 impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_excerpt() {
+        let source = "GET /a b\r\nHost: x";
+        let err = ParseError::syntax(source, &source[7..], "a space");
+        assert_eq!(err.offset(), Some(7));
+        assert_eq!(
+            err.to_string(),
+            "Expected a space at byte 7, found \"b\"".to_string()
+        );
+        let long = "é".repeat(40);
+        let ParseError::Syntax { found, .. } = ParseError::syntax(&long, &long, "x") else {
+            unreachable!()
+        };
+        assert_eq!(found, "é".repeat(16));
+        assert_eq!(err.shifted(3).offset(), Some(10));
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(
+            ParseError::InvalidHttpVersion("HTTP/9.9".into()).status(),
+            Status::HTTPVersionNotSupported
+        );
+        assert_eq!(
+            ParseError::RequestLineTooLong(10).status(),
+            Status::RequestURITooLong
+        );
+        assert_eq!(
+            ParseError::HeadersTooLarge(10).status(),
+            Status::RequestHeaderFieldsTooLarge
+        );
+        assert_eq!(
+            ParseError::TooManyHeaders(3).status(),
+            Status::RequestHeaderFieldsTooLarge
+        );
+        assert_eq!(
+            ParseError::TooManyHeaders(3).to_string(),
+            "More than 3 header fields"
+        );
+        assert_eq!(
+            ParseError::InvalidHeaderFormat("x".into()).status(),
+            Status::BadRequest
+        );
+    }
+
+    #[test]
+    fn test_parsable_delegates_to_from_str() {
+        use crate::concepts::Parsable;
+        use crate::http::{Request, Response, Uri};
+
+        let source = "GET /a b HTTP/1.1\r\n\r\n";
+        let strict = source.parse::<Request>().unwrap_err();
+        let Err(nom::Err::Error(err)) = Request::parse(source) else {
+            panic!("expected an error");
+        };
+        assert_eq!(source.len() - err.input.len(), strict.offset().unwrap());
+
+        // Both paths reject the characters never allowed in URIs.
+        assert!("/a b".parse::<Uri>().is_err());
+        assert!(Uri::parse("/a b").is_err());
+        assert!(Response::parse("HTTP/1.1 20 OK\r\n\r\n").is_err());
+        let (rest, response) = Response::parse("HTTP/1.1 200 OK\r\nA: 1\r\n\r\nbody").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(response.message.body, "body");
+    }
+}
@@ -0,0 +1,818 @@
+//! Typed extraction of request data.
+//!
+//! [`FromValue`] converts a [`Value`] into a Rust type. Implementations for
+//! structures use [`Fields`], which reads each field and accumulates the
+//! failures in [`FieldErrors`] keyed by field name (nested fields are joined
+//! with dots, e.g. `address.city` or `tags.1`).
+//!
+//! [`Request::query_as`], [`Request::form_as`] and [`Request::json_as`]
+//! extract the query string, an `application/x-www-form-urlencoded` body or
+//! a JSON body. Query and form values are strings: scalars are converted
+//! from their textual form. Failures are [`ExtractError`]s, rendered by
+//! [`ExtractError::to_response`] as a `400`, `415` or `422` response with a
+//! JSON body.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::concepts::value::Value;
+//! use hermes::concepts::Parsable;
+//! use hermes::http::extract::{FieldErrors, Fields, FromValue};
+//! use hermes::http::{Headers, RequestFactory, Status, Uri, Version};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Search {
+//!     q: String,
+//!     page: u32,
+//!     tags: Vec<String>,
+//! }
+//!
+//! impl FromValue for Search {
+//!     fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+//!         let mut fields = Fields::new(value)?;
+//!         let search = Search {
+//!             q: fields.required("q"),
+//!             page: fields.optional("page").unwrap_or(1),
+//!             tags: fields.optional("tags").unwrap_or_default(),
+//!         };
+//!         fields.finish(search)
+//!     }
+//! }
+//!
+//! let factory = RequestFactory::version(Version::Http1_1);
+//! let uri = |s: &str| Uri::parse(s).unwrap().1;
+//!
+//! let req = factory.get(uri("/search?q=rust&tags=web&tags=http"), Headers::new());
+//! let search: Search = req.query_as().unwrap();
+//! assert_eq!(search.page, 1);
+//! assert_eq!(search.tags, ["web", "http"]);
+//!
+//! let req = factory.get(uri("/search?page=two"), Headers::new());
+//! let err = req.query_as::<Search>().unwrap_err();
+//! assert_eq!(err.status(), Status::BadRequest);
+//! let errors = err.fields().unwrap();
+//! assert_eq!(errors.get("q"), ["is required"]);
+//! assert_eq!(errors.get("page"), ["must be an integer"]);
+//! ```
+
+use crate::concepts::value::json::JsonFormatter;
+use crate::concepts::value::{from_value, Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use crate::http::{
+    form_urldecode, Headers, MessageTrait, Query, Request, Response, ResponseFactory, Status,
+    Version,
+};
+use serde::de::DeserializeOwned;
+use std::fmt::{self, Display, Formatter};
+
+/// Errors of a conversion, keyed by field name.
+///
+/// The empty key holds the errors of the converted value itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldErrors {
+    errors: Dictionary<Vec<String>>,
+}
+
+impl FieldErrors {
+    /// Create an empty error list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an error list holding `message` for the value itself.
+    pub fn message(message: &str) -> Self {
+        let mut errors = Self::new();
+        errors.add("", message);
+        errors
+    }
+
+    /// Record `message` for `field`.
+    pub fn add(&mut self, field: &str, message: &str) {
+        self.errors
+            .entry(field.to_string())
+            .or_default()
+            .push(message.to_string());
+    }
+
+    /// Record the errors of `other`, nesting their fields under `prefix`.
+    pub fn merge(&mut self, prefix: &str, other: FieldErrors) {
+        for (field, messages) in other.errors {
+            let field = match (prefix.is_empty(), field.is_empty()) {
+                (_, true) => prefix.to_string(),
+                (true, false) => field,
+                (false, false) => format!("{}.{}", prefix, field),
+            };
+            self.errors.entry(field).or_default().extend(messages);
+        }
+    }
+
+    /// Messages recorded for `field`.
+    pub fn get(&self, field: &str) -> &[String] {
+        self.errors
+            .get(field)
+            .map(|messages| messages.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if no error was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Number of fields in error.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Iterate over the fields in error and their messages.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.errors
+            .iter()
+            .map(|(field, messages)| (field.as_str(), messages.as_slice()))
+    }
+
+    /// Represent the errors as a dictionary of message arrays.
+    pub fn to_value(&self) -> Value {
+        Value::Dictionary(
+            self.errors
+                .iter()
+                .map(|(field, messages)| {
+                    let messages = messages.iter().cloned().map(Value::String).collect();
+                    (field.clone(), Value::Array(messages))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Display for FieldErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut fields: Vec<_> = self.iter().collect();
+        fields.sort();
+        let fields: Vec<String> = fields
+            .into_iter()
+            .map(|(field, messages)| match field {
+                "" => messages.join(", "),
+                _ => format!("{}: {}", field, messages.join(", ")),
+            })
+            .collect();
+        write!(f, "{}", fields.join("; "))
+    }
+}
+
+impl std::error::Error for FieldErrors {}
+
+/// Conversion from a [`Value`].
+pub trait FromValue: Sized {
+    /// Convert `value`, reporting every invalid field.
+    fn from_value(value: &Value) -> Result<Self, FieldErrors>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Int(i) => Ok(i.to_string()),
+            Value::Number(n) => Ok(n.to_string()),
+            Value::Bool(b) => Ok(b.to_string()),
+            _ => Err(FieldErrors::message("must be a string")),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            Value::Int(0) => Ok(false),
+            Value::Int(1) => Ok(true),
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "on" | "yes" => Ok(true),
+                "false" | "0" | "off" | "no" => Ok(false),
+                _ => Err(FieldErrors::message("must be a boolean")),
+            },
+            _ => Err(FieldErrors::message("must be a boolean")),
+        }
+    }
+}
+
+macro_rules! integer_from_value {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+                let integer = match value {
+                    Value::Int(i) => Some(i128::from(*i)),
+                    Value::Number(n) if n.fract() == 0.0 && n.is_finite() => Some(*n as i128),
+                    Value::String(s) => s.trim().parse::<i128>().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| FieldErrors::message("must be an integer"))?;
+                <$t>::try_from(integer).map_err(|_| FieldErrors::message("is out of range"))
+            }
+        }
+    )*};
+}
+
+integer_from_value!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! float_from_value {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+                match value {
+                    Value::Number(n) => Ok(*n as $t),
+                    Value::Int(i) => Ok(*i as $t),
+                    Value::String(s) => s
+                        .trim()
+                        .parse::<$t>()
+                        .ok()
+                        .filter(|n| n.is_finite())
+                        .ok_or_else(|| FieldErrors::message("must be a number")),
+                    _ => Err(FieldErrors::message("must be a number")),
+                }
+            }
+        }
+    )*};
+}
+
+float_from_value!(f32, f64);
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// Arrays convert element-wise; a single value converts to a one element
+/// vector, as repeated query keys only become arrays from two values.
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        let items = match value {
+            Value::Null => return Ok(Vec::new()),
+            Value::Array(items) => items.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        let mut errors = FieldErrors::new();
+        let mut converted = Vec::with_capacity(items.len());
+        for (index, item) in items.iter().enumerate() {
+            match T::from_value(item) {
+                Ok(item) => converted.push(item),
+                Err(e) => errors.merge(&index.to_string(), e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(converted)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Dictionary<T> {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        let Value::Dictionary(entries) = value else {
+            return Err(FieldErrors::message("must be an object"));
+        };
+        let mut errors = FieldErrors::new();
+        let mut converted = Dictionary::new();
+        for (key, item) in entries {
+            match T::from_value(item) {
+                Ok(item) => {
+                    converted.insert(key.clone(), item);
+                }
+                Err(e) => errors.merge(key, e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(converted)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Conversion of types implementing serde's `Deserialize`.
+///
+/// Deserialization stops at the first failure, reported for the value
+/// itself rather than per field.
+///
+/// ```
+/// use hermes::concepts::Parsable;
+/// use hermes::http::extract::Serde;
+/// use hermes::http::{Headers, RequestFactory, Uri, Version};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Item {
+///     name: String,
+///     quantity: u32,
+/// }
+///
+/// let mut headers = Headers::new();
+/// headers.set("Content-Type", &["application/json"]);
+/// let uri = Uri::parse("/items").unwrap().1;
+/// let req = RequestFactory::version(Version::Http1_1)
+///     .post(uri, headers, r#"{"name": "pen", "quantity": 3}"#);
+/// let Serde(item) = req.json_as::<Serde<Item>>().unwrap();
+/// assert_eq!((item.name.as_str(), item.quantity), ("pen", 3));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Serde<T>(pub T);
+
+impl<T: DeserializeOwned> FromValue for Serde<T> {
+    fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+        from_value(value.clone())
+            .map(Serde)
+            .map_err(|e| FieldErrors::message(&e.to_string()))
+    }
+}
+
+/// Reader of the fields of a dictionary [`Value`] accumulating errors.
+///
+/// Absent and `null` fields are missing. Empty strings, as sent by empty
+/// form inputs, are missing as well when they do not convert to the field
+/// type.
+pub struct Fields<'a> {
+    values: &'a Dictionary<Value>,
+    errors: FieldErrors,
+}
+
+impl<'a> Fields<'a> {
+    /// Start reading the fields of `value`, which must be a dictionary.
+    pub fn new(value: &'a Value) -> Result<Self, FieldErrors> {
+        match value {
+            Value::Dictionary(values) => Ok(Self {
+                values,
+                errors: FieldErrors::new(),
+            }),
+            _ => Err(FieldErrors::message("must be an object")),
+        }
+    }
+
+    fn read<T: FromValue>(&mut self, name: &str) -> Option<T> {
+        let value = self.values.get(name).filter(|v| **v != Value::Null)?;
+        match T::from_value(value) {
+            Ok(converted) => Some(converted),
+            Err(_) if *value == Value::String(String::new()) => None,
+            Err(e) => {
+                self.errors.merge(name, e);
+                None
+            }
+        }
+    }
+
+    /// Read the required field `name`.
+    ///
+    /// On failure the error is recorded and the default value returned.
+    pub fn required<T: FromValue + Default>(&mut self, name: &str) -> T {
+        let errors = self.errors.len();
+        match self.read(name) {
+            Some(value) => value,
+            None => {
+                if self.errors.len() == errors {
+                    self.errors.add(name, "is required");
+                }
+                T::default()
+            }
+        }
+    }
+
+    /// Read the optional field `name`.
+    ///
+    /// Invalid values are recorded and read as `None`.
+    pub fn optional<T: FromValue>(&mut self, name: &str) -> Option<T> {
+        self.read(name)
+    }
+
+    /// Record a custom `message` for `field`.
+    pub fn error(&mut self, field: &str, message: &str) {
+        self.errors.add(field, message);
+    }
+
+    /// Errors recorded so far.
+    pub fn errors(&self) -> &FieldErrors {
+        &self.errors
+    }
+
+    /// Return `value` if no error was recorded.
+    pub fn finish<T>(self, value: T) -> Result<T, FieldErrors> {
+        if self.errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+/// Failure to extract typed data from a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractError {
+    /// The body does not have the expected media type (`415`).
+    UnsupportedMediaType(String),
+    /// The body could not be decoded (`400`).
+    Malformed(String),
+    /// Some fields are missing or invalid: `400` for the query string,
+    /// `422` for bodies.
+    Fields(Status, FieldErrors),
+}
+
+impl ExtractError {
+    /// Status to answer with.
+    pub fn status(&self) -> Status {
+        match self {
+            ExtractError::UnsupportedMediaType(_) => Status::UnsupportedMediaType,
+            ExtractError::Malformed(_) => Status::BadRequest,
+            ExtractError::Fields(status, _) => status.clone(),
+        }
+    }
+
+    /// Field errors, if any.
+    pub fn fields(&self) -> Option<&FieldErrors> {
+        match self {
+            ExtractError::Fields(_, errors) => Some(errors),
+            _ => None,
+        }
+    }
+
+    /// Render the error as a JSON response.
+    ///
+    /// The body holds the `status` code, the `error` reason phrase, a
+    /// `detail` message and, for field errors, the `fields` messages.
+    ///
+    /// ```
+    /// use hermes::http::extract::{ExtractError, FieldErrors};
+    /// use hermes::http::{MessageTrait, ResponseTrait, Status, Version};
+    ///
+    /// let mut errors = FieldErrors::new();
+    /// errors.add("email", "is required");
+    /// let response = ExtractError::Fields(Status::UnprocessableEntity, errors)
+    ///     .to_response(Version::Http1_1);
+    /// assert_eq!(response.code(), 422);
+    /// assert_eq!(
+    ///     response.body(),
+    ///     r#"{"status": 422,"error": "Unprocessable Entity","detail": "email: is required","fields": {"email": ["is required"]}}"#
+    /// );
+    /// ```
+    pub fn to_response(&self, version: Version) -> Response {
+        error_response(version, self.status(), &self.to_string(), self.fields())
+    }
+}
+
+impl Display for ExtractError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::UnsupportedMediaType(expected) => {
+                write!(f, "Expected an {} body", expected)
+            }
+            ExtractError::Malformed(reason) => write!(f, "{}", reason),
+            ExtractError::Fields(_, errors) => write!(f, "{}", errors),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// Build a JSON error response.
+///
+/// The body holds the `status` code, the `error` reason phrase, the `detail`
+/// message and, if any, the `fields` messages. It is the format of
+/// [`ExtractError::to_response`], to be used for other client errors.
+///
+/// ```
+/// use hermes::http::extract::error_response;
+/// use hermes::http::{MessageTrait, ResponseTrait, Status, Version};
+///
+/// let response = error_response(Version::Http1_1, Status::NotFound, "No such user", None);
+/// assert_eq!(response.code(), 404);
+/// assert_eq!(
+///     response.body(),
+///     r#"{"status": 404,"error": "Not Found","detail": "No such user"}"#
+/// );
+/// ```
+pub fn error_response(
+    version: Version,
+    status: Status,
+    detail: &str,
+    fields: Option<&FieldErrors>,
+) -> Response {
+    let mut body = Dictionary::new();
+    body.insert("status".to_string(), Value::Int(status.to_code().into()));
+    body.insert(
+        "error".to_string(),
+        Value::String(status.to_reason().to_string()),
+    );
+    body.insert("detail".to_string(), Value::String(detail.to_string()));
+    if let Some(errors) = fields {
+        body.insert("fields".to_string(), errors.to_value());
+    }
+    let body = JsonFormatter.format(Value::Dictionary(body));
+    let mut headers = Headers::new();
+    headers.set("Content-Type", &["application/json"]);
+    headers.set("Content-Length", &[&body.len().to_string()]);
+    ResponseFactory::version(version)
+        .with_status(status, headers)
+        .with_body(&body)
+}
+
+/// Media type of the body of `message`, without parameters, in lowercase.
+pub(crate) fn media_type(message: &impl MessageTrait) -> Option<String> {
+    message.get_header_line("Content-Type").map(|value| {
+        value
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    })
+}
+
+/// Parse the JSON body (`application/json` or a `+json` media type) of
+/// `message`.
+fn json_body(message: &impl MessageTrait) -> Result<Value, ExtractError> {
+    let is_json = media_type(message)
+        .is_some_and(|media| media == "application/json" || media.ends_with("+json"));
+    if !is_json {
+        return Err(ExtractError::UnsupportedMediaType(
+            "application/json".to_string(),
+        ));
+    }
+    JsonFormatter
+        .parse(&message.body())
+        .ok_or_else(|| ExtractError::Malformed("Invalid JSON body".to_string()))
+}
+
+impl Request {
+    /// Media type of the body, without parameters, in lowercase.
+    fn media_type(&self) -> Option<String> {
+        media_type(self)
+    }
+
+    /// Parse a JSON body (`application/json` or a `+json` media type).
+    ///
+    /// A body with another media type fails with `415 Unsupported Media
+    /// Type`, invalid JSON with `400 Bad Request`.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::concepts::Parsable;
+    /// use hermes::http::{Headers, RequestFactory, Status, Uri, Version};
+    ///
+    /// let factory = RequestFactory::version(Version::Http1_1);
+    /// let uri = Uri::parse("/items").unwrap().1;
+    /// let mut headers = Headers::new();
+    /// headers.set("Content-Type", &["application/json; charset=utf-8"]);
+    /// let req = factory.post(uri.clone(), headers.clone(), "[1, 2]");
+    /// assert_eq!(req.json().unwrap(), Value::Array(vec![Value::Int(1), Value::Int(2)]));
+    ///
+    /// let req = factory.post(uri.clone(), headers, "[1, 2");
+    /// assert_eq!(req.json().unwrap_err().status(), Status::BadRequest);
+    /// let req = factory.post(uri, Headers::new(), "[1, 2]");
+    /// assert_eq!(req.json().unwrap_err().status(), Status::UnsupportedMediaType);
+    /// ```
+    pub fn json(&self) -> Result<Value, ExtractError> {
+        json_body(self)
+    }
+
+    /// Extract the query string into `T`.
+    ///
+    /// Field errors answer `400 Bad Request`, like queries exceeding the
+    /// bounds of [`Query::try_to_value`].
+    pub fn query_as<T: FromValue>(&self) -> Result<T, ExtractError> {
+        let value = self
+            .target
+            .query()
+            .try_to_value()
+            .map_err(|e| ExtractError::Malformed(e.to_string()))?;
+        T::from_value(&value).map_err(|e| ExtractError::Fields(Status::BadRequest, e))
+    }
+
+    /// Extract an `application/x-www-form-urlencoded` body into `T`.
+    ///
+    /// Field errors answer `422 Unprocessable Entity`; forms exceeding the
+    /// bounds of [`Query::try_to_value`] answer `400 Bad Request`.
+    pub fn form_as<T: FromValue>(&self) -> Result<T, ExtractError> {
+        const FORM: &str = "application/x-www-form-urlencoded";
+        if self.media_type().as_deref() != Some(FORM) {
+            return Err(ExtractError::UnsupportedMediaType(FORM.to_string()));
+        }
+        let mut form = Query::new();
+        for (key, value) in form_urldecode(&self.body()) {
+            form.add(&key, &value);
+        }
+        let value = form
+            .try_to_value()
+            .map_err(|e| ExtractError::Malformed(e.to_string()))?;
+        T::from_value(&value).map_err(|e| ExtractError::Fields(Status::UnprocessableEntity, e))
+    }
+
+    /// Extract a JSON body (`application/json` or a `+json` media type)
+    /// into `T`.
+    ///
+    /// Field errors answer `422 Unprocessable Entity`.
+    pub fn json_as<T: FromValue>(&self) -> Result<T, ExtractError> {
+        let value = self.json()?;
+        T::from_value(&value).map_err(|e| ExtractError::Fields(Status::UnprocessableEntity, e))
+    }
+}
+
+impl Response {
+    /// Parse a JSON body, as [`Request::json`] does.
+    pub fn json(&self) -> Result<Value, ExtractError> {
+        json_body(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Parsable;
+    use crate::http::{RequestFactory, Uri};
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Address {
+        city: String,
+        zip: Option<u32>,
+    }
+
+    impl FromValue for Address {
+        fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+            let mut fields = Fields::new(value)?;
+            let address = Address {
+                city: fields.required("city"),
+                zip: fields.optional("zip"),
+            };
+            fields.finish(address)
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct User {
+        name: String,
+        age: u8,
+        admin: bool,
+        address: Address,
+        scores: Vec<f64>,
+    }
+
+    impl FromValue for User {
+        fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+            let mut fields = Fields::new(value)?;
+            let user = User {
+                name: fields.required("name"),
+                age: fields.required("age"),
+                admin: fields.optional("admin").unwrap_or_default(),
+                address: fields.required("address"),
+                scores: fields.optional("scores").unwrap_or_default(),
+            };
+            fields.finish(user)
+        }
+    }
+
+    fn request(content_type: &str, body: &str) -> Request {
+        let mut headers = Headers::new();
+        headers.set("Content-Type", &[content_type]);
+        RequestFactory::version(Version::Http1_1).post(
+            Uri::parse("/users").unwrap().1,
+            headers,
+            body,
+        )
+    }
+
+    #[test]
+    fn test_scalar_conversions() {
+        let s = |v: &str| Value::String(v.to_string());
+        assert_eq!(u8::from_value(&s(" 42 ")), Ok(42));
+        assert_eq!(i64::from_value(&Value::Number(3.0)), Ok(3));
+        assert_eq!(
+            u8::from_value(&Value::Int(300)),
+            Err(FieldErrors::message("is out of range"))
+        );
+        assert_eq!(
+            i32::from_value(&s("1.5")),
+            Err(FieldErrors::message("must be an integer"))
+        );
+        assert_eq!(f64::from_value(&s("1.5")), Ok(1.5));
+        assert!(f64::from_value(&s("NaN")).is_err());
+        assert_eq!(bool::from_value(&s("on")), Ok(true));
+        assert_eq!(bool::from_value(&Value::Int(0)), Ok(false));
+        assert_eq!(String::from_value(&Value::Int(7)), Ok("7".to_string()));
+        assert_eq!(Option::<u8>::from_value(&Value::Null), Ok(None));
+        assert_eq!(Vec::<u8>::from_value(&s("1")), Ok(vec![1]));
+        let errors = Vec::<u8>::from_value(&Value::Array(vec![s("1"), s("x")])).unwrap_err();
+        assert_eq!(errors.get("1"), ["must be an integer"]);
+    }
+
+    #[test]
+    fn test_form_extraction() {
+        let req = request(
+            "application/x-www-form-urlencoded; charset=UTF-8",
+            "name=Ann+Lee&age=30&admin=on&address[city]=Paris&address[zip]=&scores[]=1.5&scores[]=2",
+        );
+        let user: User = req.form_as().unwrap();
+        assert_eq!(
+            user,
+            User {
+                name: "Ann Lee".to_string(),
+                age: 30,
+                admin: true,
+                address: Address {
+                    city: "Paris".to_string(),
+                    zip: None,
+                },
+                scores: vec![1.5, 2.0],
+            }
+        );
+
+        let req = request(
+            "application/x-www-form-urlencoded",
+            "age=300&address[zip]=abc&scores[]=x",
+        );
+        let err = req.form_as::<User>().unwrap_err();
+        assert_eq!(err.status(), Status::UnprocessableEntity);
+        let errors = err.fields().unwrap();
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors.get("name"), ["is required"]);
+        assert_eq!(errors.get("age"), ["is out of range"]);
+        assert_eq!(errors.get("address.city"), ["is required"]);
+        assert_eq!(errors.get("address.zip"), ["must be an integer"]);
+        assert_eq!(errors.get("scores.0"), ["must be a number"]);
+
+        let err = request("application/json", "{}")
+            .form_as::<User>()
+            .unwrap_err();
+        assert_eq!(err.status(), Status::UnsupportedMediaType);
+
+        let deep = format!("name{}=x", "[]".repeat(50_000));
+        let err = request("application/x-www-form-urlencoded", &deep)
+            .form_as::<User>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ExtractError::Malformed("Parameter nested deeper than 32 levels".to_string())
+        );
+        assert_eq!(err.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_json_extraction() {
+        let req = request(
+            "application/json",
+            r#"{"name": "Bob", "age": 41, "address": {"city": "Oslo", "zip": 150}}"#,
+        );
+        let user: User = req.json_as().unwrap();
+        assert_eq!(user.address.zip, Some(150));
+        assert!(!user.admin);
+
+        let err = request("application/json", "{")
+            .json_as::<User>()
+            .unwrap_err();
+        assert_eq!(err.status(), Status::BadRequest);
+        let err = request("application/json", "[1]")
+            .json_as::<User>()
+            .unwrap_err();
+        assert_eq!(err.fields().unwrap().get(""), ["must be an object"]);
+        let err = request("text/plain", "{}").json_as::<User>().unwrap_err();
+        assert_eq!(err.status(), Status::UnsupportedMediaType);
+        assert_eq!(
+            err.to_response(Version::Http1_1).body(),
+            r#"{"status": 415,"error": "Unsupported Media Type","detail": "Expected an application/json body"}"#
+        );
+    }
+
+    #[test]
+    fn test_json_responses() {
+        let factory = ResponseFactory::version(Version::Http1_1);
+        let mut user = Dictionary::new();
+        user.insert("name".to_string(), Value::String("Ann \"A\"".into()));
+        let value = Value::Dictionary(user);
+        let resp = factory.json(Status::OK, value.clone());
+        assert_eq!(resp.json(), Ok(value));
+
+        let resp = factory.ok(Headers::new(), "{}".to_string());
+        assert_eq!(
+            resp.json().unwrap_err().status(),
+            Status::UnsupportedMediaType
+        );
+
+        let multipart = crate::http::multipart::MultipartError::UnsupportedMediaType;
+        let resp = multipart.to_response(Version::Http1_1);
+        let body = resp.json().unwrap();
+        let Value::Dictionary(body) = body else {
+            unreachable!()
+        };
+        assert_eq!(body.get("status"), Some(&Value::Int(415)));
+        assert_eq!(
+            body.get("error"),
+            Some(&Value::String("Unsupported Media Type".into()))
+        );
+    }
+}
@@ -0,0 +1,814 @@
+//! `multipart/form-data` parsing.
+//!
+//! [`MultipartParser`] is an incremental parser: body chunks are
+//! [fed](MultipartParser::feed) as they arrive and [`MultipartParser::finish`]
+//! returns the parsed [`Multipart`] form. Text fields are kept in memory;
+//! file parts stay in memory up to [`MultipartLimits::memory_threshold`] and
+//! are then spooled to a file of [`MultipartLimits::temp_dir`], removed when
+//! the [`UploadedFile`] is dropped unless it was
+//! [persisted](UploadedFile::persist). The limits on the number of parts,
+//! the size of each part and the total size are enforced while reading.
+//!
+//! [`Request::multipart`] parses the body of a request. Bodies of
+//! [`Request`]s are strings: binary content must be fed to the parser from
+//! the raw bytes, e.g. with [`MultipartParser::read_async`].
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::concepts::Parsable;
+//! use hermes::http::multipart::MultipartLimits;
+//! use hermes::http::{Headers, RequestFactory, Uri, Version};
+//!
+//! let body = "--XyZ\r\n\
+//!     Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+//!     Holidays\r\n\
+//!     --XyZ\r\n\
+//!     Content-Disposition: form-data; name=\"photo\"; filename=\"beach.txt\"\r\n\
+//!     Content-Type: text/plain\r\n\r\n\
+//!     sand and sea\r\n\
+//!     --XyZ--\r\n";
+//! let mut headers = Headers::new();
+//! headers.set("Content-Type", &["multipart/form-data; boundary=XyZ"]);
+//! let req = RequestFactory::version(Version::Http1_1)
+//!     .post(Uri::parse("/upload").unwrap().1, headers, body);
+//!
+//! let form = req.multipart(&MultipartLimits::default()).unwrap();
+//! assert_eq!(form.field("title"), Some("Holidays"));
+//! let photo = form.file("photo").unwrap();
+//! assert_eq!(photo.filename, "beach.txt");
+//! assert_eq!(photo.content_type, "text/plain");
+//! assert_eq!(photo.size, 12);
+//! assert_eq!(photo.bytes().unwrap(), b"sand and sea");
+//! ```
+
+use crate::http::extract::error_response;
+use crate::http::{percent_decode, MessageTrait, Request, Response, Status, Version};
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Bounds applied while parsing a multipart body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipartLimits {
+    /// Maximum number of parts.
+    pub max_parts: usize,
+    /// Maximum size of the headers of a part, in bytes.
+    pub max_header_size: usize,
+    /// Maximum size of the content of a part, in bytes.
+    pub max_part_size: usize,
+    /// Maximum size of the whole body, in bytes.
+    pub max_total_size: usize,
+    /// Size above which file contents are spooled to disk, in bytes.
+    pub memory_threshold: usize,
+    /// Directory receiving the spooled files.
+    pub temp_dir: PathBuf,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_parts: 100,
+            max_header_size: 8 * 1024,
+            max_part_size: 10 * 1024 * 1024,
+            max_total_size: 50 * 1024 * 1024,
+            memory_threshold: 64 * 1024,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Errors raised while parsing a multipart body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultipartError {
+    /// The request is not `multipart/form-data` or has no boundary.
+    UnsupportedMediaType,
+    /// The body does not follow the multipart syntax.
+    Malformed(String),
+    /// The body has more parts than allowed.
+    TooManyParts(usize),
+    /// A part is larger than allowed.
+    PartTooLarge(String),
+    /// The body is larger than allowed.
+    BodyTooLarge(usize),
+    /// A file could not be spooled.
+    Io(String),
+}
+
+impl MultipartError {
+    /// Status to answer a request failing with this error.
+    pub fn status(&self) -> Status {
+        match self {
+            MultipartError::UnsupportedMediaType => Status::UnsupportedMediaType,
+            MultipartError::Malformed(_) | MultipartError::TooManyParts(_) => Status::BadRequest,
+            MultipartError::PartTooLarge(_) | MultipartError::BodyTooLarge(_) => {
+                Status::RequestEntityTooLarge
+            }
+            MultipartError::Io(_) => Status::InternalServerError,
+        }
+    }
+
+    /// Render the error as a JSON response, in the format of
+    /// [`error_response`].
+    pub fn to_response(&self, version: Version) -> Response {
+        error_response(version, self.status(), &self.to_string(), None)
+    }
+}
+
+impl Display for MultipartError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MultipartError::UnsupportedMediaType => {
+                write!(f, "Expected a multipart/form-data body with a boundary")
+            }
+            MultipartError::Malformed(reason) => write!(f, "Malformed multipart body: {}", reason),
+            MultipartError::TooManyParts(max) => write!(f, "More than {} parts", max),
+            MultipartError::PartTooLarge(name) => write!(f, "Part {:?} is too large", name),
+            MultipartError::BodyTooLarge(max) => write!(f, "Body larger than {} bytes", max),
+            MultipartError::Io(reason) => write!(f, "Cannot spool upload: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+impl From<std::io::Error> for MultipartError {
+    fn from(e: std::io::Error) -> Self {
+        MultipartError::Io(e.to_string())
+    }
+}
+
+/// Extract the boundary of a `multipart/form-data` content type.
+///
+/// ```
+/// use hermes::http::multipart::boundary;
+///
+/// assert_eq!(boundary("multipart/form-data; boundary=abc").as_deref(), Some("abc"));
+/// assert_eq!(boundary("Multipart/Form-Data; boundary=\"a b\"").as_deref(), Some("a b"));
+/// assert_eq!(boundary("text/plain; boundary=abc"), None);
+/// ```
+pub fn boundary(content_type: &str) -> Option<String> {
+    let (media, params) = content_type.split_once(';')?;
+    if !media.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    parameters(params)
+        .into_iter()
+        .find(|(name, _)| name == "boundary")
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty() && value.len() <= 70)
+}
+
+/// Parse `; name=value` parameters, unquoting quoted values.
+fn parameters(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| *c == ';' || c.is_whitespace()).is_some() {}
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && *c != ';') {
+            name.push(c);
+        }
+        if name.is_empty() && chars.peek().is_none() {
+            return params;
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            }
+            while let Some(c) = chars.next_if(|c| *c != ';') {
+                value.push(c);
+            }
+        }
+        params.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+}
+
+/// Content of an uploaded file.
+#[derive(Debug)]
+enum FileData {
+    Memory(Vec<u8>),
+    Spooled(PathBuf),
+}
+
+/// A file part of a multipart form.
+#[derive(Debug)]
+pub struct UploadedFile {
+    /// Name of the form field.
+    pub name: String,
+    /// File name sent by the client. It is untrusted; see
+    /// [`UploadedFile::safe_filename`].
+    pub filename: String,
+    /// Content type sent by the client, `application/octet-stream` if none.
+    pub content_type: String,
+    /// Size of the content, in bytes.
+    pub size: usize,
+    data: FileData,
+}
+
+impl UploadedFile {
+    /// File name without directories nor control characters.
+    ///
+    /// ```
+    /// # use hermes::http::multipart::MultipartParser;
+    /// # let mut parser = MultipartParser::new("b", Default::default());
+    /// # parser.feed(b"--b\r\nContent-Disposition: form-data; name=f; \
+    /// #     filename=\"..\\\\..\\\\etc/pass\x07wd\"\r\n\r\nx\r\n--b--").unwrap();
+    /// # let form = parser.finish().unwrap();
+    /// let file = form.file("f").unwrap();
+    /// assert_eq!(file.safe_filename(), "passwd");
+    /// ```
+    pub fn safe_filename(&self) -> String {
+        self.filename
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect::<String>()
+            .trim_start_matches('.')
+            .to_string()
+    }
+
+    /// Path of the spooled file, if the content was written to disk.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.data {
+            FileData::Memory(_) => None,
+            FileData::Spooled(path) => Some(path),
+        }
+    }
+
+    /// Read the content.
+    pub fn bytes(&self) -> std::io::Result<Vec<u8>> {
+        match &self.data {
+            FileData::Memory(bytes) => Ok(bytes.clone()),
+            FileData::Spooled(path) => std::fs::read(path),
+        }
+    }
+
+    /// Move the content to `destination`.
+    pub fn persist(mut self, destination: &Path) -> std::io::Result<()> {
+        match std::mem::replace(&mut self.data, FileData::Memory(Vec::new())) {
+            FileData::Memory(bytes) => std::fs::write(destination, bytes),
+            FileData::Spooled(path) => {
+                let moved = std::fs::rename(&path, destination).or_else(|_| {
+                    std::fs::copy(&path, destination)?;
+                    std::fs::remove_file(&path)
+                });
+                if moved.is_err() {
+                    self.data = FileData::Spooled(path);
+                }
+                moved
+            }
+        }
+    }
+}
+
+impl Drop for UploadedFile {
+    fn drop(&mut self) {
+        if let FileData::Spooled(path) = &self.data {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// A parsed `multipart/form-data` form.
+#[derive(Debug, Default)]
+pub struct Multipart {
+    fields: Vec<(String, String)>,
+    files: Vec<UploadedFile>,
+}
+
+impl Multipart {
+    /// Value of the last text field named `name`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Values of the text fields named `name`, in order.
+    pub fn field_values(&self, name: &str) -> Vec<&str> {
+        self.fields
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Text fields, in order.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// First file sent for the field `name`.
+    pub fn file(&self, name: &str) -> Option<&UploadedFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// Files, in order.
+    pub fn files(&self) -> impl Iterator<Item = &UploadedFile> {
+        self.files.iter()
+    }
+
+    /// Take the files out of the form.
+    pub fn into_files(self) -> Vec<UploadedFile> {
+        self.files
+    }
+}
+
+/// Part being read.
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: String,
+    size: usize,
+    memory: Vec<u8>,
+    spool: Option<(PathBuf, File)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Preamble,
+    AfterDelimiter,
+    Headers,
+    Body,
+    End,
+}
+
+/// Incremental `multipart/form-data` parser.
+pub struct MultipartParser {
+    /// `CRLF--boundary`
+    delimiter: Vec<u8>,
+    limits: MultipartLimits,
+    state: State,
+    buffer: Vec<u8>,
+    total: usize,
+    parts: usize,
+    part: Option<Part>,
+    form: Multipart,
+}
+
+impl MultipartParser {
+    /// Create a parser for parts separated by `boundary`.
+    pub fn new(boundary: &str, limits: MultipartLimits) -> Self {
+        // The first delimiter may not be preceded by CRLF.
+        let buffer = b"\r\n".to_vec();
+        Self {
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            limits,
+            state: State::Preamble,
+            buffer,
+            total: 0,
+            parts: 0,
+            part: None,
+            form: Multipart::default(),
+        }
+    }
+
+    /// Parse the next `chunk` of the body.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), MultipartError> {
+        self.total += chunk.len();
+        if self.total > self.limits.max_total_size {
+            return Err(MultipartError::BodyTooLarge(self.limits.max_total_size));
+        }
+        if self.state == State::End {
+            return Ok(());
+        }
+        self.buffer.extend_from_slice(chunk);
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// Check the body was complete and return the form.
+    pub fn finish(mut self) -> Result<Multipart, MultipartError> {
+        match self.state {
+            State::End => Ok(std::mem::take(&mut self.form)),
+            _ => Err(MultipartError::Malformed(
+                "missing closing delimiter".to_string(),
+            )),
+        }
+    }
+
+    /// Feed the whole content of `reader`.
+    pub fn read<R: Read>(mut self, mut reader: R) -> Result<Multipart, MultipartError> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let n = reader.read(&mut chunk)?;
+            if n == 0 {
+                return self.finish();
+            }
+            self.feed(&chunk[..n])?;
+        }
+    }
+
+    /// Feed the whole content of the asynchronous `reader`.
+    pub async fn read_async<R: AsyncRead + Unpin>(
+        mut self,
+        mut reader: R,
+    ) -> Result<Multipart, MultipartError> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let n = reader.read(&mut chunk).await?;
+            if n == 0 {
+                return self.finish();
+            }
+            self.feed(&chunk[..n])?;
+        }
+    }
+
+    /// Process the buffered input; returns `false` when more is needed.
+    fn step(&mut self) -> Result<bool, MultipartError> {
+        match self.state {
+            State::Preamble => match find(&self.buffer, &self.delimiter) {
+                Some(at) => {
+                    self.buffer.drain(..at + self.delimiter.len());
+                    self.state = State::AfterDelimiter;
+                    Ok(true)
+                }
+                None => {
+                    let keep = self.delimiter.len() - 1;
+                    if self.buffer.len() > keep {
+                        self.buffer.drain(..self.buffer.len() - keep);
+                    }
+                    Ok(false)
+                }
+            },
+            State::AfterDelimiter => {
+                // Transport padding may follow the delimiter.
+                let padding = self
+                    .buffer
+                    .iter()
+                    .take_while(|b| **b == b' ' || **b == b'\t')
+                    .count();
+                let rest = &self.buffer[padding..];
+                if rest.len() < 2 {
+                    return Ok(false);
+                }
+                if rest.starts_with(b"--") {
+                    self.state = State::End;
+                    self.buffer.clear();
+                    return Ok(false);
+                }
+                if !rest.starts_with(b"\r\n") {
+                    return Err(MultipartError::Malformed(
+                        "expected CRLF after the delimiter".to_string(),
+                    ));
+                }
+                self.buffer.drain(..padding + 2);
+                self.state = State::Headers;
+                Ok(true)
+            }
+            State::Headers => {
+                let end = if self.buffer.starts_with(b"\r\n") {
+                    Some(0)
+                } else {
+                    find(&self.buffer, b"\r\n\r\n").map(|end| end + 2)
+                };
+                let Some(end) = end else {
+                    if self.buffer.len() > self.limits.max_header_size {
+                        return Err(MultipartError::Malformed(
+                            "part headers too large".to_string(),
+                        ));
+                    }
+                    return Ok(false);
+                };
+                if end > self.limits.max_header_size {
+                    return Err(MultipartError::Malformed(
+                        "part headers too large".to_string(),
+                    ));
+                }
+                let headers = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+                self.buffer.drain(..end + 2);
+                self.start_part(&headers)?;
+                self.state = State::Body;
+                Ok(true)
+            }
+            State::Body => match find(&self.buffer, &self.delimiter) {
+                Some(at) => {
+                    let content: Vec<u8> = self.buffer.drain(..at).collect();
+                    self.write(&content)?;
+                    self.buffer.drain(..self.delimiter.len());
+                    self.end_part()?;
+                    self.state = State::AfterDelimiter;
+                    Ok(true)
+                }
+                None => {
+                    // Keep what could be the start of a delimiter.
+                    let keep = self.delimiter.len() - 1;
+                    if self.buffer.len() > keep {
+                        let content: Vec<u8> =
+                            self.buffer.drain(..self.buffer.len() - keep).collect();
+                        self.write(&content)?;
+                    }
+                    Ok(false)
+                }
+            },
+            State::End => Ok(false),
+        }
+    }
+
+    fn start_part(&mut self, headers: &str) -> Result<(), MultipartError> {
+        self.parts += 1;
+        if self.parts > self.limits.max_parts {
+            return Err(MultipartError::TooManyParts(self.limits.max_parts));
+        }
+        let mut name = None;
+        let mut filename = None;
+        let mut content_type = None;
+        for line in headers.split("\r\n").filter(|l| !l.is_empty()) {
+            let (header, value) = line.split_once(':').ok_or_else(|| {
+                MultipartError::Malformed(format!("invalid part header {:?}", line))
+            })?;
+            if header.trim().eq_ignore_ascii_case("Content-Disposition") {
+                let (kind, params) = value.split_once(';').unwrap_or((value, ""));
+                if !kind.trim().eq_ignore_ascii_case("form-data") {
+                    return Err(MultipartError::Malformed(
+                        "expected a form-data disposition".to_string(),
+                    ));
+                }
+                for (param, value) in parameters(params) {
+                    match param.as_str() {
+                        "name" => name = Some(value),
+                        "filename" if filename.is_none() => filename = Some(value),
+                        "filename*" => {
+                            // RFC 5987: charset'language'percent-encoded
+                            let encoded = value.splitn(3, '\'').nth(2).unwrap_or(&value);
+                            filename = Some(percent_decode(encoded));
+                        }
+                        _ => {}
+                    }
+                }
+            } else if header.trim().eq_ignore_ascii_case("Content-Type") {
+                content_type = Some(value.trim().to_string());
+            }
+        }
+        let name =
+            name.ok_or_else(|| MultipartError::Malformed("part without a name".to_string()))?;
+        self.part = Some(Part {
+            name,
+            filename,
+            content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+            size: 0,
+            memory: Vec::new(),
+            spool: None,
+        });
+        Ok(())
+    }
+
+    fn write(&mut self, content: &[u8]) -> Result<(), MultipartError> {
+        let Some(part) = self.part.as_mut() else {
+            return Ok(());
+        };
+        part.size += content.len();
+        if part.size > self.limits.max_part_size {
+            return Err(MultipartError::PartTooLarge(part.name.clone()));
+        }
+        if let Some((_, file)) = part.spool.as_mut() {
+            file.write_all(content)?;
+            return Ok(());
+        }
+        part.memory.extend_from_slice(content);
+        if part.filename.is_some() && part.memory.len() > self.limits.memory_threshold {
+            let path = self
+                .limits
+                .temp_dir
+                .join(format!("hermes-upload-{}", uuid::Uuid::new_v4()));
+            let mut file = File::create(&path)?;
+            file.write_all(&std::mem::take(&mut part.memory))?;
+            part.spool = Some((path, file));
+        }
+        Ok(())
+    }
+
+    fn end_part(&mut self) -> Result<(), MultipartError> {
+        let Some(part) = self.part.take() else {
+            return Ok(());
+        };
+        match part.filename {
+            None => {
+                let value = String::from_utf8_lossy(&part.memory).to_string();
+                self.form.fields.push((part.name, value));
+            }
+            Some(filename) => {
+                let data = match part.spool {
+                    Some((path, mut file)) => {
+                        file.flush()?;
+                        FileData::Spooled(path)
+                    }
+                    None => FileData::Memory(part.memory),
+                };
+                self.form.files.push(UploadedFile {
+                    name: part.name,
+                    filename,
+                    content_type: part.content_type,
+                    size: part.size,
+                    data,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MultipartParser {
+    fn drop(&mut self) {
+        // Remove the file of a part interrupted by an error.
+        if let Some(Part {
+            spool: Some((path, _)),
+            ..
+        }) = &self.part
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+impl Request {
+    /// Parse a `multipart/form-data` body.
+    pub fn multipart(&self, limits: &MultipartLimits) -> Result<Multipart, MultipartError> {
+        let content_type = self.get_header_line("Content-Type").unwrap_or_default();
+        let boundary = boundary(&content_type).ok_or(MultipartError::UnsupportedMediaType)?;
+        let mut parser = MultipartParser::new(&boundary, limits.clone());
+        parser.feed(self.body_bytes())?;
+        parser.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"preamble\r\n--frontier\r\n\
+        Content-Disposition: form-data; name=\"tag\"\r\n\r\n\
+        a\r\n\
+        --frontier\r\n\
+        Content-Disposition: form-data; name=\"tag\"\r\n\r\n\
+        b\r\nc\r\n\
+        --frontier \r\n\
+        content-disposition: form-data; name=\"doc\"; filename*=UTF-8''r%C3%A9sum%C3%A9.bin\r\n\
+        Content-Type: application/octet-stream\r\n\r\n\
+        \x00\x01\xFF--frontie\r\n\r\n\
+        --frontier\r\n\
+        Content-Disposition: form-data; name=\"empty\"; filename=\"\"\r\n\r\n\
+        \r\n\
+        --frontier--\r\nepilogue";
+
+    fn limits() -> MultipartLimits {
+        MultipartLimits {
+            memory_threshold: 4,
+            ..MultipartLimits::default()
+        }
+    }
+
+    fn check(form: &Multipart) {
+        assert_eq!(form.field("tag"), Some("b\r\nc"));
+        assert_eq!(form.field_values("tag"), ["a", "b\r\nc"]);
+        let doc = form.file("doc").unwrap();
+        assert_eq!(doc.filename, "résumé.bin");
+        assert_eq!(doc.size, 14);
+        assert_eq!(doc.bytes().unwrap(), b"\x00\x01\xFF--frontie\r\n");
+        assert!(doc.path().unwrap().exists());
+        let empty = form.file("empty").unwrap();
+        assert_eq!(empty.size, 0);
+        assert_eq!(empty.content_type, "application/octet-stream");
+        assert!(empty.path().is_none());
+    }
+
+    #[test]
+    fn test_parse_whole_and_chunked() {
+        let mut parser = MultipartParser::new("frontier", limits());
+        parser.feed(BODY).unwrap();
+        let form = parser.finish().unwrap();
+        check(&form);
+
+        for size in [1, 2, 3, 7, 13] {
+            let mut parser = MultipartParser::new("frontier", limits());
+            for chunk in BODY.chunks(size) {
+                parser.feed(chunk).unwrap();
+            }
+            check(&parser.finish().unwrap());
+        }
+
+        let form = MultipartParser::new("frontier", limits())
+            .read(BODY)
+            .unwrap();
+        check(&form);
+    }
+
+    #[test]
+    fn test_request_binary_body() {
+        use crate::http::{Headers, RequestFactory, Uri, Version};
+
+        let mut headers = Headers::new();
+        headers.set("content-type", &["multipart/form-data; boundary=frontier"]);
+        let req = RequestFactory::version(Version::Http1_1)
+            .post(Uri::default(), headers, "")
+            .with_body_bytes(BODY.to_vec());
+        assert!(req.raw_body.is_some());
+        check(&req.multipart(&limits()).unwrap());
+    }
+
+    #[test]
+    fn test_spooled_files_are_removed() {
+        let form = MultipartParser::new("frontier", limits())
+            .read(BODY)
+            .unwrap();
+        let path = form.file("doc").unwrap().path().unwrap().to_path_buf();
+        assert!(path.exists());
+        drop(form);
+        assert!(!path.exists());
+
+        let form = MultipartParser::new("frontier", limits())
+            .read(BODY)
+            .unwrap();
+        let destination =
+            std::env::temp_dir().join(format!("hermes-kept-{}", uuid::Uuid::new_v4()));
+        let mut files = form.into_files();
+        let doc = files.remove(0);
+        let spooled = doc.path().unwrap().to_path_buf();
+        doc.persist(&destination).unwrap();
+        assert!(!spooled.exists());
+        assert_eq!(
+            std::fs::read(&destination).unwrap(),
+            b"\x00\x01\xFF--frontie\r\n"
+        );
+        std::fs::remove_file(destination).unwrap();
+    }
+
+    #[test]
+    fn test_limits() {
+        let parse = |limits: MultipartLimits| {
+            let mut parser = MultipartParser::new("frontier", limits);
+            parser.feed(BODY).and_then(|_| parser.finish()).map(|_| ())
+        };
+        assert_eq!(
+            parse(MultipartLimits {
+                max_parts: 2,
+                ..limits()
+            }),
+            Err(MultipartError::TooManyParts(2))
+        );
+        assert_eq!(
+            parse(MultipartLimits {
+                max_part_size: 10,
+                ..limits()
+            }),
+            Err(MultipartError::PartTooLarge("doc".to_string()))
+        );
+        assert_eq!(
+            parse(MultipartLimits {
+                max_total_size: 100,
+                ..limits()
+            }),
+            Err(MultipartError::BodyTooLarge(100))
+        );
+        assert!(matches!(
+            parse(MultipartLimits {
+                max_header_size: 20,
+                ..limits()
+            }),
+            Err(MultipartError::Malformed(_))
+        ));
+        assert_eq!(
+            MultipartError::PartTooLarge("x".into()).status(),
+            Status::RequestEntityTooLarge
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        let parse = |body: &[u8]| {
+            let mut parser = MultipartParser::new("b", MultipartLimits::default());
+            parser.feed(body).and_then(|_| parser.finish()).map(|_| ())
+        };
+        assert!(parse(b"--b\r\nContent-Disposition: form-data; name=a\r\n\r\nx").is_err());
+        assert!(parse(b"--b\r\nContent-Type: text/plain\r\n\r\nx\r\n--b--").is_err());
+        assert!(parse(b"--bx\r\n").is_err());
+        assert!(parse(b"no delimiter").is_err());
+        assert!(parse(b"--b--").is_ok());
+        assert_eq!(
+            parameters("; name=\"a;\\\"b\"; filename=c.txt ;x"),
+            [
+                ("name".to_string(), "a;\"b".to_string()),
+                ("filename".to_string(), "c.txt".to_string()),
+                ("x".to_string(), "".to_string()),
+            ]
+        );
+    }
+}
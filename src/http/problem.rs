@@ -0,0 +1,415 @@
+//! Problem details for HTTP APIs (RFC 7807).
+//!
+//! [`ProblemDetails`] describes an error and renders it as an
+//! `application/problem+json` response. Controllers wrapped in [`Fallible`]
+//! return `Result<Response, E>`: errors convert into problem details, which
+//! answer the request. The [`ErrorHandler`] middleware catches panics of
+//! the rest of the chain and answers `500 Internal Server Error` problems.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::concepts::value::Value;
+//! use hermes::concepts::Parsable;
+//! use hermes::http::problem::{ErrorHandler, Fallible, ProblemDetails};
+//! use hermes::http::routing::router::{Route, Router};
+//! use hermes::http::{Headers, MessageTrait, Method, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, Status, Uri, Version};
+//!
+//! fn show_order(_: &(), req: &mut Request) -> Result<Response, ProblemDetails> {
+//!     let id = req.param("id").unwrap_or_default();
+//!     if id != "1" {
+//!         return Err(ProblemDetails::new(Status::NotFound)
+//!             .with_type("https://example.com/probs/unknown-order")
+//!             .with_detail(&format!("Order {} does not exist", id))
+//!             .with_extension("order", Value::String(id.to_string())));
+//!     }
+//!     Ok(ResponseFactory::version(Version::Http1_1).ok(Headers::new(), "order 1".into()))
+//! }
+//!
+//! let mut router: Router<()> = Router::new();
+//! router.add_route(
+//!     Route::new("/orders/{id}", vec![Method::Get], Headers::new(), Box::new(Fallible::new(show_order)))
+//!         .with_before(Box::new(ErrorHandler::new())),
+//! );
+//! router.add_route(
+//!     Route::new("/panic", vec![Method::Get], Headers::new(), Box::new(
+//!         |_: &(), _: &mut Request| -> Response { panic!("boom") },
+//!     ))
+//!     .with_before(Box::new(ErrorHandler::new())),
+//! );
+//!
+//! let factory = RequestFactory::version(Version::Http1_1);
+//! let mut req = factory.get(Uri::parse("/orders/7").unwrap().1, Headers::new());
+//! let resp = router.handle_request(&(), &mut req).unwrap();
+//! assert_eq!(resp.code(), 404);
+//! assert_eq!(
+//!     resp.get_header_line("Content-Type"),
+//!     Some("application/problem+json".to_string())
+//! );
+//! assert_eq!(
+//!     resp.body(),
+//!     r#"{"type": "https://example.com/probs/unknown-order","title": "Not Found","status": 404,"detail": "Order 7 does not exist","order": "7"}"#
+//! );
+//!
+//! let mut req = factory.get(Uri::parse("/panic").unwrap().1, Headers::new());
+//! let resp = router.handle_request(&(), &mut req).unwrap();
+//! assert_eq!(resp.code(), 500);
+//! ```
+
+use crate::concepts::value::json::JsonFormatter;
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use crate::http::extract::{ExtractError, FieldErrors};
+use crate::http::multipart::MultipartError;
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::{Headers, MessageTrait, Request, Response, ResponseFactory, Status, Version};
+use std::fmt::{self, Display, Formatter};
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Machine-readable details of an error, as defined by RFC 7807.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProblemDetails {
+    /// URI reference identifying the problem type, `about:blank` by default.
+    pub problem_type: String,
+    /// Short summary of the problem type.
+    pub title: String,
+    /// Status of the response.
+    pub status: Status,
+    /// Explanation specific to this occurrence.
+    pub detail: Option<String>,
+    /// URI reference identifying this occurrence.
+    pub instance: Option<String>,
+    /// Additional members, written in insertion order.
+    pub extensions: Dictionary<Value>,
+}
+
+impl ProblemDetails {
+    /// Media type of problem details bodies.
+    pub const CONTENT_TYPE: &'static str = "application/problem+json";
+
+    /// Create an `about:blank` problem titled after `status`.
+    pub fn new(status: Status) -> Self {
+        Self {
+            problem_type: "about:blank".to_string(),
+            title: status.to_reason().to_string(),
+            status,
+            detail: None,
+            instance: None,
+            extensions: Dictionary::new(),
+        }
+    }
+
+    /// Set the problem type.
+    pub fn with_type(mut self, problem_type: &str) -> Self {
+        self.problem_type = problem_type.to_string();
+        self
+    }
+
+    /// Set the title.
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Set the detail.
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    /// Set the instance.
+    pub fn with_instance(mut self, instance: &str) -> Self {
+        self.instance = Some(instance.to_string());
+        self
+    }
+
+    /// Add the extension member `name`.
+    ///
+    /// Names of the standard members are ignored.
+    pub fn with_extension(mut self, name: &str, value: Value) -> Self {
+        if !matches!(name, "type" | "title" | "status" | "detail" | "instance") {
+            self.extensions.insert(name.to_string(), value);
+        }
+        self
+    }
+
+    /// Represent the problem as a dictionary, standard members first.
+    pub fn to_value(&self) -> Value {
+        let mut members = Dictionary::new();
+        members.insert("type".to_string(), Value::String(self.problem_type.clone()));
+        members.insert("title".to_string(), Value::String(self.title.clone()));
+        members.insert(
+            "status".to_string(),
+            Value::Int(self.status.to_code().into()),
+        );
+        if let Some(detail) = &self.detail {
+            members.insert("detail".to_string(), Value::String(detail.clone()));
+        }
+        if let Some(instance) = &self.instance {
+            members.insert("instance".to_string(), Value::String(instance.clone()));
+        }
+        members.extend(self.extensions.clone());
+        Value::Dictionary(members)
+    }
+
+    /// Render the problem as an `application/problem+json` response.
+    pub fn to_response(&self, version: Version) -> Response {
+        let body = JsonFormatter.format(self.to_value());
+        let mut headers = Headers::new();
+        headers.set("Content-Type", &[Self::CONTENT_TYPE]);
+        headers.set("Content-Length", &[&body.len().to_string()]);
+        ResponseFactory::version(version)
+            .with_status(self.status.clone(), headers)
+            .with_body(&body)
+    }
+}
+
+impl Display for ProblemDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.title, detail),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+impl std::error::Error for ProblemDetails {}
+
+impl From<Status> for ProblemDetails {
+    fn from(status: Status) -> Self {
+        Self::new(status)
+    }
+}
+
+/// Field errors become a `422` problem with an `errors` member.
+impl From<FieldErrors> for ProblemDetails {
+    fn from(errors: FieldErrors) -> Self {
+        Self::new(Status::UnprocessableEntity)
+            .with_detail(&errors.to_string())
+            .with_extension("errors", errors.to_value())
+    }
+}
+
+impl From<ExtractError> for ProblemDetails {
+    fn from(error: ExtractError) -> Self {
+        let problem = Self::new(error.status()).with_detail(&error.to_string());
+        match error.fields() {
+            Some(errors) => problem.with_extension("errors", errors.to_value()),
+            None => problem,
+        }
+    }
+}
+
+impl From<MultipartError> for ProblemDetails {
+    fn from(error: MultipartError) -> Self {
+        Self::new(error.status()).with_detail(&error.to_string())
+    }
+}
+
+/// [`Controller`] returning `Result<Response, E>`.
+///
+/// Errors are converted into [`ProblemDetails`] to answer the request.
+pub struct Fallible<F, E> {
+    handler: F,
+    error: PhantomData<fn() -> E>,
+}
+
+impl<F, E> Fallible<F, E> {
+    /// Wrap the fallible `handler`.
+    pub fn new(handler: F) -> Self {
+        Self {
+            handler,
+            error: PhantomData,
+        }
+    }
+}
+
+impl<Ctx, F, E> Controller<Ctx, Request, Response> for Fallible<F, E>
+where
+    F: FnMut(&Ctx, &mut Request) -> Result<Response, E> + Send,
+    E: Into<ProblemDetails>,
+{
+    fn handle(&mut self, context: &Ctx, req: &mut Request) -> Response {
+        (self.handler)(context, req)
+            .unwrap_or_else(|e| e.into().to_response(req.protocol_version()))
+    }
+}
+
+/// Middleware answering `500 Internal Server Error` problems when the rest
+/// of the chain panics.
+///
+/// Register it first so that it wraps the other middleware, with
+/// [`Router::with_middleware`](crate::http::routing::router::Router::with_middleware)
+/// to cover every route and the fallbacks. The panic
+/// message is only exposed in the `detail` member with
+/// [`ErrorHandler::expose_details`], meant for development.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorHandler {
+    expose_details: bool,
+}
+
+impl ErrorHandler {
+    /// Create a handler hiding panic messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expose panic messages in the responses.
+    pub fn expose_details(mut self, expose: bool) -> Self {
+        self.expose_details = expose;
+        self
+    }
+}
+
+impl<Ctx> Middleware<Ctx, Request, Response> for ErrorHandler {
+    fn handle(
+        &mut self,
+        context: &Ctx,
+        req: &mut Request,
+        next: &mut dyn Controller<Ctx, Request, Response>,
+    ) -> Response {
+        let version = req.protocol_version();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| next.handle(context, req)));
+        result.unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned());
+            let problem = ProblemDetails::new(Status::InternalServerError);
+            let problem = match message {
+                Some(message) if self.expose_details => problem.with_detail(&message),
+                _ => problem.with_detail("An unexpected error occurred"),
+            };
+            problem.to_response(version)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Parsable;
+    use crate::http::{RequestFactory, ResponseTrait, Uri};
+
+    #[test]
+    fn test_problem_body() {
+        let mut errors = FieldErrors::new();
+        errors.add("email", "is required");
+        let problem = ProblemDetails::from(errors)
+            .with_instance("/signup/42")
+            .with_extension("status", Value::Int(200))
+            .with_extension("a", Value::Bool(true));
+        let resp = problem.to_response(Version::Http1_1);
+        assert_eq!(resp.status(), Status::UnprocessableEntity);
+        assert_eq!(
+            resp.body(),
+            r#"{"type": "about:blank","title": "Unprocessable Entity","status": 422,"detail": "email: is required","instance": "/signup/42","errors": {"email": ["is required"]},"a": true}"#
+        );
+        assert_eq!(
+            resp.get_header_line("Content-Length"),
+            Some(resp.body().len().to_string())
+        );
+        let Value::Dictionary(members) = problem.to_value() else {
+            unreachable!()
+        };
+        assert_eq!(members.len(), 7);
+        assert_eq!(
+            problem.to_string(),
+            "Unprocessable Entity: email: is required"
+        );
+    }
+
+    #[test]
+    fn test_fallible_and_panics() {
+        let factory = RequestFactory::version(Version::Http1_0);
+        let mut req = factory.get(Uri::parse("/").unwrap().1, Headers::new());
+
+        let mut ctrl = Fallible::new(|_: &(), req: &mut Request| {
+            req.json().map(|_| -> Response { unreachable!() })
+        });
+        let resp = ctrl.handle(&(), &mut req);
+        assert_eq!(resp.status(), Status::UnsupportedMediaType);
+        assert_eq!(resp.protocol_version(), Version::Http1_0);
+
+        let mut panicking = |_: &(), _: &mut Request| -> Response { panic!("secret {}", 42) };
+        let resp = ErrorHandler::new().handle(&(), &mut req, &mut panicking);
+        assert_eq!(resp.status(), Status::InternalServerError);
+        assert!(!resp.body().contains("secret"));
+        let resp = ErrorHandler::new()
+            .expose_details(true)
+            .handle(&(), &mut req, &mut panicking);
+        assert!(resp.body().contains("\"detail\": \"secret 42\""));
+    }
+    #[test]
+    fn test_group_survives_panics() {
+        use crate::http::routing::router::{Route, RouteGroup, Router};
+        use crate::http::security::headers::SecurityHeaders;
+        use crate::http::{Method, ResponseFactory};
+
+        let factory = ResponseFactory::version(Version::Http1_1);
+        let mut group = RouteGroup::new("")
+            .with_before(Box::new(ErrorHandler::new()))
+            .with_before(Box::new(SecurityHeaders::new()));
+        group.add_route(Route::new(
+            "/ok",
+            vec![Method::Get],
+            Headers::new(),
+            Box::new(move |_: &(), _: &mut Request| factory.no_content(Headers::new())),
+        ));
+        group.add_route(Route::new(
+            "/boom",
+            vec![Method::Get],
+            Headers::new(),
+            Box::new(|_: &(), _: &mut Request| -> Response { panic!("boom") }),
+        ));
+        let mut router = Router::new();
+        router.add_group(group);
+
+        let factory = RequestFactory::version(Version::Http1_1);
+        for (path, status) in [
+            ("/ok", Status::NoContent),
+            ("/boom", Status::InternalServerError),
+            ("/ok", Status::NoContent),
+        ] {
+            let mut req = factory.get(Uri::parse(path).unwrap().1, Headers::new());
+            let resp = router.handle_request(&(), &mut req).unwrap();
+            assert_eq!(resp.status(), status);
+        }
+    }
+
+    #[test]
+    fn test_router_error_handler() {
+        use crate::http::routing::router::{Route, RouteGroup, Router};
+        use crate::http::Method;
+
+        let mut admin = RouteGroup::new("/admin");
+        admin.add_route(Route::new(
+            "/boom",
+            vec![Method::Get],
+            Headers::new(),
+            Box::new(|_: &(), _: &mut Request| -> Response { panic!("boom") }),
+        ));
+        let mut router = Router::new()
+            .with_middleware(Box::new(ErrorHandler::new()))
+            .with_not_found(Box::new(Fallible::new(
+                |_: &(), _: &mut Request| -> Result<Response, Status> { Err(Status::NotFound) },
+            )));
+        router.add_group(RouteGroup::new("/api"));
+        router.add_group(admin);
+
+        let factory = RequestFactory::version(Version::Http1_1);
+        for (path, status) in [
+            ("/admin/boom", Status::InternalServerError),
+            ("/api/none", Status::NotFound),
+        ] {
+            let mut req = factory.get(Uri::parse(path).unwrap().1, Headers::new());
+            let resp = router.handle_request(&(), &mut req).unwrap();
+            assert_eq!(resp.status(), status);
+            assert_eq!(
+                resp.get_header_line("Content-Type"),
+                Some("application/problem+json".to_string())
+            );
+        }
+    }
+}
@@ -32,7 +32,9 @@ pub trait Middleware<Ctx, Req: RequestTrait = Request, Res: ResponseTrait = Resp
     ) -> Res;
 }
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
+
+type LockedMiddleware<Ctx, Req, Res> = Arc<Mutex<Box<dyn Middleware<Ctx, Req, Res>>>>;
 
 /// Wrapper to share middleware instances between multiple routes.
 ///
@@ -40,7 +42,7 @@ use std::sync::{Arc, Mutex};
 /// cloned while maintaining interior mutability. Each clone will lock the
 /// middleware when handling a request so access is synchronised.
 pub struct SharedMiddleware<Ctx, Req: RequestTrait = Request, Res: ResponseTrait = Response> {
-    inner: Arc<Mutex<Box<dyn Middleware<Ctx, Req, Res>>>>,
+    inner: LockedMiddleware<Ctx, Req, Res>,
 }
 
 impl<Ctx, Req: RequestTrait, Res: ResponseTrait> Clone for SharedMiddleware<Ctx, Req, Res> {
@@ -77,7 +79,7 @@ impl<Ctx, Req: RequestTrait, Res: ResponseTrait> Middleware<Ctx, Req, Res>
         req: &mut Req,
         next: &mut dyn Controller<Ctx, Req, Res>,
     ) -> Res {
-        let mut guard = self.inner.lock().unwrap();
+        let mut guard = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
         guard.as_mut().handle(context, req, next)
     }
 }
@@ -112,7 +114,7 @@ impl<'a, Ctx, Req: RequestTrait, Res: ResponseTrait> Controller<Ctx, Req, Res>
 
 /// Recursively execute a slice of middleware, then the controller, followed by
 /// after middleware.
-fn execute_middleware_chain<Ctx, Req: RequestTrait, Res: ResponseTrait>(
+pub(crate) fn execute_middleware_chain<Ctx, Req: RequestTrait, Res: ResponseTrait>(
     before: &mut [Box<dyn Middleware<Ctx, Req, Res>>],
     controller: &mut dyn Controller<Ctx, Req, Res>,
     after: &mut [Box<dyn Middleware<Ctx, Req, Res>>],
@@ -195,6 +197,7 @@ impl<Ctx, Req: RequestTrait, Res: ResponseTrait> Controller<Ctx, Req, Res>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::concepts::Dictionary;
     use crate::http::{
         Authority, Headers, Message, MessageTrait, Method, Path, Query, Request, Response,
         ResponseFactory, Status, Uri, Version,
@@ -212,6 +215,8 @@ mod tests {
             method,
             target: uri,
             message: Message::v1_1(Headers::new(), String::new()),
+            attributes: Dictionary::new(),
+            raw_body: None,
         }
     }
 
@@ -3,10 +3,17 @@
 //! The router stores a list of [`Route`] definitions and can match an incoming
 //! [`Request`] to the first route that satisfies all
 //! conditions (path, method and required headers).
+//!
+//! Requests matching no route can be answered by fallback controllers, and
+//! router middleware wraps every dispatched request, fallbacks included.
 
 use crate::concepts::{BoxVec, Dictionary};
-use crate::http::routing::controller::{Controller, Mediator, Middleware, SharedMiddleware};
-use crate::http::{Headers, Method, Request, RequestTrait, Response, ResponseTrait};
+use crate::http::routing::controller::{
+    execute_middleware_chain, Controller, Mediator, Middleware, SharedMiddleware,
+};
+use crate::http::{
+    percent_decode, Headers, Method, Request, RequestTrait, Response, ResponseTrait,
+};
 
 /// A single route definition used by the [`Router`].
 pub struct Route<Ctx, Req: RequestTrait = Request, Res: ResponseTrait = Response> {
@@ -18,6 +25,8 @@ pub struct Route<Ctx, Req: RequestTrait = Request, Res: ResponseTrait = Response
     pub headers: Headers,
     /// Controller handling the request when this route matches.
     pub controller: Box<dyn Controller<Ctx, Req, Res>>,
+    before: BoxVec<dyn Middleware<Ctx, Req, Res>>,
+    after: BoxVec<dyn Middleware<Ctx, Req, Res>>,
 }
 
 impl<Ctx, Req: RequestTrait, Res: ResponseTrait> core::fmt::Debug for Route<Ctx, Req, Res> {
@@ -43,31 +52,55 @@ impl<Ctx, Req: RequestTrait, Res: ResponseTrait> Route<Ctx, Req, Res> {
             methods,
             headers,
             controller,
+            before: Vec::new(),
+            after: Vec::new(),
         }
     }
 
+    /// Append middleware executed before the controller of this route.
+    ///
+    /// Inside a [`RouteGroup`], it runs after the middleware of the group.
+    pub fn with_before(mut self, mw: Box<dyn Middleware<Ctx, Req, Res>>) -> Self {
+        self.before.push(mw);
+        self
+    }
+
+    /// Append middleware executed after the controller of this route.
+    ///
+    /// Inside a [`RouteGroup`], it runs before the middleware of the group.
+    pub fn with_after(mut self, mw: Box<dyn Middleware<Ctx, Req, Res>>) -> Self {
+        self.after.push(mw);
+        self
+    }
+
     /// Check whether `req` matches this route.
     pub fn matches(&self, req: &Req) -> Option<Dictionary<String>> {
         // check method
         if !self.methods.is_empty() && !self.methods.contains(&req.get_method()) {
             return None;
         }
-        // check headers
-        for (key, values) in self.headers.iter() {
-            if let Some(v) = req.headers().get(key) {
-                if v != values {
-                    return None;
-                }
-            } else {
-                return None;
-            }
+        if !self.matches_headers(req) {
+            return None;
         }
         self.match_path(&req.get_uri().path.to_string())
     }
 
+    /// Check the required headers of this route against `req`.
+    fn matches_headers(&self, req: &Req) -> bool {
+        self.headers
+            .iter()
+            .all(|(key, values)| req.headers().get(key) == Some(values))
+    }
+
     /// Invoke the controller for this route.
     pub fn handle(&mut self, context: &Ctx, req: &mut Req) -> Res {
-        self.controller.handle(context, req)
+        execute_middleware_chain(
+            &mut self.before,
+            self.controller.as_mut(),
+            &mut self.after,
+            context,
+            req,
+        )
     }
 
     /// Match the path part of the URL and extract parameters.
@@ -76,18 +109,47 @@ impl<Ctx, Req: RequestTrait, Res: ResponseTrait> Route<Ctx, Req, Res> {
     }
 
     /// Match `path` against a pattern and extract parameters.
+    ///
+    /// A last segment of the form `{*name}` captures the rest of the path,
+    /// possibly empty. Segments are percent-decoded before matching.
+    ///
+    /// ```
+    /// use hermes::http::routing::router::Route;
+    ///
+    /// let params = Route::<()>::match_pattern("/files/{*path}", "/files/a/b.txt").unwrap();
+    /// assert_eq!(params.get("path"), Some(&"a/b.txt".to_string()));
+    /// assert!(Route::<()>::match_pattern("/files/{*path}", "/files").is_some());
+    /// assert!(Route::<()>::match_pattern("/files/{id}", "/files/a/b").is_none());
+    /// let params = Route::<()>::match_pattern("/users/{name}", "/users/Jo%C3%A9").unwrap();
+    /// assert_eq!(params.get("name"), Some(&"Joé".to_string()));
+    /// ```
     pub fn match_pattern(pattern: &str, path: &str) -> Option<Dictionary<String>> {
         let mut params = Dictionary::new();
-        let pattern_parts: Vec<&str> = pattern.trim_matches('/').split('/').collect();
-        let path_parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let mut pattern_parts: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+        let mut path_parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if let Some(name) = pattern_parts
+            .last()
+            .and_then(|p| p.strip_prefix("{*"))
+            .and_then(|p| p.strip_suffix('}'))
+        {
+            let fixed = pattern_parts.len() - 1;
+            if path_parts.len() < fixed {
+                return None;
+            }
+            let rest = path_parts.split_off(fixed);
+            let rest: Vec<String> = rest.iter().map(|s| percent_decode(s)).collect();
+            params.insert(name.to_string(), rest.join("/"));
+            pattern_parts.truncate(fixed);
+        }
         if pattern_parts.len() != path_parts.len() {
             return None;
         }
         for (p, val) in pattern_parts.iter().zip(path_parts.iter()) {
+            let val = percent_decode(val);
             if p.starts_with('{') && p.ends_with('}') {
                 let name = &p[1..p.len() - 1];
-                params.insert(name.to_string(), val.to_string());
-            } else if p != val {
+                params.insert(name.to_string(), val);
+            } else if *p != val {
                 return None;
             }
         }
@@ -181,16 +243,20 @@ impl<Ctx: 'static, Req: RequestTrait + 'static, Res: ResponseTrait + 'static>
         let mut routes = Vec::new();
         for mut route in self.routes {
             route.pattern = Self::join_paths(&combined_prefix, &route.pattern);
-            let before_vec: BoxVec<dyn Middleware<Ctx, Req, Res>> = before
-                .iter()
-                .cloned()
-                .map(|m| Box::new(m) as Box<dyn Middleware<_, _, _>>)
-                .collect();
-            let after_vec: BoxVec<dyn Middleware<Ctx, Req, Res>> = after
+            let mut before_vec: BoxVec<dyn Middleware<Ctx, Req, Res>> = before
                 .iter()
                 .cloned()
                 .map(|m| Box::new(m) as Box<dyn Middleware<_, _, _>>)
                 .collect();
+            before_vec.append(&mut route.before);
+            let mut after_vec: BoxVec<dyn Middleware<Ctx, Req, Res>> =
+                std::mem::take(&mut route.after);
+            after_vec.extend(
+                after
+                    .iter()
+                    .cloned()
+                    .map(|m| Box::new(m) as Box<dyn Middleware<_, _, _>>),
+            );
             route.controller = Box::new(Mediator::new(before_vec, route.controller, after_vec));
             routes.push(route);
         }
@@ -203,9 +269,46 @@ impl<Ctx: 'static, Req: RequestTrait + 'static, Res: ResponseTrait + 'static>
 }
 
 /// Collection of [`Route`]s able to select one for a given request.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct Router<Ctx, Req: RequestTrait = Request, Res: ResponseTrait = Response> {
     routes: Vec<Route<Ctx, Req, Res>>,
+    middleware: BoxVec<dyn Middleware<Ctx, Req, Res>>,
+    not_found: Option<Box<dyn Controller<Ctx, Req, Res>>>,
+    method_not_allowed: Option<Box<dyn Controller<Ctx, Req, Res>>>,
+}
+
+impl<Ctx, Req: RequestTrait, Res: ResponseTrait> core::fmt::Debug for Router<Ctx, Req, Res> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes)
+            .field("middleware", &self.middleware.len())
+            .finish()
+    }
+}
+
+/// Controller selected by [`Router::handle_request`].
+enum Dispatch<'a, Ctx, Req: RequestTrait, Res: ResponseTrait> {
+    Route(&'a mut Route<Ctx, Req, Res>),
+    /// Fallback controller and the methods listed in the `Allow` header.
+    Fallback(&'a mut dyn Controller<Ctx, Req, Res>, Vec<Method>),
+}
+
+impl<Ctx, Req: RequestTrait, Res: ResponseTrait> Controller<Ctx, Req, Res>
+    for Dispatch<'_, Ctx, Req, Res>
+{
+    fn handle(&mut self, context: &Ctx, req: &mut Req) -> Res {
+        match self {
+            Dispatch::Route(route) => route.handle(context, req),
+            Dispatch::Fallback(controller, allow) => {
+                let mut res = controller.handle(context, req);
+                if !allow.is_empty() {
+                    let allow: Vec<String> = allow.iter().map(Method::to_string).collect();
+                    res.headers_mut().set("Allow", &[&allow.join(", ")]);
+                }
+                res
+            }
+        }
+    }
 }
 
 impl<Ctx: 'static, Req: RequestTrait + 'static, Res: ResponseTrait + 'static>
@@ -213,7 +316,42 @@ impl<Ctx: 'static, Req: RequestTrait + 'static, Res: ResponseTrait + 'static>
 {
     /// Create an empty [`Router`].
     pub fn new() -> Self {
-        Self { routes: Vec::new() }
+        Self {
+            routes: Vec::new(),
+            middleware: Vec::new(),
+            not_found: None,
+            method_not_allowed: None,
+        }
+    }
+
+    /// Append middleware wrapping every request dispatched by the router,
+    /// fallbacks included.
+    ///
+    /// It runs before the middleware of groups and routes, in the order it is
+    /// added.
+    pub fn with_middleware(mut self, mw: Box<dyn Middleware<Ctx, Req, Res>>) -> Self {
+        self.middleware.push(mw);
+        self
+    }
+
+    /// Answer requests matching no route with `controller`.
+    pub fn with_not_found(mut self, controller: Box<dyn Controller<Ctx, Req, Res>>) -> Self {
+        self.not_found = Some(controller);
+        self
+    }
+
+    /// Answer requests matching the path of routes but none of their methods
+    /// with `controller`.
+    ///
+    /// The router adds an `Allow` header listing the methods of these routes.
+    /// Without this fallback, such requests are answered by the
+    /// [`with_not_found`](Router::with_not_found) one.
+    pub fn with_method_not_allowed(
+        mut self,
+        controller: Box<dyn Controller<Ctx, Req, Res>>,
+    ) -> Self {
+        self.method_not_allowed = Some(controller);
+        self
     }
 
     /// Register a new route.
@@ -233,7 +371,7 @@ impl<Ctx: 'static, Req: RequestTrait + 'static, Res: ResponseTrait + 'static>
     }
 
     /// Attempt to match `req` against registered routes.
-    pub fn match_request(&self, req: &Req) -> Option<RouteMatch<Ctx, Req, Res>> {
+    pub fn match_request(&self, req: &Req) -> Option<RouteMatch<'_, Ctx, Req, Res>> {
         for route in &self.routes {
             if let Some(params) = route.matches(req) {
                 return Some(RouteMatch { route, params });
@@ -242,14 +380,53 @@ impl<Ctx: 'static, Req: RequestTrait + 'static, Res: ResponseTrait + 'static>
         None
     }
 
-    /// Handle `req` and return the generated [`Response`] if a route matches.
-    pub fn handle_request(&mut self, context: &Ctx, req: &mut Req) -> Option<Res> {
-        for route in &mut self.routes {
-            if route.matches(req).is_some() {
-                return Some(route.handle(context, req));
+    /// Methods of the routes matching the path and headers of `req`.
+    fn allowed_methods(&self, req: &Req) -> Vec<Method> {
+        let path = req.get_uri().path.to_string();
+        let mut methods = Vec::new();
+        for route in &self.routes {
+            if route.matches_headers(req) && route.match_path(&path).is_some() {
+                for method in &route.methods {
+                    if !methods.contains(method) {
+                        methods.push(method.clone());
+                    }
+                }
             }
         }
-        None
+        methods
+    }
+
+    /// Handle `req` and return the generated [`Response`] if a route or a
+    /// fallback answers it.
+    pub fn handle_request(&mut self, context: &Ctx, req: &mut Req) -> Option<Res> {
+        let found = self
+            .routes
+            .iter()
+            .enumerate()
+            .find_map(|(index, route)| route.matches(req).map(|params| (index, params)));
+        let mut dispatch = match found {
+            Some((index, params)) => {
+                req.set_route_params(params);
+                Dispatch::Route(&mut self.routes[index])
+            }
+            None => {
+                let allow = self.allowed_methods(req);
+                match (&mut self.method_not_allowed, &mut self.not_found) {
+                    (Some(controller), _) if !allow.is_empty() => {
+                        Dispatch::Fallback(controller.as_mut(), allow)
+                    }
+                    (_, Some(controller)) => Dispatch::Fallback(controller.as_mut(), Vec::new()),
+                    _ => return None,
+                }
+            }
+        };
+        Some(execute_middleware_chain(
+            &mut self.middleware,
+            &mut dispatch,
+            &mut [],
+            context,
+            req,
+        ))
     }
 }
 
@@ -273,6 +450,8 @@ mod tests {
             method,
             target: uri,
             message: Message::v1_1(Headers::new(), String::new()),
+            attributes: Dictionary::new(),
+            raw_body: None,
         }
     }
 
@@ -523,4 +702,121 @@ mod tests {
         assert!(req.has_header("X-Group-Before"));
         assert!(resp.has_header("X-Group-After"));
     }
+
+    struct RouteBefore;
+    impl Middleware<(), Request, Response> for RouteBefore {
+        fn handle(
+            &mut self,
+            ctx: &(),
+            req: &mut Request,
+            next: &mut dyn Controller<(), Request, Response>,
+        ) -> Response {
+            // group middleware runs first
+            assert!(req.has_header("X-Group-Before"));
+            next.handle(ctx, req)
+        }
+    }
+
+    #[test]
+    fn test_route_middleware_and_params() {
+        let factory = ResponseFactory::version(Version::Http1_1);
+        let mut group = RouteGroup::new("/api").with_before(Box::new(GroupBefore));
+        group.add_route(
+            Route::new(
+                "/items/{id}",
+                vec![Method::Get],
+                Headers::new(),
+                Box::new(move |_: &(), req: &mut Request| {
+                    assert_eq!(req.param("id"), Some("7"));
+                    factory.no_content(Headers::new())
+                }),
+            )
+            .with_before(Box::new(RouteBefore))
+            .with_after(Box::new(GroupAfter)),
+        );
+
+        let mut router = Router::new();
+        router.add_group(group);
+
+        let mut req = request(Method::Get, "/api/items/7");
+        let resp = router.handle_request(&(), &mut req).unwrap();
+        assert_eq!(resp.status(), Status::NoContent);
+        assert!(resp.has_header("X-Group-After"));
+    }
+
+    struct RouterBefore;
+    impl Middleware<(), Request, Response> for RouterBefore {
+        fn handle(
+            &mut self,
+            ctx: &(),
+            req: &mut Request,
+            next: &mut dyn Controller<(), Request, Response>,
+        ) -> Response {
+            // router middleware runs before the group one
+            assert!(!req.has_header("X-Group-Before"));
+            req.headers_mut().add("X-Router", "1");
+            next.handle(ctx, req)
+        }
+    }
+
+    #[test]
+    fn test_router_middleware_and_fallbacks() {
+        fn answer(status: Status) -> Box<dyn Controller<(), Request, Response>> {
+            Box::new(move |_: &(), _req: &mut Request| {
+                ResponseFactory::version(Version::Http1_1)
+                    .with_status(status.clone(), Headers::new())
+            })
+        }
+
+        let mut group = RouteGroup::new("/api").with_before(Box::new(GroupBefore));
+        group.add_route(Route::new(
+            "/items/{id}",
+            vec![Method::Get, Method::Put],
+            Headers::new(),
+            answer(Status::NoContent),
+        ));
+        group.add_route(Route::new(
+            "/items/{id}",
+            vec![Method::Get, Method::Delete],
+            Headers::new(),
+            answer(Status::NoContent),
+        ));
+
+        let mut router = Router::new().with_middleware(Box::new(RouterBefore));
+        router.add_group(group);
+        assert!(router
+            .handle_request(&(), &mut request(Method::Get, "/missing"))
+            .is_none());
+
+        let mut router = router
+            .with_middleware(Box::new(GroupAfter))
+            .with_not_found(answer(Status::NotFound));
+        let mut req = request(Method::Get, "/api/items/7");
+        let resp = router.handle_request(&(), &mut req).unwrap();
+        assert_eq!(resp.status(), Status::NoContent);
+        assert!(req.has_header("X-Router"));
+        assert!(resp.has_header("X-Group-After"));
+
+        let mut req = request(Method::Post, "/api/items/7");
+        let resp = router.handle_request(&(), &mut req).unwrap();
+        assert_eq!(resp.status(), Status::NotFound);
+        assert!(req.has_header("X-Router"));
+        assert!(resp.has_header("X-Group-After"));
+        assert!(!resp.has_header("Allow"));
+
+        let mut router = router.with_method_not_allowed(answer(Status::MethodNotAllowed));
+        let resp = router
+            .handle_request(&(), &mut request(Method::Post, "/api/items/7"))
+            .unwrap();
+        assert_eq!(resp.status(), Status::MethodNotAllowed);
+        assert_eq!(
+            resp.get_header_line("Allow"),
+            Some("GET, PUT, DELETE".to_string())
+        );
+        assert!(resp.has_header("X-Group-After"));
+        let resp = router
+            .handle_request(&(), &mut request(Method::Post, "/api/other"))
+            .unwrap();
+        assert_eq!(resp.status(), Status::NotFound);
+    }
 }
@@ -0,0 +1,58 @@
+//! Security related services and middleware.
+//!
+//! The submodules provide building blocks to protect routes: parsing
+//! credentials sent by clients, verifying them against services registered in
+//! the [`Container`](crate::container::Container), exposing the
+//! authenticated principal to controllers and checking what it is allowed to
+//! do.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod authentication;
+pub mod authorization;
+pub mod cors;
+pub mod csrf;
+pub mod headers;
+pub mod jwt;
+pub mod oauth;
+pub mod password;
+pub mod rate_limit;
+
+/// Generate `bytes` random bytes from the operating system and encode them
+/// as an unpadded base64url string.
+///
+/// Suitable for nonces, CSRF tokens or OAuth `state` values.
+///
+/// # Examples
+/// ```
+/// use hermes::http::security::random_token;
+///
+/// let token = random_token(32);
+/// assert_eq!(token.len(), 43);
+/// assert_ne!(token, random_token(32));
+/// ```
+pub fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::rngs::OsRng.fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+/// Compare two secret tokens in constant time.
+pub(crate) fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Current Unix time in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,572 @@
+//! HTTP `Basic` and `Bearer` authentication.
+//!
+//! [`Credentials`] are read from the `Authorization` header and checked by a
+//! [`CredentialVerifier`] (user name and password) or a [`TokenVerifier`]
+//! (bearer tokens). Verifiers are services registered in the [`Container`] as
+//! boxed trait objects. The [`Authenticator`] middleware ties everything
+//! together: it rejects unauthenticated requests with a `401 Unauthorized`
+//! response carrying the right `WWW-Authenticate` challenge and stores the
+//! authenticated [`Principal`] as a request attribute for controllers.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::container::Container;
+//! use hermes::http::routing::router::{Route, RouteGroup, Router};
+//! use hermes::http::security::authentication::{
+//!     Authenticator, Credentials, CredentialVerifier, Principal,
+//! };
+//! use hermes::http::{
+//!     Headers, Method, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, Uri,
+//!     Version,
+//! };
+//! use hermes::concepts::Parsable;
+//!
+//! fn whoami(_: &Container, req: &mut Request) -> Response {
+//!     let principal = Principal::from_request(req).unwrap();
+//!     ResponseFactory::version(Version::Http1_1).ok(Headers::new(), principal.identifier)
+//! }
+//!
+//! let mut container = Container::new();
+//! container.register::<Box<dyn CredentialVerifier>>(Box::new(|user: &str, password: &str| {
+//!     (user == "alice" && password == "secret").then(|| Principal::new(user))
+//! }));
+//!
+//! let mut group = RouteGroup::new("/admin").with_before(Box::new(Authenticator::basic("admin")));
+//! group.add_route(Route::new("/me", vec![Method::Get], Headers::new(), Box::new(whoami)));
+//! let mut router: Router<Container> = Router::new();
+//! router.add_group(group);
+//!
+//! let (_, uri) = Uri::parse("/admin/me").unwrap();
+//! let factory = RequestFactory::version(Version::Http1_1);
+//!
+//! let mut anonymous = factory.get(uri.clone(), Headers::new());
+//! let resp = router.handle_request(&container, &mut anonymous).unwrap();
+//! assert_eq!(resp.code(), 401);
+//!
+//! let mut headers = Headers::new();
+//! headers.add("Authorization", &Credentials::basic("alice", "secret").to_string());
+//! let mut req = factory.get(uri, headers);
+//! let resp = router.handle_request(&container, &mut req).unwrap();
+//! assert_eq!(resp.code(), 200);
+//! ```
+
+use crate::concepts::value::Value;
+use crate::concepts::Dictionary;
+use crate::container::Container;
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::{
+    AuthenticationScheme, Headers, MessageTrait, Request, Response, ResponseFactory, Status,
+    WWWAuthenticate,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::any::Any;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// Credentials sent by a client in the `Authorization` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// `Basic` credentials (RFC 7617).
+    Basic { username: String, password: String },
+    /// `Bearer` token (RFC 6750).
+    Bearer(String),
+}
+
+impl Credentials {
+    /// Build `Basic` credentials.
+    pub fn basic(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Build a `Bearer` token.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self::Bearer(token.into())
+    }
+
+    /// Authentication scheme of these credentials.
+    pub fn scheme(&self) -> AuthenticationScheme {
+        match self {
+            Credentials::Basic { .. } => AuthenticationScheme::Basic,
+            Credentials::Bearer(_) => AuthenticationScheme::Bearer,
+        }
+    }
+
+    /// Parse the value of an `Authorization` header.
+    ///
+    /// The scheme name is case-insensitive. Returns `None` for other schemes
+    /// or malformed credentials.
+    ///
+    /// ```
+    /// use hermes::http::security::authentication::Credentials;
+    ///
+    /// let creds = Credentials::parse("Basic YWxpY2U6c2VjcmV0").unwrap();
+    /// assert_eq!(creds, Credentials::basic("alice", "secret"));
+    /// assert_eq!(Credentials::parse("bearer abc"), Some(Credentials::bearer("abc")));
+    /// assert!(Credentials::parse("Digest abc").is_none());
+    /// ```
+    pub fn parse(header: &str) -> Option<Self> {
+        let (scheme, value) = header.trim().split_once(' ')?;
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        if scheme.eq_ignore_ascii_case("Basic") {
+            let decoded = STANDARD.decode(value).ok()?;
+            let decoded = String::from_utf8(decoded).ok()?;
+            let (username, password) = decoded.split_once(':')?;
+            Some(Self::basic(username, password))
+        } else if scheme.eq_ignore_ascii_case("Bearer") {
+            Some(Self::bearer(value))
+        } else {
+            None
+        }
+    }
+
+    /// Read the credentials from the `Authorization` header of `req`.
+    pub fn from_request(req: &impl MessageTrait) -> Option<Self> {
+        req.get_header_line("Authorization")
+            .and_then(|line| Self::parse(&line))
+    }
+}
+
+impl Display for Credentials {
+    /// Render the credentials as an `Authorization` header value.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Credentials::Basic { username, password } => write!(
+                f,
+                "{} {}",
+                self.scheme(),
+                STANDARD.encode(format!("{}:{}", username, password))
+            ),
+            Credentials::Bearer(token) => write!(f, "{} {}", self.scheme(), token),
+        }
+    }
+}
+
+/// Identity of an authenticated client.
+///
+/// A principal is stored in the request attributes under
+/// [`Principal::ATTRIBUTE`] as a [`Value::Dictionary`] so it can be copied to
+/// sessions or serialized like any other value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Principal {
+    /// Unique identifier of the user or client (user name, subject...).
+    pub identifier: String,
+    /// Roles granted to the principal.
+    pub roles: Vec<String>,
+    /// Fine-grained permissions granted to the principal.
+    pub permissions: Vec<String>,
+    /// Additional data describing the principal.
+    pub claims: Dictionary<Value>,
+}
+
+impl Principal {
+    /// Name of the request attribute holding the authenticated principal.
+    pub const ATTRIBUTE: &'static str = "principal";
+
+    /// Create a principal without roles, permissions or claims.
+    pub fn new(identifier: impl Into<String>) -> Self {
+        Self {
+            identifier: identifier.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Grant a role to the principal.
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    /// Grant a permission to the principal.
+    pub fn with_permission(mut self, permission: impl Into<String>) -> Self {
+        self.permissions.push(permission.into());
+        self
+    }
+
+    /// Attach a claim to the principal.
+    pub fn with_claim(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.claims.insert(name.into(), value);
+        self
+    }
+
+    /// Returns `true` if the principal has `role`.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Returns `true` if the principal has `permission`.
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+
+    /// Convert the principal into a [`Value::Dictionary`].
+    pub fn to_value(&self) -> Value {
+        let strings =
+            |list: &[String]| Value::Array(list.iter().cloned().map(Value::String).collect());
+        let mut d = Dictionary::new();
+        d.insert(
+            "identifier".to_string(),
+            Value::String(self.identifier.clone()),
+        );
+        d.insert("roles".to_string(), strings(&self.roles));
+        d.insert("permissions".to_string(), strings(&self.permissions));
+        d.insert("claims".to_string(), Value::Dictionary(self.claims.clone()));
+        Value::Dictionary(d)
+    }
+
+    /// Rebuild a principal from a value produced by [`Principal::to_value`].
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let Value::Dictionary(d) = value else {
+            return None;
+        };
+        let Some(Value::String(identifier)) = d.get("identifier") else {
+            return None;
+        };
+        let strings = |key: &str| match d.get(key) {
+            Some(Value::Array(a)) => a
+                .iter()
+                .filter_map(|v| match v {
+                    Value::String(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Some(Self {
+            identifier: identifier.clone(),
+            roles: strings("roles"),
+            permissions: strings("permissions"),
+            claims: match d.get("claims") {
+                Some(Value::Dictionary(c)) => c.clone(),
+                _ => Dictionary::new(),
+            },
+        })
+    }
+
+    /// Retrieve the principal authenticated for `req`, if any.
+    pub fn from_request(req: &Request) -> Option<Self> {
+        req.attribute(Self::ATTRIBUTE).and_then(Self::from_value)
+    }
+}
+
+/// Service checking a user name and password.
+///
+/// Register it in the [`Container`] as a `Box<dyn CredentialVerifier>`.
+/// Closures of type `Fn(&str, &str) -> Option<Principal>` implement this trait.
+pub trait CredentialVerifier: Send + Sync {
+    /// Return the principal matching the credentials or `None` if they are invalid.
+    fn verify(&self, username: &str, password: &str) -> Option<Principal>;
+}
+
+impl<F> CredentialVerifier for F
+where
+    F: Fn(&str, &str) -> Option<Principal> + Send + Sync,
+{
+    fn verify(&self, username: &str, password: &str) -> Option<Principal> {
+        self(username, password)
+    }
+}
+
+/// Service checking a bearer token.
+///
+/// Register it in the [`Container`] as a `Box<dyn TokenVerifier>`.
+/// Closures of type `Fn(&str) -> Option<Principal>` implement this trait.
+pub trait TokenVerifier: Send + Sync {
+    /// Return the principal the token was issued to or `None` if it is invalid.
+    fn verify(&self, token: &str) -> Option<Principal>;
+}
+
+impl<F> TokenVerifier for F
+where
+    F: Fn(&str) -> Option<Principal> + Send + Sync,
+{
+    fn verify(&self, token: &str) -> Option<Principal> {
+        self(token)
+    }
+}
+
+/// Middleware authenticating requests with `Basic` or `Bearer` credentials.
+///
+/// The verifier is resolved from the [`Container`] on each request, using the
+/// default instance unless a name is given with
+/// [`with_verifier`](Authenticator::with_verifier). Requests without valid
+/// credentials receive a `401 Unauthorized` response. A missing verifier is a
+/// configuration error and answers `500 Internal Server Error`.
+#[derive(Debug, Clone)]
+pub struct Authenticator {
+    scheme: AuthenticationScheme,
+    realm: Option<String>,
+    verifier: Option<String>,
+    optional: bool,
+}
+
+impl Authenticator {
+    fn new(scheme: AuthenticationScheme, realm: &str) -> Self {
+        Self {
+            scheme,
+            realm: (!realm.is_empty()).then(|| realm.to_string()),
+            verifier: None,
+            optional: false,
+        }
+    }
+
+    /// Authenticate with `Basic` credentials checked by a [`CredentialVerifier`].
+    pub fn basic(realm: &str) -> Self {
+        Self::new(AuthenticationScheme::Basic, realm)
+    }
+
+    /// Authenticate with `Bearer` tokens checked by a [`TokenVerifier`].
+    pub fn bearer(realm: &str) -> Self {
+        Self::new(AuthenticationScheme::Bearer, realm)
+    }
+
+    /// Resolve the verifier registered under `name` instead of the default one.
+    pub fn with_verifier(mut self, name: &str) -> Self {
+        self.verifier = Some(name.to_string());
+        self
+    }
+
+    /// Let requests without an `Authorization` header through anonymously.
+    ///
+    /// Requests sending invalid credentials, including credentials of
+    /// another scheme, are still rejected.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Challenge sent in the `WWW-Authenticate` header of `401` responses.
+    pub fn challenge(&self) -> WWWAuthenticate {
+        WWWAuthenticate {
+            scheme: self.scheme,
+            realm: self.realm.clone(),
+            charset: None,
+        }
+    }
+
+    /// Verify `credentials` with the verifier registered in `container`.
+    pub fn authenticate(
+        &self,
+        container: &Container,
+        credentials: &Credentials,
+    ) -> Option<Principal> {
+        match credentials {
+            Credentials::Basic { username, password } => self
+                .resolve::<Box<dyn CredentialVerifier>>(container)?
+                .verify(username, password),
+            Credentials::Bearer(token) => self
+                .resolve::<Box<dyn TokenVerifier>>(container)?
+                .verify(token),
+        }
+    }
+
+    fn has_verifier(&self, container: &Container) -> bool {
+        match self.scheme {
+            AuthenticationScheme::Basic => self
+                .resolve::<Box<dyn CredentialVerifier>>(container)
+                .is_some(),
+            _ => self.resolve::<Box<dyn TokenVerifier>>(container).is_some(),
+        }
+    }
+
+    fn resolve<T: Any + Send + Sync>(&self, container: &Container) -> Option<Arc<T>> {
+        match &self.verifier {
+            Some(name) => container.resolve_named::<T>(name),
+            None => container.resolve::<T>(),
+        }
+    }
+
+    fn unauthorized(&self, req: &Request) -> Response {
+        ResponseFactory::version(req.protocol_version())
+            .unauthorized(self.challenge(), Headers::new())
+    }
+}
+
+impl Middleware<Container, Request, Response> for Authenticator {
+    fn handle(
+        &mut self,
+        context: &Container,
+        req: &mut Request,
+        next: &mut dyn Controller<Container, Request, Response>,
+    ) -> Response {
+        req.remove_attribute(Principal::ATTRIBUTE);
+        if self.optional && !req.has_header("Authorization") {
+            return next.handle(context, req);
+        }
+        if !self.has_verifier(context) {
+            return ResponseFactory::version(req.protocol_version())
+                .with_status(Status::InternalServerError, Headers::new());
+        }
+        let credentials = Credentials::from_request(req).filter(|c| c.scheme() == self.scheme);
+        let Some(credentials) = credentials else {
+            return self.unauthorized(req);
+        };
+        match self.authenticate(context, &credentials) {
+            Some(principal) => {
+                req.set_attribute(Principal::ATTRIBUTE, principal.to_value());
+                next.handle(context, req)
+            }
+            None => self.unauthorized(req),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{RequestFactory, ResponseTrait, Status, Uri, Version};
+
+    fn container() -> Container {
+        let mut c = Container::new();
+        c.register::<Box<dyn CredentialVerifier>>(Box::new(|user: &str, password: &str| {
+            (user == "alice" && password == "secret")
+                .then(|| Principal::new(user).with_role("admin"))
+        }));
+        c.register::<Box<dyn TokenVerifier>>(Box::new(|token: &str| {
+            (token == "t0k3n").then(|| Principal::new("service"))
+        }));
+        c
+    }
+
+    fn request(authorization: Option<&str>) -> Request {
+        let mut headers = Headers::new();
+        if let Some(value) = authorization {
+            headers.add("Authorization", value);
+        }
+        RequestFactory::version(Version::Http1_1).get(Uri::default(), headers)
+    }
+
+    fn echo(_: &Container, req: &mut Request) -> Response {
+        let body = Principal::from_request(req)
+            .map(|p| p.identifier)
+            .unwrap_or_else(|| "anonymous".to_string());
+        ResponseFactory::version(Version::Http1_1).ok(Headers::new(), body)
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        assert_eq!(
+            Credentials::parse("Basic YWxpY2U6c2VjcmV0"),
+            Some(Credentials::basic("alice", "secret"))
+        );
+        assert_eq!(
+            Credentials::parse("Basic YTpiOmM="),
+            Some(Credentials::basic("a", "b:c"))
+        );
+        assert_eq!(Credentials::parse("Basic !!!"), None);
+        assert_eq!(Credentials::parse("Bearer "), None);
+        assert_eq!(Credentials::parse("Bearer"), None);
+        let creds = Credentials::basic("alice", "secret");
+        assert_eq!(Credentials::parse(&creds.to_string()), Some(creds));
+    }
+
+    #[test]
+    fn test_principal_value_round_trip() {
+        let principal = Principal::new("bob")
+            .with_role("editor")
+            .with_permission("posts.write")
+            .with_claim("email", Value::String("bob@example.com".into()));
+        assert!(principal.has_role("editor"));
+        assert!(!principal.has_role("admin"));
+        assert!(principal.has_permission("posts.write"));
+        assert_eq!(
+            Principal::from_value(&principal.to_value()),
+            Some(principal)
+        );
+        assert_eq!(Principal::from_value(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_basic_authentication() {
+        let c = container();
+        let mut auth = Authenticator::basic("admin");
+
+        let mut req = request(None);
+        let resp = auth.handle(&c, &mut req, &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+        assert_eq!(
+            resp.get_header_line("WWW-Authenticate"),
+            Some("Basic realm=\"admin\"".to_string())
+        );
+
+        let wrong = Credentials::basic("alice", "nope").to_string();
+        let resp = auth.handle(&c, &mut request(Some(&wrong)), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+
+        let good = Credentials::basic("alice", "secret").to_string();
+        let mut req = request(Some(&good));
+        let resp = auth.handle(&c, &mut req, &mut echo);
+        assert_eq!(resp.status(), Status::OK);
+        assert_eq!(resp.body(), "alice");
+        assert!(Principal::from_request(&req).unwrap().has_role("admin"));
+    }
+
+    #[test]
+    fn test_bearer_authentication() {
+        let c = container();
+        let mut auth = Authenticator::bearer("api");
+
+        let resp = auth.handle(&c, &mut request(Some("Bearer t0k3n")), &mut echo);
+        assert_eq!(resp.body(), "service");
+
+        let resp = auth.handle(&c, &mut request(Some("Bearer bad")), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+        assert_eq!(
+            resp.get_header_line("WWW-Authenticate"),
+            Some("Bearer realm=\"api\"".to_string())
+        );
+
+        let basic = Credentials::basic("alice", "secret").to_string();
+        let resp = auth.handle(&c, &mut request(Some(&basic)), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+    }
+
+    #[test]
+    fn test_optional_and_named_verifier() {
+        let mut c = container();
+        c.register_named::<Box<dyn TokenVerifier>>(
+            "internal",
+            Box::new(|token: &str| (token == "internal").then(|| Principal::new("robot"))),
+        );
+
+        let mut auth = Authenticator::bearer("api").optional();
+        let mut req =
+            request(None).with_attribute(Principal::ATTRIBUTE, Principal::new("spoof").to_value());
+        let resp = auth.handle(&c, &mut req, &mut echo);
+        assert_eq!(resp.body(), "anonymous");
+        let resp = auth.handle(&c, &mut request(Some("Bearer bad")), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+        let basic = Credentials::basic("alice", "secret").to_string();
+        let resp = auth.handle(&c, &mut request(Some(&basic)), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+        let resp = auth.handle(&c, &mut request(Some("Bearer")), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+
+        let mut auth = Authenticator::bearer("api").with_verifier("internal");
+        let resp = auth.handle(&c, &mut request(Some("Bearer internal")), &mut echo);
+        assert_eq!(resp.body(), "robot");
+        let resp = auth.handle(&c, &mut request(Some("Bearer t0k3n")), &mut echo);
+        assert_eq!(resp.status(), Status::Unauthorized);
+    }
+
+    #[test]
+    fn test_missing_verifier_fails() {
+        let c = Container::new();
+        let mut auth = Authenticator::basic("");
+        let good = Credentials::basic("alice", "secret").to_string();
+        let resp = auth.handle(&c, &mut request(Some(&good)), &mut echo);
+        assert_eq!(resp.status(), Status::InternalServerError);
+        assert_eq!(resp.get_header_line("WWW-Authenticate"), None);
+
+        let mut auth = Authenticator::bearer("").with_verifier("missing");
+        let resp = auth.handle(&container(), &mut request(None), &mut echo);
+        assert_eq!(resp.status(), Status::InternalServerError);
+    }
+}
@@ -0,0 +1,332 @@
+//! Role, permission and policy based authorization.
+//!
+//! A [`Guard`] is a middleware declaring what the authenticated [`Principal`]
+//! must be allowed to do to reach a [`Route`](crate::http::routing::router::Route)
+//! or the routes of a [`RouteGroup`](crate::http::routing::router::RouteGroup).
+//! It must run after an
+//! [`Authenticator`](crate::http::security::authentication::Authenticator):
+//! requests without principal or failing a requirement receive a
+//! `403 Forbidden` response.
+//!
+//! Besides roles and permissions, a guard evaluates [`Policy`] objects. They
+//! receive the whole request, so resource-level decisions can use the route
+//! parameters exposed by [`Request::param`]. Policies are either given inline
+//! or registered by name in the [`Container`] as `Box<dyn Policy>`.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::container::Container;
+//! use hermes::http::routing::router::{Route, Router};
+//! use hermes::http::security::authentication::Principal;
+//! use hermes::http::security::authorization::{Guard, Policy};
+//! use hermes::http::{Headers, Method, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, Uri, Version};
+//! use hermes::concepts::Parsable;
+//!
+//! fn profile(_: &Container, _: &mut Request) -> Response {
+//!     ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+//! }
+//!
+//! let mut container = Container::new();
+//! container.register_named::<Box<dyn Policy>>(
+//!     "owner",
+//!     Box::new(|_: &Container, principal: &Principal, req: &Request| {
+//!         req.param("user") == Some(principal.identifier.as_str())
+//!     }),
+//! );
+//!
+//! let mut router: Router<Container> = Router::new();
+//! router.add_route(
+//!     Route::new("/users/{user}", vec![Method::Get], Headers::new(), Box::new(profile))
+//!         .with_before(Box::new(Guard::policy("owner"))),
+//! );
+//!
+//! let factory = RequestFactory::version(Version::Http1_1);
+//! let (_, uri) = Uri::parse("/users/alice").unwrap();
+//! let alice = Principal::new("alice").to_value();
+//! let mut req = factory.get(uri.clone(), Headers::new()).with_attribute(Principal::ATTRIBUTE, alice);
+//! assert_eq!(router.handle_request(&container, &mut req).unwrap().code(), 204);
+//!
+//! let bob = Principal::new("bob").to_value();
+//! let mut req = factory.get(uri, Headers::new()).with_attribute(Principal::ATTRIBUTE, bob);
+//! assert_eq!(router.handle_request(&container, &mut req).unwrap().code(), 403);
+//! ```
+
+use crate::container::Container;
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::security::authentication::Principal;
+use crate::http::{Headers, MessageTrait, Request, Response, ResponseFactory};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// Decide whether a principal may perform a request.
+///
+/// Closures of type `Fn(&Container, &Principal, &Request) -> bool`
+/// implement this trait.
+pub trait Policy: Send + Sync {
+    /// Return `true` if `principal` is allowed to perform `req`.
+    fn allows(&self, container: &Container, principal: &Principal, req: &Request) -> bool;
+}
+
+impl<F> Policy for F
+where
+    F: Fn(&Container, &Principal, &Request) -> bool + Send + Sync,
+{
+    fn allows(&self, container: &Container, principal: &Principal, req: &Request) -> bool {
+        self(container, principal, req)
+    }
+}
+
+#[derive(Clone)]
+enum Requirement {
+    Role(String),
+    AnyRole(Vec<String>),
+    Permission(String),
+    Named(String),
+    Inline(Arc<dyn Policy>),
+}
+
+impl Debug for Requirement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Role(role) => f.debug_tuple("Role").field(role).finish(),
+            Requirement::AnyRole(roles) => f.debug_tuple("AnyRole").field(roles).finish(),
+            Requirement::Permission(p) => f.debug_tuple("Permission").field(p).finish(),
+            Requirement::Named(name) => f.debug_tuple("Named").field(name).finish(),
+            Requirement::Inline(_) => f.write_str("Inline"),
+        }
+    }
+}
+
+/// Middleware rejecting requests whose principal does not meet all the
+/// declared requirements with `403 Forbidden`.
+///
+/// Named policies missing from the [`Container`] deny access.
+#[derive(Debug, Clone, Default)]
+pub struct Guard {
+    requirements: Vec<Requirement>,
+}
+
+impl Guard {
+    /// Create a guard only requiring an authenticated principal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a guard requiring `role`.
+    pub fn role(role: &str) -> Self {
+        Self::new().with_role(role)
+    }
+
+    /// Create a guard requiring `permission`.
+    pub fn permission(permission: &str) -> Self {
+        Self::new().with_permission(permission)
+    }
+
+    /// Create a guard evaluating the policy registered under `name`.
+    pub fn policy(name: &str) -> Self {
+        Self::new().with_policy(name)
+    }
+
+    /// Require `role`.
+    pub fn with_role(mut self, role: &str) -> Self {
+        self.requirements.push(Requirement::Role(role.to_string()));
+        self
+    }
+
+    /// Require at least one of `roles`.
+    pub fn with_any_role(mut self, roles: &[&str]) -> Self {
+        self.requirements.push(Requirement::AnyRole(
+            roles.iter().map(|r| r.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Require `permission`.
+    pub fn with_permission(mut self, permission: &str) -> Self {
+        self.requirements
+            .push(Requirement::Permission(permission.to_string()));
+        self
+    }
+
+    /// Require the `Box<dyn Policy>` registered under `name` in the container.
+    pub fn with_policy(mut self, name: &str) -> Self {
+        self.requirements.push(Requirement::Named(name.to_string()));
+        self
+    }
+
+    /// Require `policy`.
+    pub fn with(mut self, policy: impl Policy + 'static) -> Self {
+        self.requirements
+            .push(Requirement::Inline(Arc::new(policy)));
+        self
+    }
+
+    /// Return `true` if `principal` meets every requirement for `req`.
+    pub fn allows(&self, container: &Container, principal: &Principal, req: &Request) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| match requirement {
+                Requirement::Role(role) => principal.has_role(role),
+                Requirement::AnyRole(roles) => roles.iter().any(|r| principal.has_role(r)),
+                Requirement::Permission(p) => principal.has_permission(p),
+                Requirement::Named(name) => container
+                    .resolve_named::<Box<dyn Policy>>(name)
+                    .is_some_and(|policy| policy.allows(container, principal, req)),
+                Requirement::Inline(policy) => policy.allows(container, principal, req),
+            })
+    }
+}
+
+impl Middleware<Container, Request, Response> for Guard {
+    fn handle(
+        &mut self,
+        context: &Container,
+        req: &mut Request,
+        next: &mut dyn Controller<Container, Request, Response>,
+    ) -> Response {
+        match Principal::from_request(req) {
+            Some(principal) if self.allows(context, &principal, req) => next.handle(context, req),
+            _ => ResponseFactory::version(req.protocol_version()).forbidden(Headers::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Parsable;
+    use crate::http::routing::router::{Route, RouteGroup, Router};
+    use crate::http::security::authentication::{Authenticator, Credentials, TokenVerifier};
+    use crate::http::{Method, RequestFactory, ResponseTrait, Status, Uri, Version};
+
+    fn container() -> Container {
+        let mut c = Container::new();
+        c.register::<Box<dyn TokenVerifier>>(Box::new(|token: &str| match token {
+            "admin" => Some(Principal::new("root").with_role("admin")),
+            "editor" => Some(
+                Principal::new("ed")
+                    .with_role("editor")
+                    .with_permission("posts.write"),
+            ),
+            _ => None,
+        }));
+        c.register_named::<Box<dyn Policy>>(
+            "author",
+            Box::new(|_: &Container, p: &Principal, req: &Request| {
+                req.param("author") == Some(p.identifier.as_str())
+            }),
+        );
+        c
+    }
+
+    fn router() -> Router<Container> {
+        let ok = |_: &Container, _: &mut Request| {
+            ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+        };
+        let mut group = RouteGroup::new("/admin")
+            .with_before(Box::new(Authenticator::bearer("")))
+            .with_before(Box::new(Guard::role("admin")));
+        group.add_route(Route::new("/", vec![], Headers::new(), Box::new(ok)));
+        let mut posts = RouteGroup::new("/posts").with_before(Box::new(Authenticator::bearer("")));
+        posts.add_route(
+            Route::new(
+                "/{author}",
+                vec![Method::Post],
+                Headers::new(),
+                Box::new(ok),
+            )
+            .with_before(Box::new(
+                Guard::permission("posts.write").with_policy("author"),
+            )),
+        );
+        posts.add_route(
+            Route::new(
+                "/{author}",
+                vec![Method::Delete],
+                Headers::new(),
+                Box::new(ok),
+            )
+            .with_before(Box::new(
+                Guard::new().with_any_role(&["admin", "moderator"]),
+            )),
+        );
+        let mut router = Router::new();
+        router.add_group(group);
+        router.add_group(posts);
+        router
+    }
+
+    fn call(router: &mut Router<Container>, method: Method, path: &str, token: &str) -> Status {
+        let mut headers = Headers::new();
+        headers.add("Authorization", &Credentials::bearer(token).to_string());
+        let (_, uri) = Uri::parse(path).unwrap();
+        let mut req = RequestFactory::version(Version::Http1_1).build(method, uri, headers, "");
+        router
+            .handle_request(&container(), &mut req)
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn test_role_guard() {
+        let mut router = router();
+        assert_eq!(
+            call(&mut router, Method::Get, "/admin", "admin"),
+            Status::NoContent
+        );
+        assert_eq!(
+            call(&mut router, Method::Get, "/admin", "editor"),
+            Status::Forbidden
+        );
+        assert_eq!(
+            call(&mut router, Method::Get, "/admin", "nobody"),
+            Status::Unauthorized
+        );
+    }
+
+    #[test]
+    fn test_permission_and_resource_policy() {
+        let mut router = router();
+        assert_eq!(
+            call(&mut router, Method::Post, "/posts/ed", "editor"),
+            Status::NoContent
+        );
+        assert_eq!(
+            call(&mut router, Method::Post, "/posts/root", "editor"),
+            Status::Forbidden
+        );
+        assert_eq!(
+            call(&mut router, Method::Post, "/posts/root", "admin"),
+            Status::Forbidden
+        );
+        assert_eq!(
+            call(&mut router, Method::Delete, "/posts/ed", "admin"),
+            Status::NoContent
+        );
+        assert_eq!(
+            call(&mut router, Method::Delete, "/posts/ed", "editor"),
+            Status::Forbidden
+        );
+    }
+
+    #[test]
+    fn test_missing_principal_or_policy() {
+        let container = Container::new();
+        let req = RequestFactory::version(Version::Http1_1).get(Uri::default(), Headers::new());
+        let principal = Principal::new("alice");
+        assert!(Guard::new().allows(&container, &principal, &req));
+        assert!(!Guard::policy("unknown").allows(&container, &principal, &req));
+        assert!(Guard::new()
+            .with(|_: &Container, p: &Principal, _: &Request| p.identifier == "alice")
+            .allows(&container, &principal, &req));
+
+        let mut guard = Guard::new();
+        let mut next = |_: &Container, _: &mut Request| {
+            ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+        };
+        let mut anonymous = req.clone();
+        let resp = guard.handle(&container, &mut anonymous, &mut next);
+        assert_eq!(resp.status(), Status::Forbidden);
+    }
+}
@@ -0,0 +1,495 @@
+//! Cross-Origin Resource Sharing.
+//!
+//! The [`Cors`] middleware adds `Access-Control-*` headers to responses of
+//! requests coming from allowed origins and answers preflight requests
+//! (`OPTIONS` with `Access-Control-Request-Method`) itself.
+//!
+//! Preflight requests only reach the middleware if a route matches them:
+//! register [`Cors::preflight_route`] with a catch-all pattern in the group
+//! protected by the middleware.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::http::routing::router::{Route, RouteGroup, Router};
+//! use hermes::http::security::cors::Cors;
+//! use hermes::http::{Headers, Method, MessageTrait, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, Uri, Version};
+//! use hermes::concepts::Parsable;
+//!
+//! let cors = Cors::new()
+//!     .allow_origin("https://app.example.com")
+//!     .allow_origin("https://*.preview.example.com")
+//!     .allow_headers(&["Content-Type"])
+//!     .max_age(600);
+//!
+//! let mut api = RouteGroup::new("/api").with_before(Box::new(cors.clone()));
+//! api.add_route(Route::new("/items", vec![Method::Post], Headers::new(), Box::new(
+//!     |_: &(), _: &mut Request| ResponseFactory::version(Version::Http1_1).no_content(Headers::new()),
+//! )));
+//! api.add_route(cors.preflight_route("/{*path}"));
+//! let mut router: Router<()> = Router::new();
+//! router.add_group(api);
+//!
+//! let mut headers = Headers::new();
+//! headers.add("Origin", "https://pr-42.preview.example.com");
+//! headers.add("Access-Control-Request-Method", "POST");
+//! headers.add("Access-Control-Request-Headers", "content-type");
+//! let (_, uri) = Uri::parse("/api/items").unwrap();
+//! let mut preflight = RequestFactory::version(Version::Http1_1).build(Method::Options, uri, headers, "");
+//! let resp = router.handle_request(&(), &mut preflight).unwrap();
+//! assert_eq!(resp.code(), 204);
+//! assert_eq!(
+//!     resp.get_header_line("Access-Control-Allow-Origin"),
+//!     Some("https://pr-42.preview.example.com".to_string())
+//! );
+//! assert_eq!(resp.get_header_line("Access-Control-Max-Age"), Some("600".to_string()));
+//! ```
+
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::routing::router::Route;
+use crate::http::{Headers, MessageTrait, Method, Request, Response, ResponseFactory};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+type OriginPredicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+enum OriginRule {
+    Any,
+    Exact(String),
+    Wildcard(String, String),
+    Predicate(OriginPredicate),
+}
+
+impl OriginRule {
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginRule::Any => true,
+            OriginRule::Exact(o) => o.eq_ignore_ascii_case(origin),
+            OriginRule::Wildcard(prefix, suffix) => {
+                let origin = origin.to_ascii_lowercase();
+                origin.len() > prefix.len() + suffix.len()
+                    && origin.starts_with(prefix.as_str())
+                    && origin.ends_with(suffix.as_str())
+            }
+            OriginRule::Predicate(f) => f(origin),
+        }
+    }
+}
+
+impl Debug for OriginRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OriginRule::Any => f.write_str("Any"),
+            OriginRule::Exact(o) => f.debug_tuple("Exact").field(o).finish(),
+            OriginRule::Wildcard(p, s) => f.debug_tuple("Wildcard").field(p).field(s).finish(),
+            OriginRule::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
+
+/// CORS policy and middleware.
+///
+/// By default no origin is allowed; the usual methods (`GET`, `HEAD`,
+/// `POST`, `PUT`, `PATCH`, `DELETE`) are accepted and the headers requested
+/// in preflights are reflected.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: Vec<OriginRule>,
+    methods: Vec<Method>,
+    headers: Option<Vec<String>>,
+    exposed: Vec<String>,
+    credentials: bool,
+    max_age: Option<u64>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cors {
+    /// Create a policy allowing no origin.
+    pub fn new() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: vec![
+                Method::Get,
+                Method::Head,
+                Method::Post,
+                Method::Put,
+                Method::Patch,
+                Method::Delete,
+            ],
+            headers: None,
+            exposed: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allow every origin.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.origins.push(OriginRule::Any);
+        self
+    }
+
+    /// Allow `origin`, e.g. `https://example.com`.
+    ///
+    /// A single `*` matches any non-empty part of the origin, e.g.
+    /// `https://*.example.com`.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        let rule = match origin.split_once('*') {
+            Some((prefix, suffix)) => OriginRule::Wildcard(prefix.to_string(), suffix.to_string()),
+            None => OriginRule::Exact(origin),
+        };
+        self.origins.push(rule);
+        self
+    }
+
+    /// Allow origins accepted by `predicate`.
+    pub fn allow_origin_fn(
+        mut self,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.origins
+            .push(OriginRule::Predicate(Arc::new(predicate)));
+        self
+    }
+
+    /// Set the allowed methods.
+    pub fn allow_methods(mut self, methods: &[Method]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    /// Only allow the given request headers instead of reflecting the
+    /// requested ones.
+    pub fn allow_headers(mut self, headers: &[&str]) -> Self {
+        self.headers = Some(headers.iter().map(|h| h.to_string()).collect());
+        self
+    }
+
+    /// Expose `headers` to scripts.
+    pub fn expose_headers(mut self, headers: &[&str]) -> Self {
+        self.exposed = headers.iter().map(|h| h.to_string()).collect();
+        self
+    }
+
+    /// Allow cookies and `Authorization` headers.
+    ///
+    /// The actual origin is then sent instead of `*`, as required by browsers.
+    pub fn allow_credentials(mut self) -> Self {
+        self.credentials = true;
+        self
+    }
+
+    /// Let browsers cache preflight results for `seconds`.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Returns `true` if requests from `origin` are allowed.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.origins.iter().any(|rule| rule.matches(origin))
+    }
+
+    /// Returns `true` if `req` is a CORS preflight request.
+    pub fn is_preflight(req: &Request) -> bool {
+        req.method == Method::Options
+            && req.get_header_line("Origin").is_some()
+            && req
+                .get_header_line("Access-Control-Request-Method")
+                .is_some()
+    }
+
+    fn allow_origin_value(&self, origin: &str) -> String {
+        let any = self.origins.iter().any(|r| matches!(r, OriginRule::Any));
+        if any && !self.credentials {
+            "*".to_string()
+        } else {
+            origin.to_string()
+        }
+    }
+
+    /// Add `Origin` to the `Vary` header unless every origin gets `*`: the
+    /// answer then depends on the origin, even when it is rejected.
+    fn add_vary(&self, headers: &mut Headers) {
+        let varies = self.allow_origin_value("") != "*";
+        let listed = headers
+            .get("Vary")
+            .is_some_and(|values| values.iter().any(|v| v.eq_ignore_ascii_case("Origin")));
+        if varies && !listed {
+            headers.add("Vary", "Origin");
+        }
+    }
+
+    fn add_common(&self, origin: &str, headers: &mut Headers) {
+        let value = self.allow_origin_value(origin);
+        headers.set("Access-Control-Allow-Origin", &[&value]);
+        if self.credentials {
+            headers.set("Access-Control-Allow-Credentials", &["true"]);
+        }
+    }
+
+    /// Build the response to the preflight request `req`.
+    ///
+    /// Preflights from disallowed origins or asking for disallowed methods or
+    /// headers are answered with `403 Forbidden`.
+    pub fn preflight(&self, req: &Request) -> Response {
+        let factory = ResponseFactory::version(req.protocol_version());
+        let origin = req.get_header_line("Origin").unwrap_or_default();
+        let method = req
+            .get_header_line("Access-Control-Request-Method")
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let requested: Vec<String> = req
+            .get_header_line("Access-Control-Request-Headers")
+            .unwrap_or_default()
+            .split(',')
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty())
+            .collect();
+
+        let method_allowed = self.methods.iter().any(|m| m.to_string() == method);
+        let headers_allowed = match &self.headers {
+            Some(allowed) => requested
+                .iter()
+                .all(|h| allowed.iter().any(|a| a.eq_ignore_ascii_case(h))),
+            None => true,
+        };
+        let mut headers = Headers::new();
+        self.add_vary(&mut headers);
+        if !self.allows_origin(&origin) || !method_allowed || !headers_allowed {
+            return factory.forbidden(headers);
+        }
+
+        self.add_common(&origin, &mut headers);
+        let methods: Vec<String> = self.methods.iter().map(|m| m.to_string()).collect();
+        headers.set("Access-Control-Allow-Methods", &[&methods.join(", ")]);
+        let allowed = match &self.headers {
+            Some(allowed) => allowed.join(", "),
+            None => requested.join(", "),
+        };
+        if !allowed.is_empty() {
+            headers.set("Access-Control-Allow-Headers", &[&allowed]);
+        }
+        if let Some(max_age) = self.max_age {
+            headers.set("Access-Control-Max-Age", &[&max_age.to_string()]);
+        }
+        factory.no_content(headers)
+    }
+
+    /// Add the CORS headers for a non-preflight request from `origin`, or
+    /// without origin when `None`.
+    ///
+    /// `Vary: Origin` is added unless every origin is answered with `*`,
+    /// so that caches do not share answers between origins.
+    pub fn decorate(&self, origin: Option<&str>, res: &mut Response) {
+        self.add_vary(res.headers_mut());
+        let Some(origin) = origin.filter(|origin| self.allows_origin(origin)) else {
+            return;
+        };
+        let mut headers = Headers::new();
+        self.add_common(origin, &mut headers);
+        if !self.exposed.is_empty() {
+            headers.set("Access-Control-Expose-Headers", &[&self.exposed.join(", ")]);
+        }
+        for (name, values) in headers.iter() {
+            res.headers_mut().set(name, &[&values.join(",")]);
+        }
+    }
+
+    /// Route answering the preflight requests matching `pattern`, usually a
+    /// catch-all pattern such as `/{*path}`.
+    pub fn preflight_route<Ctx: 'static>(&self, pattern: &str) -> Route<Ctx> {
+        let cors = self.clone();
+        Route::new(
+            pattern,
+            vec![Method::Options],
+            Headers::new(),
+            Box::new(move |_: &Ctx, req: &mut Request| cors.preflight(req)),
+        )
+    }
+}
+
+impl<Ctx> Middleware<Ctx, Request, Response> for Cors {
+    fn handle(
+        &mut self,
+        context: &Ctx,
+        req: &mut Request,
+        next: &mut dyn Controller<Ctx, Request, Response>,
+    ) -> Response {
+        if Self::is_preflight(req) {
+            return self.preflight(req);
+        }
+        let origin = req.get_header_line("Origin");
+        let mut res = next.handle(context, req);
+        self.decorate(origin.as_deref(), &mut res);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{RequestFactory, ResponseTrait, Status, Uri, Version};
+
+    fn request(method: Method, headers: &[(&str, &str)]) -> Request {
+        let mut h = Headers::new();
+        for (name, value) in headers {
+            h.add(name, value);
+        }
+        RequestFactory::version(Version::Http1_1).build(method, Uri::default(), h, "")
+    }
+
+    fn run(cors: &mut Cors, req: &mut Request) -> Response {
+        let mut next = |_: &(), _: &mut Request| {
+            let mut headers = Headers::new();
+            headers.add("Vary", "Accept");
+            ResponseFactory::version(Version::Http1_1).ok(headers, "ok".to_string())
+        };
+        cors.handle(&(), req, &mut next)
+    }
+
+    #[test]
+    fn test_origin_rules() {
+        let cors = Cors::new()
+            .allow_origin("https://example.com/")
+            .allow_origin("https://*.example.org")
+            .allow_origin_fn(|o| o.ends_with(".test"));
+        assert!(cors.allows_origin("https://EXAMPLE.com"));
+        assert!(!cors.allows_origin("http://example.com"));
+        assert!(cors.allows_origin("https://a.example.org"));
+        assert!(!cors.allows_origin("https://.example.org"));
+        assert!(!cors.allows_origin("https://example.org"));
+        assert!(cors.allows_origin("http://localhost.test"));
+        assert!(!Cors::new().allows_origin("https://example.com"));
+    }
+
+    #[test]
+    fn test_simple_request() {
+        let mut cors = Cors::new()
+            .allow_origin("https://example.com")
+            .expose_headers(&["X-Total"])
+            .allow_credentials();
+        let mut req = request(Method::Get, &[("origin", "https://example.com")]);
+        let res = run(&mut cors, &mut req);
+        assert_eq!(res.status(), Status::OK);
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Origin"),
+            Some("https://example.com".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Credentials"),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("Access-Control-Expose-Headers"),
+            Some("X-Total".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("Vary"),
+            Some("Accept,Origin".to_string())
+        );
+
+        let mut req = request(Method::Get, &[("Origin", "https://evil.com")]);
+        let res = run(&mut cors, &mut req);
+        assert_eq!(res.status(), Status::OK);
+        assert!(!res.has_header("Access-Control-Allow-Origin"));
+        assert_eq!(
+            res.get_header_line("Vary"),
+            Some("Accept,Origin".to_string())
+        );
+        let res = run(&mut cors, &mut request(Method::Get, &[]));
+        assert_eq!(
+            res.get_header_line("Vary"),
+            Some("Accept,Origin".to_string())
+        );
+    }
+
+    #[test]
+    fn test_any_origin() {
+        let mut cors = Cors::new().allow_any_origin();
+        let mut req = request(Method::Get, &[("Origin", "https://a.com")]);
+        let res = run(&mut cors, &mut req);
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Origin"),
+            Some("*".to_string())
+        );
+        assert_eq!(res.get_header_line("Vary"), Some("Accept".to_string()));
+
+        let mut cors = cors.allow_credentials();
+        let res = run(&mut cors, &mut req);
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Origin"),
+            Some("https://a.com".to_string())
+        );
+    }
+
+    #[test]
+    fn test_preflight() {
+        let mut cors = Cors::new()
+            .allow_origin("https://example.com")
+            .allow_methods(&[Method::Get, Method::Put])
+            .allow_headers(&["Content-Type", "X-Token"]);
+        let mut req = request(
+            Method::Options,
+            &[
+                ("Origin", "https://example.com"),
+                ("Access-Control-Request-Method", "PUT"),
+                ("Access-Control-Request-Headers", "x-token"),
+            ],
+        );
+        let res = run(&mut cors, &mut req);
+        assert_eq!(res.status(), Status::NoContent);
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Methods"),
+            Some("GET, PUT".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Headers"),
+            Some("Content-Type, X-Token".to_string())
+        );
+        assert!(!res.has_header("Access-Control-Max-Age"));
+
+        for (method, headers) in [("DELETE", "x-token"), ("PUT", "x-other")] {
+            let mut req = request(
+                Method::Options,
+                &[
+                    ("Origin", "https://example.com"),
+                    ("Access-Control-Request-Method", method),
+                    ("Access-Control-Request-Headers", headers),
+                ],
+            );
+            let res = run(&mut cors, &mut req);
+            assert_eq!(res.status(), Status::Forbidden);
+            assert_eq!(res.get_header_line("Vary"), Some("Origin".to_string()));
+        }
+
+        // Plain OPTIONS requests reach the controller.
+        let mut req = request(Method::Options, &[("Origin", "https://example.com")]);
+        assert_eq!(run(&mut cors, &mut req).status(), Status::OK);
+    }
+
+    #[test]
+    fn test_reflected_headers() {
+        let cors = Cors::new().allow_origin("https://example.com");
+        let req = request(
+            Method::Options,
+            &[
+                ("Origin", "https://example.com"),
+                ("Access-Control-Request-Method", "post"),
+                ("Access-Control-Request-Headers", "X-A, X-B"),
+            ],
+        );
+        let res = cors.preflight(&req);
+        assert_eq!(
+            res.get_header_line("Access-Control-Allow-Headers"),
+            Some("X-A, X-B".to_string())
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Cross-Site Request Forgery protection.
+//!
+//! [`Csrf`] implements the synchronizer token pattern: a random token is
+//! kept in the session, embedded in forms and sent back with unsafe requests
+//! in a form field or a header. [`Csrf::verify`] checks requests directly;
+//! [`Csrf::rule`] checks the field along with the other rules of a
+//! [`Validator`](crate::http::validation::Validator).
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::concepts::Parsable;
+//! use hermes::http::security::csrf::Csrf;
+//! use hermes::http::session::{FileStore, Session};
+//! use hermes::http::{Headers, RequestFactory, Uri, Version};
+//!
+//! let dir = std::env::temp_dir().join("hermes_csrf_doc");
+//! let mut session = Session::new("doc", FileStore::new(&dir));
+//! let csrf = Csrf::new();
+//! let token = csrf.token(&mut session);
+//! assert_eq!(
+//!     csrf.hidden_input(&mut session),
+//!     format!("<input type=\"hidden\" name=\"_csrf\" value=\"{}\">", token)
+//! );
+//!
+//! let factory = RequestFactory::version(Version::Http1_1);
+//! let uri = Uri::parse("/profile").unwrap().1;
+//! let mut headers = Headers::new();
+//! headers.set("Content-Type", &["application/x-www-form-urlencoded"]);
+//! let body = format!("name=Bob&_csrf={}", token);
+//! let req = factory.post(uri.clone(), headers.clone(), &body);
+//! assert!(csrf.verify(&session, &req));
+//!
+//! let forged = factory.post(uri, headers, "name=Eve&_csrf=guess");
+//! assert!(!csrf.verify(&session, &forged));
+//! ```
+
+use super::{random_token, same_token};
+use crate::concepts::value::Value;
+use crate::http::extract::media_type;
+use crate::http::session::{Session, SessionStore};
+use crate::http::validation::Rule;
+use crate::http::{form_urldecode, MessageTrait, Request};
+
+/// Synchronizer token CSRF protection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Csrf {
+    /// Session key of the token.
+    pub session_key: String,
+    /// Form field carrying the token.
+    pub field: String,
+    /// Header carrying the token, for scripts.
+    pub header: String,
+}
+
+impl Default for Csrf {
+    fn default() -> Self {
+        Self {
+            session_key: "csrf.token".to_string(),
+            field: "_csrf".to_string(),
+            header: "X-CSRF-Token".to_string(),
+        }
+    }
+}
+
+impl Csrf {
+    /// Create a protection using the `_csrf` field and `X-CSRF-Token` header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `field` as form field.
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = field.to_string();
+        self
+    }
+
+    /// Use `header` as header.
+    pub fn with_header(mut self, header: &str) -> Self {
+        self.header = header.to_string();
+        self
+    }
+
+    /// Token of `session`, generated on first use.
+    pub fn token<S: SessionStore + Clone>(&self, session: &mut Session<S>) -> String {
+        match session.get(&self.session_key) {
+            Some(Value::String(token)) => token.clone(),
+            _ => self.rotate(session),
+        }
+    }
+
+    /// Replace the token of `session`, e.g. after a login.
+    pub fn rotate<S: SessionStore + Clone>(&self, session: &mut Session<S>) -> String {
+        let token = random_token(32);
+        session.insert(self.session_key.clone(), Value::String(token.clone()));
+        token
+    }
+
+    /// Hidden form input carrying the token.
+    pub fn hidden_input<S: SessionStore + Clone>(&self, session: &mut Session<S>) -> String {
+        format!(
+            "<input type=\"hidden\" name=\"{}\" value=\"{}\">",
+            self.field,
+            self.token(session)
+        )
+    }
+
+    /// Check the token sent with `request`.
+    ///
+    /// Safe methods are always accepted. Other requests must send the token
+    /// of the session in the header or in the field of an
+    /// `application/x-www-form-urlencoded` body.
+    pub fn verify<S: SessionStore + Clone>(&self, session: &Session<S>, request: &Request) -> bool {
+        if request.method.is_safe() {
+            return true;
+        }
+        let sent = request.get_header_line(&self.header).or_else(|| {
+            let is_form =
+                media_type(request).as_deref() == Some("application/x-www-form-urlencoded");
+            is_form
+                .then(|| form_urldecode(&request.body()))
+                .and_then(|pairs| pairs.into_iter().find(|(key, _)| *key == self.field))
+                .map(|(_, value)| value)
+        });
+        match (sent, session.get(&self.session_key)) {
+            (Some(sent), Some(Value::String(expected))) => same_token(&sent, expected),
+            _ => false,
+        }
+    }
+
+    /// Validation rule requiring the token of `session` in the field.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::security::csrf::Csrf;
+    /// use hermes::http::session::{FileStore, Session};
+    /// use hermes::http::validation::{Rule, Validator};
+    ///
+    /// let dir = std::env::temp_dir().join("hermes_csrf_rule_doc");
+    /// let mut session = Session::new("doc", FileStore::new(&dir));
+    /// let csrf = Csrf::new();
+    /// let validator = Validator::new()
+    ///     .field("title", [Rule::required()])
+    ///     .field(&csrf.field, [csrf.rule(&mut session)]);
+    ///
+    /// let form = Value::Dictionary(
+    ///     [("title".to_string(), Value::String("Hello".into()))].into(),
+    /// );
+    /// let errors = validator.validate(&form).unwrap_err();
+    /// assert_eq!(errors.get("_csrf"), ["is invalid, reload the form"]);
+    /// ```
+    pub fn rule<S: SessionStore + Clone>(&self, session: &mut Session<S>) -> Rule {
+        let expected = self.token(session);
+        Rule::required()
+            .and(Rule::custom(move |value| match value {
+                Value::String(sent) if same_token(sent, &expected) => Ok(()),
+                _ => Err("does not match".to_string()),
+            }))
+            .message("is invalid, reload the form")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Parsable;
+    use crate::http::session::FileStore;
+    use crate::http::{Headers, Method, RequestFactory, Uri, Version};
+
+    #[test]
+    fn test_verify() {
+        let dir = std::env::temp_dir().join("hermes_csrf_verify");
+        let mut session = Session::new("s", FileStore::new(&dir));
+        let csrf = Csrf::new().with_header("X-XSRF-Token");
+        let factory = RequestFactory::version(Version::Http1_1);
+        let uri = Uri::parse("/x").unwrap().1;
+
+        // No token in the session yet.
+        let req = factory.post(uri.clone(), Headers::new(), "_csrf=");
+        assert!(!csrf.verify(&session, &req));
+
+        let token = csrf.token(&mut session);
+        assert_eq!(csrf.token(&mut session), token);
+        assert!(csrf.verify(&session, &factory.get(uri.clone(), Headers::new())));
+
+        let mut headers = Headers::new();
+        headers.set("X-XSRF-Token", &[&token]);
+        let req = factory.build(Method::Delete, uri.clone(), headers, "");
+        assert!(csrf.verify(&session, &req));
+
+        // The field is only read from form bodies.
+        let body = format!("_csrf={}", token);
+        assert!(!csrf.verify(&session, &factory.post(uri.clone(), Headers::new(), &body)));
+
+        let rotated = csrf.rotate(&mut session);
+        assert_ne!(rotated, token);
+        let mut headers = Headers::new();
+        headers.set("X-XSRF-Token", &[&token]);
+        assert!(!csrf.verify(&session, &factory.post(uri, headers, "")));
+        assert!(same_token("abc", "abc"));
+        assert!(!same_token("abc", "abd"));
+        assert!(!same_token("abc", "ab"));
+    }
+}
@@ -0,0 +1,589 @@
+//! Security related response headers.
+//!
+//! The [`SecurityHeaders`] middleware adds `Content-Security-Policy`,
+//! `Strict-Transport-Security`, `X-Content-Type-Options`, `X-Frame-Options`,
+//! `Referrer-Policy` and `Permissions-Policy` to responses. Headers already
+//! set by the controller are left untouched.
+//!
+//! When the [`ContentSecurityPolicy`] uses nonces, a fresh nonce is generated
+//! for each request and exposed to controllers with [`SecurityHeaders::nonce`]
+//! so inline `<script>` and `<style>` elements can carry it.
+//!
+//! Routes can override the configuration by registering another
+//! [`SecurityHeaders`] on a nested [`RouteGroup`](crate::http::routing::router::RouteGroup)
+//! or on the route itself: the innermost middleware decides, including
+//! headers it disables. A [`SecurityHeaders::with_frame_options`] decision
+//! also sets the `frame-ancestors` directive of the policy sent by an outer
+//! middleware.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::http::routing::router::{Route, RouteGroup, Router};
+//! use hermes::http::security::headers::{ContentSecurityPolicy, SecurityHeaders};
+//! use hermes::http::{Headers, Method, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, MessageTrait, Uri, Version};
+//! use hermes::concepts::Parsable;
+//!
+//! fn page(_: &(), req: &mut Request) -> Response {
+//!     let nonce = SecurityHeaders::nonce(req).unwrap_or_default().to_string();
+//!     let body = format!("<script nonce=\"{}\">start()</script>", nonce);
+//!     ResponseFactory::version(Version::Http1_1).ok(Headers::new(), body)
+//! }
+//!
+//! let mut site = RouteGroup::new("/").with_before(Box::new(
+//!     SecurityHeaders::new().with_csp(ContentSecurityPolicy::default().with_nonce("script-src")),
+//! ));
+//! site.add_route(Route::new("/", vec![Method::Get], Headers::new(), Box::new(page)));
+//! let mut embeds = RouteGroup::new("/embed")
+//!     .with_before(Box::new(SecurityHeaders::empty().allow_framing(&["https://partner.example"])));
+//! embeds.add_route(Route::new("/", vec![Method::Get], Headers::new(), Box::new(page)));
+//! site.add_group(embeds);
+//!
+//! let mut router: Router<()> = Router::new();
+//! router.add_group(site);
+//!
+//! let factory = RequestFactory::version(Version::Http1_1);
+//! let mut req = factory.get(Uri::parse("/").unwrap().1, Headers::new());
+//! let resp = router.handle_request(&(), &mut req).unwrap();
+//! let csp = resp.get_header_line("Content-Security-Policy").unwrap();
+//! assert!(csp.contains(&format!("'nonce-{}'", SecurityHeaders::nonce(&req).unwrap())));
+//! assert_eq!(resp.get_header_line("X-Frame-Options"), Some("DENY".to_string()));
+//!
+//! let mut req = factory.get(Uri::parse("/embed").unwrap().1, Headers::new());
+//! let resp = router.handle_request(&(), &mut req).unwrap();
+//! assert!(!resp.has_header("X-Frame-Options"));
+//! assert!(resp
+//!     .get_header_line("Content-Security-Policy")
+//!     .unwrap()
+//!     .contains("frame-ancestors https://partner.example"));
+//! ```
+
+use crate::concepts::value::Value;
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::security::random_token;
+use crate::http::{MessageTrait, Request, Response};
+
+/// `Content-Security-Policy` builder.
+///
+/// The default policy only allows resources from the same origin and
+/// forbids plugins and framing:
+/// `default-src 'self'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'`.
+///
+/// ```
+/// use hermes::http::security::headers::ContentSecurityPolicy;
+///
+/// let csp = ContentSecurityPolicy::new()
+///     .directive("default-src", &["'self'"])
+///     .add_source("img-src", "https:")
+///     .with_nonce("script-src");
+/// assert_eq!(
+///     csp.render(Some("abc")),
+///     "default-src 'self'; img-src https:; script-src 'nonce-abc'"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<String>)>,
+    nonces: Vec<String>,
+    report_only: bool,
+}
+
+impl Default for ContentSecurityPolicy {
+    fn default() -> Self {
+        Self::new()
+            .directive("default-src", &["'self'"])
+            .directive("object-src", &["'none'"])
+            .directive("base-uri", &["'self'"])
+            .directive("frame-ancestors", &["'none'"])
+    }
+}
+
+impl ContentSecurityPolicy {
+    /// Create an empty policy.
+    pub fn new() -> Self {
+        Self {
+            directives: Vec::new(),
+            nonces: Vec::new(),
+            report_only: false,
+        }
+    }
+
+    fn entry(&mut self, name: &str) -> &mut Vec<String> {
+        let index = match self.directives.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.directives.push((name.to_string(), Vec::new()));
+                self.directives.len() - 1
+            }
+        };
+        &mut self.directives[index].1
+    }
+
+    /// Set the sources of directive `name`, replacing previous ones.
+    pub fn directive(mut self, name: &str, sources: &[&str]) -> Self {
+        *self.entry(name) = sources.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Append `source` to directive `name`.
+    pub fn add_source(mut self, name: &str, source: &str) -> Self {
+        let sources = self.entry(name);
+        if !sources.iter().any(|s| s == source) {
+            sources.push(source.to_string());
+        }
+        self
+    }
+
+    /// Remove directive `name`.
+    pub fn without(mut self, name: &str) -> Self {
+        self.directives.retain(|(n, _)| n != name);
+        self.nonces.retain(|n| n != name);
+        self
+    }
+
+    /// Allow elements carrying the per-request nonce in directive `name`
+    /// (usually `script-src` or `style-src`).
+    pub fn with_nonce(mut self, name: &str) -> Self {
+        self.entry(name);
+        if !self.nonces.iter().any(|n| n == name) {
+            self.nonces.push(name.to_string());
+        }
+        self
+    }
+
+    /// Returns `true` if the policy needs a nonce.
+    pub fn uses_nonce(&self) -> bool {
+        !self.nonces.is_empty()
+    }
+
+    /// Only report violations (`Content-Security-Policy-Report-Only`).
+    pub fn report_only(mut self) -> Self {
+        self.report_only = true;
+        self
+    }
+
+    /// Name of the header carrying this policy.
+    pub fn header_name(&self) -> &'static str {
+        if self.report_only {
+            "Content-Security-Policy-Report-Only"
+        } else {
+            "Content-Security-Policy"
+        }
+    }
+
+    /// Render the header value, inserting `nonce` where requested.
+    pub fn render(&self, nonce: Option<&str>) -> String {
+        self.directives
+            .iter()
+            .map(|(name, sources)| {
+                let mut parts = vec![name.clone()];
+                parts.extend(sources.iter().cloned());
+                if let Some(nonce) = nonce.filter(|_| self.nonces.contains(name)) {
+                    parts.push(format!("'nonce-{}'", nonce));
+                }
+                parts.join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Values of the `X-Frame-Options` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOptions {
+    Deny,
+    SameOrigin,
+}
+
+impl FrameOptions {
+    fn value(&self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "DENY",
+            FrameOptions::SameOrigin => "SAMEORIGIN",
+        }
+    }
+
+    fn ancestors(&self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "'none'",
+            FrameOptions::SameOrigin => "'self'",
+        }
+    }
+}
+
+/// A header decision: `None` means the header must not be added.
+type Setting = (String, Option<String>);
+
+/// Middleware adding security headers to responses.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    csp: Option<Option<ContentSecurityPolicy>>,
+    /// `frame-ancestors` sources, applied to the policy sent.
+    frame_ancestors: Option<Vec<String>>,
+    headers: Vec<Setting>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecurityHeaders {
+    /// Request attribute holding the CSP nonce of the current request.
+    pub const NONCE_ATTRIBUTE: &'static str = "csp_nonce";
+    /// Request attribute listing the headers already decided by inner middleware.
+    const HANDLED_ATTRIBUTE: &'static str = "security_headers";
+    /// Request attribute holding the `frame-ancestors` decided by inner middleware.
+    const FRAME_ANCESTORS_ATTRIBUTE: &'static str = "security_frame_ancestors";
+
+    /// Create the middleware with safe defaults:
+    ///
+    /// - the default [`ContentSecurityPolicy`],
+    /// - `Strict-Transport-Security: max-age=31536000; includeSubDomains`,
+    /// - `X-Content-Type-Options: nosniff`,
+    /// - `X-Frame-Options: DENY`,
+    /// - `Referrer-Policy: strict-origin-when-cross-origin`.
+    pub fn new() -> Self {
+        Self::empty()
+            .with_csp(ContentSecurityPolicy::default())
+            .with_hsts(31_536_000, true, false)
+            .with_header("X-Content-Type-Options", "nosniff")
+            .with_header("X-Frame-Options", FrameOptions::Deny.value())
+            .with_referrer_policy("strict-origin-when-cross-origin")
+    }
+
+    /// Create the middleware without any header, leaving decisions to outer
+    /// middleware.
+    pub fn empty() -> Self {
+        Self {
+            csp: None,
+            frame_ancestors: None,
+            headers: Vec::new(),
+        }
+    }
+
+    fn setting(mut self, name: &str, value: Option<String>) -> Self {
+        self.headers.retain(|(n, _)| n != name);
+        self.headers.push((name.to_string(), value));
+        self
+    }
+
+    /// Set header `name` to `value`.
+    pub fn with_header(self, name: &str, value: &str) -> Self {
+        self.setting(name, Some(value.to_string()))
+    }
+
+    /// Never add header `name`, even if outer middleware would.
+    pub fn without(self, name: &str) -> Self {
+        self.setting(name, None)
+    }
+
+    /// Use `csp` as `Content-Security-Policy`.
+    pub fn with_csp(mut self, csp: ContentSecurityPolicy) -> Self {
+        self.csp = Some(Some(csp));
+        self
+    }
+
+    /// Never add a `Content-Security-Policy`.
+    pub fn without_csp(mut self) -> Self {
+        self.csp = Some(None);
+        self
+    }
+
+    /// Set `Strict-Transport-Security`. Browsers ignore it on plain HTTP.
+    pub fn with_hsts(self, max_age: u64, include_subdomains: bool, preload: bool) -> Self {
+        let mut value = format!("max-age={}", max_age);
+        if include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if preload {
+            value.push_str("; preload");
+        }
+        self.with_header("Strict-Transport-Security", &value)
+    }
+
+    /// Set `X-Frame-Options` and the matching CSP `frame-ancestors` directive.
+    ///
+    /// The directive is applied to the policy of this middleware or, when it
+    /// has none, to the one sent by an outer middleware.
+    pub fn with_frame_options(mut self, options: FrameOptions) -> Self {
+        self.frame_ancestors = Some(vec![options.ancestors().to_string()]);
+        self.with_header("X-Frame-Options", options.value())
+    }
+
+    /// Allow framing by `origins`: disables `X-Frame-Options`, which cannot
+    /// express a list of origins, and sets the CSP `frame-ancestors`.
+    ///
+    /// When no policy is configured, the default one is used.
+    pub fn allow_framing(mut self, origins: &[&str]) -> Self {
+        let csp = match self.csp.take() {
+            Some(Some(csp)) => csp,
+            _ => ContentSecurityPolicy::default(),
+        };
+        self.csp = Some(Some(csp.directive("frame-ancestors", origins)));
+        self.without("X-Frame-Options")
+    }
+
+    /// Set `Referrer-Policy`.
+    pub fn with_referrer_policy(self, policy: &str) -> Self {
+        self.with_header("Referrer-Policy", policy)
+    }
+
+    /// Set `Permissions-Policy`, e.g. `camera=(), geolocation=(self)`.
+    pub fn with_permissions_policy(self, policy: &str) -> Self {
+        self.with_header("Permissions-Policy", policy)
+    }
+
+    /// CSP nonce generated for `req`, if any.
+    pub fn nonce(req: &Request) -> Option<&str> {
+        match req.attribute(Self::NONCE_ATTRIBUTE) {
+            Some(Value::String(nonce)) => Some(nonce),
+            _ => None,
+        }
+    }
+
+    fn strings(req: &Request, name: &str) -> Option<Vec<String>> {
+        match req.attribute(name) {
+            Some(Value::Array(items)) => Some(
+                items
+                    .iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, req: &mut Request, res: &mut Response) {
+        let mut handled = Self::strings(req, Self::HANDLED_ATTRIBUTE).unwrap_or_default();
+        let ancestors = Self::strings(req, Self::FRAME_ANCESTORS_ATTRIBUTE);
+        if ancestors.is_none() {
+            if let Some(ancestors) = &self.frame_ancestors {
+                req.set_attribute(Self::FRAME_ANCESTORS_ATTRIBUTE, strings_value(ancestors));
+            }
+        }
+        let ancestors = ancestors.or_else(|| self.frame_ancestors.clone());
+        let mut decisions = self.headers.clone();
+        if let Some(csp) = &self.csp {
+            let value = csp.as_ref().map(|csp| match &ancestors {
+                Some(sources) => {
+                    let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+                    csp.clone()
+                        .directive("frame-ancestors", &sources)
+                        .render(Self::nonce(req))
+                }
+                None => csp.render(Self::nonce(req)),
+            });
+            decisions.push(("Content-Security-Policy".to_string(), value));
+        }
+        for (name, value) in decisions {
+            // A report-only policy leaves the enforced one to the outer
+            // middleware, and conversely.
+            let header = match (&name[..], &self.csp) {
+                ("Content-Security-Policy", Some(Some(csp))) => csp.header_name(),
+                _ => &name,
+            };
+            if handled.iter().any(|h| h == header) {
+                continue;
+            }
+            if let Some(value) = value {
+                if !res.has_header(header) {
+                    res.headers_mut().set(header, &[&value]);
+                }
+            }
+            handled.push(header.to_string());
+        }
+        req.set_attribute(Self::HANDLED_ATTRIBUTE, strings_value(&handled));
+    }
+}
+
+fn strings_value(strings: &[String]) -> Value {
+    Value::Array(strings.iter().cloned().map(Value::String).collect())
+}
+
+impl<Ctx> Middleware<Ctx, Request, Response> for SecurityHeaders {
+    fn handle(
+        &mut self,
+        context: &Ctx,
+        req: &mut Request,
+        next: &mut dyn Controller<Ctx, Request, Response>,
+    ) -> Response {
+        if self
+            .csp
+            .as_ref()
+            .is_some_and(|csp| csp.as_ref().is_some_and(|csp| csp.uses_nonce()))
+            && Self::nonce(req).is_none()
+        {
+            req.set_attribute(Self::NONCE_ATTRIBUTE, Value::String(random_token(16)));
+        }
+        let mut res = next.handle(context, req);
+        self.apply(req, &mut res);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Headers, RequestFactory, ResponseFactory, Uri, Version};
+
+    fn run(mw: &mut SecurityHeaders, req: &mut Request, headers: Headers) -> Response {
+        let mut next = move |_: &(), _: &mut Request| {
+            ResponseFactory::version(Version::Http1_1).no_content(headers.clone())
+        };
+        mw.handle(&(), req, &mut next)
+    }
+
+    fn request() -> Request {
+        RequestFactory::version(Version::Http1_1).get(Uri::default(), Headers::new())
+    }
+
+    #[test]
+    fn test_defaults() {
+        let mut req = request();
+        let res = run(&mut SecurityHeaders::new(), &mut req, Headers::new());
+        assert_eq!(
+            res.get_header_line("Content-Security-Policy"),
+            Some(
+                "default-src 'self'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            res.get_header_line("Strict-Transport-Security"),
+            Some("max-age=31536000; includeSubDomains".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("X-Content-Type-Options"),
+            Some("nosniff".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("X-Frame-Options"),
+            Some("DENY".to_string())
+        );
+        assert!(res.has_header("Referrer-Policy"));
+        assert!(!res.has_header("Permissions-Policy"));
+        assert!(SecurityHeaders::nonce(&req).is_none());
+    }
+
+    #[test]
+    fn test_controller_headers_are_kept() {
+        let mut headers = Headers::new();
+        headers.add("X-Frame-Options", "SAMEORIGIN");
+        let res = run(&mut SecurityHeaders::new(), &mut request(), headers);
+        assert_eq!(
+            res.get_header_line("X-Frame-Options"),
+            Some("SAMEORIGIN".to_string())
+        );
+    }
+
+    #[test]
+    fn test_nonce_per_request() {
+        let mut mw = SecurityHeaders::empty().with_csp(
+            ContentSecurityPolicy::new()
+                .with_nonce("script-src")
+                .report_only(),
+        );
+        let (mut first, mut second) = (request(), request());
+        let res = run(&mut mw, &mut first, Headers::new());
+        run(&mut mw, &mut second, Headers::new());
+        let nonce = SecurityHeaders::nonce(&first).unwrap().to_string();
+        assert_ne!(Some(nonce.as_str()), SecurityHeaders::nonce(&second));
+        assert_eq!(
+            res.get_header_line("Content-Security-Policy-Report-Only"),
+            Some(format!("script-src 'nonce-{}'", nonce))
+        );
+        assert!(!res.has_header("Content-Security-Policy"));
+    }
+
+    #[test]
+    fn test_inner_overrides_outer() {
+        let mut outer = SecurityHeaders::new();
+        let mut inner = SecurityHeaders::empty()
+            .with_frame_options(FrameOptions::SameOrigin)
+            .with_permissions_policy("camera=()")
+            .without("Strict-Transport-Security");
+        let mut req = request();
+        let mut next = |ctx: &(), req: &mut Request| {
+            let mut ctrl = |_: &(), _: &mut Request| {
+                ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+            };
+            inner.handle(ctx, req, &mut ctrl)
+        };
+        let res = outer.handle(&(), &mut req, &mut next);
+        assert_eq!(
+            res.get_header_line("X-Frame-Options"),
+            Some("SAMEORIGIN".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("Permissions-Policy"),
+            Some("camera=()".to_string())
+        );
+        assert!(!res.has_header("Strict-Transport-Security"));
+        assert_eq!(
+            res.get_header_line("X-Content-Type-Options"),
+            Some("nosniff".to_string())
+        );
+        // The outer policy follows the inner frame options.
+        assert_eq!(
+            res.get_header_line("Content-Security-Policy"),
+            Some(
+                "default-src 'self'; object-src 'none'; base-uri 'self'; frame-ancestors 'self'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_inner_report_only_keeps_outer_policy() {
+        let mut outer = SecurityHeaders::new();
+        let mut inner = SecurityHeaders::empty().with_csp(
+            ContentSecurityPolicy::new()
+                .directive("img-src", &["*"])
+                .report_only(),
+        );
+        let mut req = request();
+        let mut next = |ctx: &(), req: &mut Request| {
+            let mut ctrl = |_: &(), _: &mut Request| {
+                ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+            };
+            inner.handle(ctx, req, &mut ctrl)
+        };
+        let res = outer.handle(&(), &mut req, &mut next);
+        assert_eq!(
+            res.get_header_line("Content-Security-Policy-Report-Only"),
+            Some("img-src *".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("Content-Security-Policy"),
+            Some(
+                "default-src 'self'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_frame_options_keep_csp_decision() {
+        let mut mw = SecurityHeaders::new()
+            .without_csp()
+            .with_frame_options(FrameOptions::SameOrigin);
+        let res = run(&mut mw, &mut request(), Headers::new());
+        assert!(!res.has_header("Content-Security-Policy"));
+        assert_eq!(
+            res.get_header_line("X-Frame-Options"),
+            Some("SAMEORIGIN".to_string())
+        );
+
+        let mut mw = SecurityHeaders::new().with_frame_options(FrameOptions::SameOrigin);
+        let res = run(&mut mw, &mut request(), Headers::new());
+        assert!(res
+            .get_header_line("Content-Security-Policy")
+            .unwrap()
+            .ends_with("frame-ancestors 'self'"));
+    }
+}
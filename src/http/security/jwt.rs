@@ -0,0 +1,746 @@
+//! JSON Web Tokens (RFC 7519).
+//!
+//! Tokens are signed with `HS256`, `RS256` or `ES256` using a [`JwtKey`] and
+//! verified against a [`KeySet`], where the `kid` header selects the key.
+//! Claims are plain [`Value`] dictionaries, the same structure used by
+//! sessions, and are checked by a [`Validation`] (`exp`, `nbf`, `iss`, `aud`
+//! with some clock skew).
+//!
+//! [`JwtVerifier`] implements [`TokenVerifier`] so it can be registered in the
+//! [`Container`](crate::container::Container) for the `Bearer`
+//! [`Authenticator`](crate::http::security::authentication::Authenticator).
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::concepts::value::Value;
+//! use hermes::concepts::Dictionary;
+//! use hermes::http::security::authentication::TokenVerifier;
+//! use hermes::http::security::jwt::{encode, JwtKey, JwtVerifier, KeySet, Validation};
+//!
+//! let key = JwtKey::hmac("secret").with_kid("2024");
+//! let mut claims = Dictionary::new();
+//! claims.insert("sub".to_string(), Value::String("alice".into()));
+//! claims.insert("exp".to_string(), Value::Int(4_102_444_800));
+//! let token = encode(&claims, &key).unwrap();
+//!
+//! let verifier = JwtVerifier::new(KeySet::new().with_key(key), Validation::new());
+//! assert_eq!(verifier.decode(&token).unwrap(), claims);
+//! assert_eq!(verifier.verify(&token).unwrap().identifier, "alice");
+//! ```
+
+use crate::concepts::value::json::JsonFormatter;
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use crate::http::security::authentication::{Principal, TokenVerifier};
+use crate::http::security::now;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use p256::ecdsa::{
+    Signature as EcSignature, SigningKey as EcSigningKey, VerifyingKey as EcVerifyingKey,
+};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs1v15::{
+    Signature as RsaSignature, SigningKey as RsaSigningKey, VerifyingKey as RsaVerifyingKey,
+};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use sha2::Sha256;
+use std::fmt::{self, Display, Formatter};
+
+/// Errors raised while issuing or verifying a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwtError {
+    /// The token is not made of three base64url encoded JSON parts.
+    Malformed(String),
+    /// The `alg` header names an unsupported algorithm.
+    UnsupportedAlgorithm(String),
+    /// No key matches the `kid` and algorithm of the token.
+    UnknownKey(Option<String>),
+    /// Key material could not be loaded or cannot sign.
+    InvalidKey(String),
+    /// The signature does not match the token content.
+    InvalidSignature,
+    /// The `exp` claim is in the past.
+    Expired,
+    /// The `nbf` claim is in the future.
+    NotYetValid,
+    /// The `iss` claim does not match the expected issuer.
+    InvalidIssuer,
+    /// The `aud` claim does not contain the expected audience.
+    InvalidAudience,
+    /// A required claim is missing or has the wrong type.
+    MissingClaim(String),
+}
+
+impl Display for JwtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtError::Malformed(reason) => write!(f, "Malformed token: {}", reason),
+            JwtError::UnsupportedAlgorithm(alg) => write!(f, "Unsupported algorithm: {}", alg),
+            JwtError::UnknownKey(Some(kid)) => write!(f, "Unknown key: {}", kid),
+            JwtError::UnknownKey(None) => write!(f, "No key available for this token"),
+            JwtError::InvalidKey(reason) => write!(f, "Invalid key: {}", reason),
+            JwtError::InvalidSignature => write!(f, "Invalid signature"),
+            JwtError::Expired => write!(f, "Token expired"),
+            JwtError::NotYetValid => write!(f, "Token not yet valid"),
+            JwtError::InvalidIssuer => write!(f, "Invalid issuer"),
+            JwtError::InvalidAudience => write!(f, "Invalid audience"),
+            JwtError::MissingClaim(claim) => write!(f, "Missing claim: {}", claim),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+/// Signature algorithms supported for tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// HMAC using SHA-256.
+    HS256,
+    /// RSASSA-PKCS1-v1_5 using SHA-256.
+    RS256,
+    /// ECDSA using P-256 and SHA-256.
+    ES256,
+}
+
+impl Algorithm {
+    /// Parse the value of an `alg` header.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "HS256" => Some(Self::HS256),
+            "RS256" => Some(Self::RS256),
+            "ES256" => Some(Self::ES256),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Algorithm::HS256 => "HS256",
+                Algorithm::RS256 => "RS256",
+                Algorithm::ES256 => "ES256",
+            }
+        )
+    }
+}
+
+#[derive(Clone)]
+enum KeyMaterial {
+    Hmac(Vec<u8>),
+    RsaPrivate(Box<RsaPrivateKey>),
+    RsaPublic(Box<RsaPublicKey>),
+    EcPrivate(EcSigningKey),
+    EcPublic(EcVerifyingKey),
+}
+
+/// Key used to sign or verify tokens, optionally identified by a `kid`.
+///
+/// Private keys can both sign and verify, public keys only verify.
+#[derive(Clone)]
+pub struct JwtKey {
+    /// Identifier written to and matched against the `kid` header.
+    pub kid: Option<String>,
+    material: KeyMaterial,
+}
+
+impl fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm())
+            .finish()
+    }
+}
+
+impl JwtKey {
+    fn new(material: KeyMaterial) -> Self {
+        Self {
+            kid: None,
+            material,
+        }
+    }
+
+    /// Shared secret for `HS256`.
+    pub fn hmac(secret: impl AsRef<[u8]>) -> Self {
+        Self::new(KeyMaterial::Hmac(secret.as_ref().to_vec()))
+    }
+
+    /// RSA private key for `RS256`, PEM encoded (PKCS#8 or PKCS#1).
+    pub fn rsa_private_pem(pem: &str) -> Result<Self, JwtError> {
+        let key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+        Ok(Self::new(KeyMaterial::RsaPrivate(Box::new(key))))
+    }
+
+    /// RSA public key for `RS256`, PEM encoded (SPKI or PKCS#1).
+    pub fn rsa_public_pem(pem: &str) -> Result<Self, JwtError> {
+        let key = RsaPublicKey::from_public_key_pem(pem)
+            .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+            .map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+        Ok(Self::new(KeyMaterial::RsaPublic(Box::new(key))))
+    }
+
+    /// P-256 private key for `ES256`, PEM encoded (PKCS#8).
+    pub fn ec_private_pem(pem: &str) -> Result<Self, JwtError> {
+        let key =
+            EcSigningKey::from_pkcs8_pem(pem).map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+        Ok(Self::new(KeyMaterial::EcPrivate(key)))
+    }
+
+    /// P-256 public key for `ES256`, PEM encoded (SPKI).
+    pub fn ec_public_pem(pem: &str) -> Result<Self, JwtError> {
+        let key = EcVerifyingKey::from_public_key_pem(pem)
+            .map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+        Ok(Self::new(KeyMaterial::EcPublic(key)))
+    }
+
+    /// Set the key identifier.
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    /// Algorithm this key is used with.
+    pub fn algorithm(&self) -> Algorithm {
+        match self.material {
+            KeyMaterial::Hmac(_) => Algorithm::HS256,
+            KeyMaterial::RsaPrivate(_) | KeyMaterial::RsaPublic(_) => Algorithm::RS256,
+            KeyMaterial::EcPrivate(_) | KeyMaterial::EcPublic(_) => Algorithm::ES256,
+        }
+    }
+
+    /// Returns `true` if the key can sign tokens.
+    pub fn can_sign(&self) -> bool {
+        !matches!(
+            self.material,
+            KeyMaterial::RsaPublic(_) | KeyMaterial::EcPublic(_)
+        )
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JwtError> {
+        match &self.material {
+            KeyMaterial::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+                mac.update(message);
+                Ok(mac.finalize().into_bytes().to_vec())
+            }
+            KeyMaterial::RsaPrivate(key) => {
+                let signer = RsaSigningKey::<Sha256>::new(key.as_ref().clone());
+                Ok(signer.sign(message).to_vec())
+            }
+            KeyMaterial::EcPrivate(key) => {
+                let signature: EcSignature = key.sign(message);
+                Ok(signature.to_bytes().to_vec())
+            }
+            KeyMaterial::RsaPublic(_) | KeyMaterial::EcPublic(_) => Err(JwtError::InvalidKey(
+                "a public key cannot sign tokens".to_string(),
+            )),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match &self.material {
+            KeyMaterial::Hmac(secret) => Hmac::<Sha256>::new_from_slice(secret)
+                .map(|mut mac| {
+                    mac.update(message);
+                    mac.verify_slice(signature).is_ok()
+                })
+                .unwrap_or(false),
+            KeyMaterial::RsaPrivate(key) => {
+                Self::verify_rsa(&key.to_public_key(), message, signature)
+            }
+            KeyMaterial::RsaPublic(key) => Self::verify_rsa(key, message, signature),
+            KeyMaterial::EcPrivate(key) => Self::verify_ec(key.verifying_key(), message, signature),
+            KeyMaterial::EcPublic(key) => Self::verify_ec(key, message, signature),
+        }
+    }
+
+    fn verify_rsa(key: &RsaPublicKey, message: &[u8], signature: &[u8]) -> bool {
+        RsaSignature::try_from(signature)
+            .map(|s| {
+                RsaVerifyingKey::<Sha256>::new(key.clone())
+                    .verify(message, &s)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    }
+
+    fn verify_ec(key: &EcVerifyingKey, message: &[u8], signature: &[u8]) -> bool {
+        EcSignature::from_slice(signature)
+            .map(|s| key.verify(message, &s).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Collection of keys accepted when verifying tokens.
+#[derive(Debug, Clone, Default)]
+pub struct KeySet {
+    keys: Vec<JwtKey>,
+}
+
+impl KeySet {
+    /// Create an empty key set.
+    pub fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    /// Add a key to the set.
+    pub fn add(&mut self, key: JwtKey) {
+        self.keys.push(key);
+    }
+
+    /// Return the set with `key` added.
+    pub fn with_key(mut self, key: JwtKey) -> Self {
+        self.add(key);
+        self
+    }
+
+    /// Retrieve the key identified by `kid`.
+    pub fn get(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|k| k.kid.as_deref() == Some(kid))
+    }
+
+    /// Select the key used to verify a token signed with `algorithm`.
+    ///
+    /// When the token names a `kid` only that key is considered, otherwise
+    /// the first key of the right algorithm is used.
+    pub fn select(&self, kid: Option<&str>, algorithm: Algorithm) -> Option<&JwtKey> {
+        match kid {
+            Some(kid) => self.get(kid).filter(|k| k.algorithm() == algorithm),
+            None => self.keys.iter().find(|k| k.algorithm() == algorithm),
+        }
+    }
+
+    /// Iterate over the keys.
+    pub fn iter(&self) -> impl Iterator<Item = &JwtKey> {
+        self.keys.iter()
+    }
+}
+
+/// Header of a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtHeader {
+    pub algorithm: Algorithm,
+    pub kid: Option<String>,
+}
+
+/// Rules applied to the registered claims of a token.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    /// Clock skew tolerated when checking `exp` and `nbf`, in seconds.
+    pub leeway: u64,
+    /// Expected value of the `iss` claim.
+    pub issuer: Option<String>,
+    /// Audience that must be listed in the `aud` claim.
+    pub audience: Option<String>,
+    /// Claims that must be present.
+    pub required: Vec<String>,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Self {
+            leeway: 60,
+            issuer: None,
+            audience: None,
+            required: vec!["exp".to_string()],
+        }
+    }
+}
+
+impl Validation {
+    /// Default rules: `exp` is required and a 60 seconds leeway is applied.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tolerated clock skew in seconds.
+    pub fn with_leeway(mut self, seconds: u64) -> Self {
+        self.leeway = seconds;
+        self
+    }
+
+    /// Require the `iss` claim to be `issuer`.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self.require("iss")
+    }
+
+    /// Require the `aud` claim to contain `audience`.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self.require("aud")
+    }
+
+    /// Require `claim` to be present.
+    pub fn require(mut self, claim: impl Into<String>) -> Self {
+        let claim = claim.into();
+        if !self.required.contains(&claim) {
+            self.required.push(claim);
+        }
+        self
+    }
+
+    /// Check `claims` at the Unix time `now`.
+    pub fn validate(&self, claims: &Dictionary<Value>, now: u64) -> Result<(), JwtError> {
+        for claim in &self.required {
+            if !claims.contains_key(claim) {
+                return Err(JwtError::MissingClaim(claim.clone()));
+            }
+        }
+        if let Some(exp) = claims.get("exp") {
+            let exp = timestamp(exp).ok_or_else(|| JwtError::MissingClaim("exp".to_string()))?;
+            if now > exp.saturating_add(self.leeway) {
+                return Err(JwtError::Expired);
+            }
+        }
+        if let Some(nbf) = claims.get("nbf") {
+            let nbf = timestamp(nbf).ok_or_else(|| JwtError::MissingClaim("nbf".to_string()))?;
+            if now.saturating_add(self.leeway) < nbf {
+                return Err(JwtError::NotYetValid);
+            }
+        }
+        if let Some(issuer) = &self.issuer {
+            if claims.get("iss") != Some(&Value::String(issuer.clone())) {
+                return Err(JwtError::InvalidIssuer);
+            }
+        }
+        if let Some(audience) = &self.audience {
+            let matches = match claims.get("aud") {
+                Some(Value::String(aud)) => aud == audience,
+                Some(Value::Array(auds)) => auds.contains(&Value::String(audience.clone())),
+                _ => false,
+            };
+            if !matches {
+                return Err(JwtError::InvalidAudience);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn timestamp(value: &Value) -> Option<u64> {
+    match value {
+        Value::Int(i) => u64::try_from(*i).ok(),
+        Value::Number(n) if n.is_finite() && *n >= 0.0 => Some(*n as u64),
+        _ => None,
+    }
+}
+
+fn decode_part(part: &str) -> Result<Dictionary<Value>, JwtError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|e| JwtError::Malformed(e.to_string()))?;
+    let json = String::from_utf8(bytes).map_err(|e| JwtError::Malformed(e.to_string()))?;
+    match JsonFormatter.parse(&json) {
+        Some(Value::Dictionary(d)) => Ok(d),
+        _ => Err(JwtError::Malformed("expected a JSON object".to_string())),
+    }
+}
+
+fn split(token: &str) -> Result<(&str, &str, &str), JwtError> {
+    let mut parts = token.split('.');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(claims), Some(signature), None) => Ok((header, claims, signature)),
+        _ => Err(JwtError::Malformed("expected three parts".to_string())),
+    }
+}
+
+/// Read the header of `token` without verifying it.
+pub fn decode_header(token: &str) -> Result<JwtHeader, JwtError> {
+    let (header, _, _) = split(token)?;
+    let header = decode_part(header)?;
+    let algorithm = match header.get("alg") {
+        Some(Value::String(alg)) => {
+            Algorithm::from_name(alg).ok_or_else(|| JwtError::UnsupportedAlgorithm(alg.clone()))?
+        }
+        _ => return Err(JwtError::MissingClaim("alg".to_string())),
+    };
+    let kid = match header.get("kid") {
+        Some(Value::String(kid)) => Some(kid.clone()),
+        _ => None,
+    };
+    Ok(JwtHeader { algorithm, kid })
+}
+
+/// Sign `claims` with `key` and return the compact token.
+pub fn encode(claims: &Dictionary<Value>, key: &JwtKey) -> Result<String, JwtError> {
+    let mut header = Dictionary::new();
+    header.insert(
+        "alg".to_string(),
+        Value::String(key.algorithm().to_string()),
+    );
+    header.insert("typ".to_string(), Value::String("JWT".to_string()));
+    if let Some(kid) = &key.kid {
+        header.insert("kid".to_string(), Value::String(kid.clone()));
+    }
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(JsonFormatter.format(Value::Dictionary(header))),
+        URL_SAFE_NO_PAD.encode(JsonFormatter.format(Value::Dictionary(claims.clone())))
+    );
+    let signature = key.sign(signing_input.as_bytes())?;
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+/// Verify the signature of `token` with `keys` and validate its claims.
+pub fn decode(
+    token: &str,
+    keys: &KeySet,
+    validation: &Validation,
+) -> Result<Dictionary<Value>, JwtError> {
+    let header = decode_header(token)?;
+    let (encoded_header, encoded_claims, signature) = split(token)?;
+    let key = keys
+        .select(header.kid.as_deref(), header.algorithm)
+        .ok_or(JwtError::UnknownKey(header.kid))?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|e| JwtError::Malformed(e.to_string()))?;
+    let signing_input = format!("{}.{}", encoded_header, encoded_claims);
+    if !key.verify(signing_input.as_bytes(), &signature) {
+        return Err(JwtError::InvalidSignature);
+    }
+    let claims = decode_part(encoded_claims)?;
+    validation.validate(&claims, now())?;
+    Ok(claims)
+}
+
+/// Bearer token verifier backed by JSON Web Tokens.
+///
+/// The authenticated [`Principal`] uses the `sub` claim as identifier, the
+/// `roles` claim as roles, the `permissions` claim and the space separated
+/// `scope` claim as permissions, and keeps every claim.
+#[derive(Debug, Clone)]
+pub struct JwtVerifier {
+    pub keys: KeySet,
+    pub validation: Validation,
+}
+
+impl JwtVerifier {
+    /// Create a verifier accepting tokens signed by `keys`.
+    pub fn new(keys: KeySet, validation: Validation) -> Self {
+        Self { keys, validation }
+    }
+
+    /// Verify `token` and return its claims.
+    pub fn decode(&self, token: &str) -> Result<Dictionary<Value>, JwtError> {
+        decode(token, &self.keys, &self.validation)
+    }
+
+    /// Build the principal described by `claims`.
+    pub fn principal(claims: Dictionary<Value>) -> Option<Principal> {
+        let Some(Value::String(subject)) = claims.get("sub") else {
+            return None;
+        };
+        let strings = |key: &str| match claims.get(key) {
+            Some(Value::Array(a)) => a
+                .iter()
+                .filter_map(|v| match v {
+                    Value::String(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut principal = Principal::new(subject.clone());
+        principal.roles = strings("roles");
+        principal.permissions = strings("permissions");
+        if let Some(Value::String(scope)) = claims.get("scope") {
+            principal
+                .permissions
+                .extend(scope.split_whitespace().map(|s| s.to_string()));
+        }
+        principal.claims = claims;
+        Some(principal)
+    }
+}
+
+impl TokenVerifier for JwtVerifier {
+    fn verify(&self, token: &str) -> Option<Principal> {
+        self.decode(token).ok().and_then(Self::principal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSA_PRIVATE: &str = include_str!("../../../tests/fixtures/jwt/rsa.pem");
+    const RSA_PUBLIC: &str = include_str!("../../../tests/fixtures/jwt/rsa.pub");
+    const EC_PRIVATE: &str = include_str!("../../../tests/fixtures/jwt/ec.pem");
+    const EC_PUBLIC: &str = include_str!("../../../tests/fixtures/jwt/ec.pub");
+
+    fn claims() -> Dictionary<Value> {
+        let mut claims = Dictionary::new();
+        claims.insert("sub".to_string(), Value::String("alice".into()));
+        claims.insert("exp".to_string(), Value::Int(4_102_444_800));
+        claims.insert(
+            "roles".to_string(),
+            Value::Array(vec![Value::String("admin".into())]),
+        );
+        claims.insert("scope".to_string(), Value::String("read write".into()));
+        claims
+    }
+
+    #[test]
+    fn test_hs256_round_trip() {
+        let key = JwtKey::hmac("secret");
+        let token = encode(&claims(), &key).unwrap();
+        assert_eq!(token.split('.').count(), 3);
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.algorithm, Algorithm::HS256);
+        assert_eq!(header.kid, None);
+
+        let keys = KeySet::new().with_key(key);
+        assert_eq!(decode(&token, &keys, &Validation::new()).unwrap(), claims());
+
+        let other = KeySet::new().with_key(JwtKey::hmac("other"));
+        assert_eq!(
+            decode(&token, &other, &Validation::new()),
+            Err(JwtError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_rs256_and_es256_round_trip() {
+        let rsa = JwtKey::rsa_private_pem(RSA_PRIVATE)
+            .unwrap()
+            .with_kid("rsa");
+        let ec = JwtKey::ec_private_pem(EC_PRIVATE).unwrap().with_kid("ec");
+        let keys = KeySet::new()
+            .with_key(JwtKey::rsa_public_pem(RSA_PUBLIC).unwrap().with_kid("rsa"))
+            .with_key(JwtKey::ec_public_pem(EC_PUBLIC).unwrap().with_kid("ec"));
+
+        for key in [rsa, ec] {
+            let token = encode(&claims(), &key).unwrap();
+            assert_eq!(decode_header(&token).unwrap().algorithm, key.algorithm());
+            assert_eq!(decode(&token, &keys, &Validation::new()).unwrap(), claims());
+        }
+
+        let public = JwtKey::ec_public_pem(EC_PUBLIC).unwrap();
+        assert!(!public.can_sign());
+        assert!(matches!(
+            encode(&claims(), &public),
+            Err(JwtError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_kid_lookup() {
+        let old = JwtKey::hmac("old").with_kid("1");
+        let new = JwtKey::hmac("new").with_kid("2");
+        let keys = KeySet::new().with_key(old.clone()).with_key(new.clone());
+        for key in [old, new] {
+            let token = encode(&claims(), &key).unwrap();
+            assert!(decode(&token, &keys, &Validation::new()).is_ok());
+        }
+
+        let unknown = encode(&claims(), &JwtKey::hmac("new").with_kid("3")).unwrap();
+        assert_eq!(
+            decode(&unknown, &keys, &Validation::new()),
+            Err(JwtError::UnknownKey(Some("3".to_string())))
+        );
+
+        // A token cannot select a key meant for another algorithm.
+        let rsa_keys =
+            KeySet::new().with_key(JwtKey::rsa_public_pem(RSA_PUBLIC).unwrap().with_kid("1"));
+        let token = encode(&claims(), &JwtKey::hmac(RSA_PUBLIC).with_kid("1")).unwrap();
+        assert_eq!(
+            decode(&token, &rsa_keys, &Validation::new()),
+            Err(JwtError::UnknownKey(Some("1".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_claims_validation() {
+        let mut claims = Dictionary::new();
+        claims.insert("exp".to_string(), Value::Int(1_000));
+        claims.insert("nbf".to_string(), Value::Int(500));
+        claims.insert("iss".to_string(), Value::String("hermes".into()));
+        claims.insert(
+            "aud".to_string(),
+            Value::Array(vec![
+                Value::String("api".into()),
+                Value::String("web".into()),
+            ]),
+        );
+
+        let v = Validation::new().with_leeway(10);
+        assert_eq!(v.validate(&claims, 1_005), Ok(()));
+        assert_eq!(v.validate(&claims, 1_011), Err(JwtError::Expired));
+        assert_eq!(v.validate(&claims, 495), Ok(()));
+        assert_eq!(v.validate(&claims, 489), Err(JwtError::NotYetValid));
+
+        let v = Validation::new().with_issuer("hermes").with_audience("web");
+        assert_eq!(v.validate(&claims, 800), Ok(()));
+        let v = Validation::new().with_issuer("other");
+        assert_eq!(v.validate(&claims, 800), Err(JwtError::InvalidIssuer));
+        let v = Validation::new().with_audience("mobile");
+        assert_eq!(v.validate(&claims, 800), Err(JwtError::InvalidAudience));
+
+        claims.shift_remove("exp");
+        assert_eq!(
+            Validation::new().validate(&claims, 800),
+            Err(JwtError::MissingClaim("exp".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_malformed_tokens() {
+        let keys = KeySet::new().with_key(JwtKey::hmac("secret"));
+        let v = Validation::new();
+        assert!(matches!(
+            decode("abc", &keys, &v),
+            Err(JwtError::Malformed(_))
+        ));
+        assert!(matches!(
+            decode("a.b.c.d", &keys, &v),
+            Err(JwtError::Malformed(_))
+        ));
+        let none = format!(
+            "{}.{}.",
+            URL_SAFE_NO_PAD.encode("{\"alg\":\"none\"}"),
+            URL_SAFE_NO_PAD.encode("{}")
+        );
+        assert_eq!(
+            decode(&none, &keys, &v),
+            Err(JwtError::UnsupportedAlgorithm("none".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_verifier_builds_principal() {
+        let key = JwtKey::hmac("secret");
+        let verifier = JwtVerifier::new(KeySet::new().with_key(key.clone()), Validation::new());
+        let principal = verifier.verify(&encode(&claims(), &key).unwrap()).unwrap();
+        assert_eq!(principal.identifier, "alice");
+        assert!(principal.has_role("admin"));
+        assert!(principal.has_permission("read"));
+        assert!(principal.has_permission("write"));
+        assert_eq!(
+            principal.claims.get("scope"),
+            Some(&Value::String("read write".into()))
+        );
+
+        let mut anonymous = claims();
+        anonymous.shift_remove("sub");
+        assert!(verifier
+            .verify(&encode(&anonymous, &key).unwrap())
+            .is_none());
+    }
+}
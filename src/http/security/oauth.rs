@@ -0,0 +1,554 @@
+//! OAuth 2.0 authorization code flow with PKCE (RFC 6749, RFC 7636).
+//!
+//! [`OAuthClient`] drives a login against an authorization server:
+//!
+//! 1. [`authorize`](OAuthClient::authorize) stores a random `state` and PKCE
+//!    code verifier in the [`Session`] and redirects the user agent to the
+//!    authorization endpoint.
+//! 2. [`callback`](OAuthClient::callback) handles the request received on the
+//!    redirect URI: it checks the `state`, exchanges the `code` at the token
+//!    endpoint with the [`Client`] and stores the [`TokenSet`] in the session.
+//! 3. [`refresh`](OAuthClient::refresh) obtains a new access token with the
+//!    stored refresh token.
+//!
+//! Session changes are not persisted automatically; call
+//! [`Session::persist`] once the response is built.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::http::security::oauth::OAuthClient;
+//! use hermes::http::session::{FileStore, Session};
+//! use hermes::http::{ResponseTrait, Status};
+//!
+//! let oauth = OAuthClient::new(
+//!     "my-app",
+//!     "http://auth.example.com/authorize",
+//!     "http://auth.example.com/token",
+//!     "http://app.example.com/callback",
+//! )
+//! .unwrap()
+//! .with_scopes(&["openid", "profile"]);
+//!
+//! let dir = std::env::temp_dir().join("hermes_oauth_doc");
+//! let mut session = Session::new("doc", FileStore::new(&dir));
+//! let response = oauth.authorize(&mut session);
+//! assert_eq!(response.status(), Status::Found);
+//! assert!(session.get("oauth.state").is_some());
+//! # std::fs::remove_dir_all(&dir).ok();
+//! ```
+
+use crate::concepts::value::json::JsonFormatter;
+use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use crate::http::security::authentication::Credentials;
+use crate::http::security::{now, random_token, same_token};
+use crate::http::services::client::Client;
+use crate::http::session::{Session, SessionStore};
+use crate::http::{
+    form_encode, form_urlencode, Headers, MessageTrait, Redirection, Request, Response,
+    ResponseFactory, ResponseTrait, Uri, Version,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Errors raised during the authorization code flow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OAuthError {
+    /// An endpoint URL is not an absolute URI.
+    InvalidUrl(String),
+    /// The `state` returned by the authorization server does not match.
+    StateMismatch,
+    /// The callback request carries no authorization `code`.
+    MissingCode,
+    /// No refresh token is available.
+    MissingRefreshToken,
+    /// The authorization server returned an error.
+    Provider {
+        error: String,
+        description: Option<String>,
+    },
+    /// The token endpoint answered with an unexpected payload.
+    InvalidResponse(String),
+    /// The token endpoint could not be reached.
+    Io(String),
+}
+
+impl Display for OAuthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OAuthError::InvalidUrl(url) => write!(f, "Invalid OAuth endpoint URL: {}", url),
+            OAuthError::StateMismatch => write!(f, "OAuth state mismatch"),
+            OAuthError::MissingCode => write!(f, "Missing authorization code"),
+            OAuthError::MissingRefreshToken => write!(f, "Missing refresh token"),
+            OAuthError::Provider {
+                error,
+                description: Some(description),
+            } => write!(f, "Authorization server error: {} ({})", error, description),
+            OAuthError::Provider { error, .. } => {
+                write!(f, "Authorization server error: {}", error)
+            }
+            OAuthError::InvalidResponse(reason) => write!(f, "Invalid token response: {}", reason),
+            OAuthError::Io(reason) => write!(f, "Token endpoint unreachable: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for OAuthError {}
+
+/// Tokens issued by the authorization server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenSet {
+    pub access_token: String,
+    pub token_type: String,
+    pub refresh_token: Option<String>,
+    /// Unix time after which the access token is expired.
+    pub expires_at: Option<u64>,
+    pub scope: Option<String>,
+    pub id_token: Option<String>,
+}
+
+impl TokenSet {
+    /// Build a token set from a token endpoint response received at `issued_at`.
+    pub fn from_response(data: &Dictionary<Value>, issued_at: u64) -> Result<Self, OAuthError> {
+        let string = |key: &str| match data.get(key) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let access_token = string("access_token")
+            .ok_or_else(|| OAuthError::InvalidResponse("missing access_token".to_string()))?;
+        let expires_in = match data.get("expires_in") {
+            Some(Value::Int(i)) => u64::try_from(*i).ok(),
+            Some(Value::String(s)) => s.parse().ok(),
+            _ => None,
+        };
+        Ok(Self {
+            access_token,
+            token_type: string("token_type").unwrap_or_else(|| "Bearer".to_string()),
+            refresh_token: string("refresh_token"),
+            expires_at: expires_in.map(|e| issued_at.saturating_add(e)),
+            scope: string("scope"),
+            id_token: string("id_token"),
+        })
+    }
+
+    /// Returns `true` if the access token is expired at the Unix time `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| now >= at)
+    }
+
+    /// Convert the tokens into a [`Value::Dictionary`] for session storage.
+    pub fn to_value(&self) -> Value {
+        let mut d = Dictionary::new();
+        d.insert(
+            "access_token".to_string(),
+            Value::String(self.access_token.clone()),
+        );
+        d.insert(
+            "token_type".to_string(),
+            Value::String(self.token_type.clone()),
+        );
+        let optional = [
+            ("refresh_token", &self.refresh_token),
+            ("scope", &self.scope),
+            ("id_token", &self.id_token),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                d.insert(key.to_string(), Value::String(value.clone()));
+            }
+        }
+        if let Some(at) = self.expires_at {
+            d.insert("expires_at".to_string(), Value::Int(at as i64));
+        }
+        Value::Dictionary(d)
+    }
+
+    /// Rebuild tokens stored with [`TokenSet::to_value`].
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let Value::Dictionary(d) = value else {
+            return None;
+        };
+        let string = |key: &str| match d.get(key) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        Some(Self {
+            access_token: string("access_token")?,
+            token_type: string("token_type").unwrap_or_else(|| "Bearer".to_string()),
+            refresh_token: string("refresh_token"),
+            expires_at: match d.get("expires_at") {
+                Some(Value::Int(i)) => u64::try_from(*i).ok(),
+                _ => None,
+            },
+            scope: string("scope"),
+            id_token: string("id_token"),
+        })
+    }
+}
+
+/// Derive the `S256` PKCE code challenge of `verifier`.
+///
+/// ```
+/// use hermes::http::security::oauth::code_challenge;
+///
+/// // Example from RFC 7636, appendix B.
+/// assert_eq!(
+///     code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+///     "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+/// );
+/// ```
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Parameters of a pending authorization request.
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// URL the user agent must be redirected to.
+    pub url: Uri,
+    /// Opaque value bound to the user session.
+    pub state: String,
+    /// PKCE secret sent when exchanging the code.
+    pub code_verifier: String,
+}
+
+/// OAuth 2.0 client using the authorization code grant with PKCE.
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub authorize_url: Uri,
+    pub token_url: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// Prefix of the keys used in the session.
+    pub session_key: String,
+}
+
+impl OAuthClient {
+    /// Create a public client (without secret).
+    ///
+    /// Fails with [`OAuthError::InvalidUrl`] unless both endpoints are
+    /// absolute URIs.
+    pub fn new(
+        client_id: &str,
+        authorize_url: &str,
+        token_url: &str,
+        redirect_uri: &str,
+    ) -> Result<Self, OAuthError> {
+        let endpoint = |url: &str| {
+            Uri::from_str(url)
+                .ok()
+                .filter(|uri| !uri.scheme.is_empty() && !uri.authority.host.is_empty())
+                .ok_or_else(|| OAuthError::InvalidUrl(url.to_string()))
+        };
+        let authorize_url = endpoint(authorize_url)?;
+        endpoint(token_url)?;
+        Ok(Self {
+            client_id: client_id.to_string(),
+            client_secret: None,
+            authorize_url,
+            token_url: token_url.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes: Vec::new(),
+            session_key: "oauth".to_string(),
+        })
+    }
+
+    /// Authenticate at the token endpoint with `secret` (HTTP Basic).
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.client_secret = Some(secret.to_string());
+        self
+    }
+
+    /// Request the given scopes.
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Use `key` as prefix of the session keys.
+    pub fn with_session_key(mut self, key: &str) -> Self {
+        self.session_key = key.to_string();
+        self
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}.{}", self.session_key, name)
+    }
+
+    /// Generate a fresh `state` and code verifier and build the authorization URL.
+    pub fn authorization_request(&self) -> AuthorizationRequest {
+        let state = random_token(16);
+        let code_verifier = random_token(32);
+        let url = self.authorize_url.clone();
+        let mut params = vec![
+            ("response_type", "code".to_string()),
+            ("client_id", self.client_id.clone()),
+            ("redirect_uri", self.redirect_uri.clone()),
+            ("state", state.clone()),
+            ("code_challenge", code_challenge(&code_verifier)),
+            ("code_challenge_method", "S256".to_string()),
+        ];
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+        let mut query = url.query();
+        for (key, value) in params {
+            query.set(key, &value);
+        }
+        let url = url.with_query(&query);
+        AuthorizationRequest {
+            url,
+            state,
+            code_verifier,
+        }
+    }
+
+    /// Start the login: remember the request in `session` and redirect to
+    /// the authorization endpoint.
+    pub fn authorize<S: SessionStore + Clone>(&self, session: &mut Session<S>) -> Response {
+        let request = self.authorization_request();
+        session.insert(self.key("state"), Value::String(request.state));
+        session.insert(self.key("verifier"), Value::String(request.code_verifier));
+        ResponseFactory::version(Version::Http1_1).redirect(Redirection::Found(request.url))
+    }
+
+    /// Handle the request received on the redirect URI.
+    ///
+    /// On success the tokens are stored in `session` and returned.
+    pub async fn callback<S: SessionStore + Clone>(
+        &self,
+        req: &Request,
+        session: &mut Session<S>,
+    ) -> Result<TokenSet, OAuthError> {
+        let query = req.target.query();
+        let expected = session.get(&self.key("state")).cloned();
+        let verifier = session.get(&self.key("verifier")).cloned();
+        session.remove(&self.key("state"));
+        session.remove(&self.key("verifier"));
+
+        if let Some(error) = query.get("error") {
+            return Err(OAuthError::Provider {
+                error: error.clone(),
+                description: query.get("error_description").cloned(),
+            });
+        }
+        match (query.get("state"), expected) {
+            (Some(state), Some(Value::String(expected))) if same_token(state, &expected) => {}
+            _ => return Err(OAuthError::StateMismatch),
+        }
+        let Some(code) = query.get("code").filter(|c| !c.is_empty()) else {
+            return Err(OAuthError::MissingCode);
+        };
+        let Some(Value::String(verifier)) = verifier else {
+            return Err(OAuthError::StateMismatch);
+        };
+        let tokens = self.exchange_code(code, &verifier).await?;
+        session.insert(self.key("tokens"), tokens.to_value());
+        Ok(tokens)
+    }
+
+    /// Exchange an authorization `code` for tokens.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<TokenSet, OAuthError> {
+        self.token_request(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_uri),
+            ("client_id", &self.client_id),
+            ("code_verifier", code_verifier),
+        ])
+        .await
+    }
+
+    /// Obtain new tokens using `refresh_token`.
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<TokenSet, OAuthError> {
+        let mut tokens = self
+            .token_request(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+                ("client_id", &self.client_id),
+            ])
+            .await?;
+        if tokens.refresh_token.is_none() {
+            tokens.refresh_token = Some(refresh_token.to_string());
+        }
+        Ok(tokens)
+    }
+
+    /// Refresh the tokens stored in `session` and store the new ones.
+    pub async fn refresh<S: SessionStore + Clone>(
+        &self,
+        session: &mut Session<S>,
+    ) -> Result<TokenSet, OAuthError> {
+        let refresh_token = self
+            .tokens(session)
+            .and_then(|t| t.refresh_token)
+            .ok_or(OAuthError::MissingRefreshToken)?;
+        let tokens = self.refresh_token(&refresh_token).await?;
+        session.insert(self.key("tokens"), tokens.to_value());
+        Ok(tokens)
+    }
+
+    /// Tokens currently stored in `session`.
+    pub fn tokens<S: SessionStore + Clone>(&self, session: &Session<S>) -> Option<TokenSet> {
+        session
+            .get(&self.key("tokens"))
+            .and_then(TokenSet::from_value)
+    }
+
+    /// Forget the tokens stored in `session`.
+    pub fn logout<S: SessionStore + Clone>(&self, session: &mut Session<S>) {
+        session.remove(&self.key("tokens"));
+    }
+
+    /// `Basic` credentials authenticating the client at the token endpoint.
+    ///
+    /// The identifier and secret are form-urlencoded first (RFC 6749,
+    /// section 2.3.1).
+    fn client_credentials(&self) -> Option<Credentials> {
+        self.client_secret
+            .as_ref()
+            .map(|secret| Credentials::basic(form_encode(&self.client_id), form_encode(secret)))
+    }
+
+    async fn token_request(&self, params: &[(&str, &str)]) -> Result<TokenSet, OAuthError> {
+        let body = form_urlencode(params.iter().copied());
+        let mut headers = Headers::new();
+        headers.set("Content-Type", &["application/x-www-form-urlencoded"]);
+        headers.set("Accept", &["application/json"]);
+        headers.set("Content-Length", &[&body.len().to_string()]);
+        if let Some(credentials) = self.client_credentials() {
+            headers.set("Authorization", &[&credentials.to_string()]);
+        }
+        let issued_at = now();
+        let response = Client::post(&self.token_url, headers, &body)
+            .await
+            .map_err(|e| OAuthError::Io(e.to_string()))?;
+        let data = match JsonFormatter.parse(&response.body()) {
+            Some(Value::Dictionary(d)) => d,
+            _ => {
+                return Err(OAuthError::InvalidResponse(format!(
+                    "{} {}",
+                    response.code(),
+                    response.reason()
+                )))
+            }
+        };
+        if let Some(Value::String(error)) = data.get("error") {
+            return Err(OAuthError::Provider {
+                error: error.clone(),
+                description: match data.get("error_description") {
+                    Some(Value::String(d)) => Some(d.clone()),
+                    _ => None,
+                },
+            });
+        }
+        if !response.status().is_successful() {
+            return Err(OAuthError::InvalidResponse(format!(
+                "{} {}",
+                response.code(),
+                response.reason()
+            )));
+        }
+        TokenSet::from_response(&data, issued_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization_request() {
+        let client = OAuthClient::new(
+            "app",
+            "http://auth.local/authorize?prompt=login",
+            "http://auth.local/token",
+            "http://app.local/cb",
+        )
+        .unwrap()
+        .with_scopes(&["openid", "email"]);
+        let request = client.authorization_request();
+        let query = request.url.query();
+        assert_eq!(request.url.authority.host, "auth.local");
+        assert_eq!(query.get("prompt"), Some(&"login".to_string()));
+        assert_eq!(query.get("response_type"), Some(&"code".to_string()));
+        assert_eq!(query.get("client_id"), Some(&"app".to_string()));
+        assert_eq!(
+            query.get("redirect_uri"),
+            Some(&"http://app.local/cb".to_string())
+        );
+        assert_eq!(query.get("scope"), Some(&"openid email".to_string()));
+        let url = request.url.to_string();
+        assert!(url.contains("&redirect_uri=http%3A%2F%2Fapp.local%2Fcb&"));
+        assert!(url.ends_with("&scope=openid+email"));
+        assert_eq!(query.get("state"), Some(&request.state));
+        assert_eq!(
+            query.get("code_challenge"),
+            Some(&code_challenge(&request.code_verifier))
+        );
+        assert_eq!(request.code_verifier.len(), 43);
+        assert_ne!(client.authorization_request().state, request.state);
+    }
+
+    #[test]
+    fn test_invalid_endpoints() {
+        for (authorize, token) in [
+            ("", "http://auth.local/token"),
+            ("/authorize", "http://auth.local/token"),
+            ("http://auth.local/a b", "http://auth.local/token"),
+            ("http://auth.local/authorize", "token"),
+        ] {
+            assert!(matches!(
+                OAuthClient::new("app", authorize, token, "http://app.local/cb"),
+                Err(OAuthError::InvalidUrl(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_client_credentials() {
+        let client = OAuthClient::new(
+            "my app",
+            "http://auth.local/authorize",
+            "http://auth.local/token",
+            "http://app.local/cb",
+        )
+        .unwrap();
+        assert_eq!(client.client_credentials(), None);
+        let client = client.with_secret("s3cr:t+/~");
+        assert_eq!(
+            client.client_credentials(),
+            Some(Credentials::basic("my+app", "s3cr%3At%2B%2F%7E"))
+        );
+    }
+
+    #[test]
+    fn test_token_set() {
+        let mut data = Dictionary::new();
+        data.insert("access_token".to_string(), Value::String("a".into()));
+        data.insert("refresh_token".to_string(), Value::String("r".into()));
+        data.insert("expires_in".to_string(), Value::Int(3600));
+        let tokens = TokenSet::from_response(&data, 1_000).unwrap();
+        assert_eq!(tokens.token_type, "Bearer");
+        assert_eq!(tokens.expires_at, Some(4_600));
+        assert!(!tokens.is_expired(4_599));
+        assert!(tokens.is_expired(4_600));
+        assert_eq!(TokenSet::from_value(&tokens.to_value()), Some(tokens));
+
+        data.shift_remove("access_token");
+        assert!(matches!(
+            TokenSet::from_response(&data, 0),
+            Err(OAuthError::InvalidResponse(_))
+        ));
+    }
+}
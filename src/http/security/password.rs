@@ -0,0 +1,274 @@
+//! Password hashing.
+//!
+//! Passwords are hashed with Argon2id and stored as [PHC strings] embedding
+//! the algorithm, its parameters and the salt, e.g.
+//! `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`. Verification compares the
+//! digests in constant time.
+//!
+//! The [`PasswordHasher`] service is meant to be registered in the
+//! [`Container`](crate::container::Container) as `Box<dyn PasswordHasher>`.
+//! [`PasswordVerifier`] combines it with a user lookup into a
+//! [`CredentialVerifier`] usable by the
+//! [`Authenticator`](crate::http::security::authentication::Authenticator).
+//!
+//! [PHC strings]: https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::container::Container;
+//! use hermes::http::security::authentication::{CredentialVerifier, Principal};
+//! use hermes::http::security::password::{Argon2Hasher, PasswordHasher, PasswordVerifier};
+//!
+//! let mut container = Container::new();
+//! container.register::<Box<dyn PasswordHasher>>(Box::new(Argon2Hasher::new(1024, 1, 1)));
+//!
+//! let hasher = container.resolve::<Box<dyn PasswordHasher>>().unwrap();
+//! let stored = hasher.hash("secret").unwrap();
+//! assert!(stored.starts_with("$argon2id$"));
+//!
+//! let verifier = PasswordVerifier::new(hasher, move |user: &str| {
+//!     (user == "alice").then(|| (stored.clone(), Principal::new(user)))
+//! });
+//! assert!(verifier.verify("alice", "secret").is_some());
+//! assert!(verifier.verify("alice", "wrong").is_none());
+//! assert!(verifier.verify("bob", "secret").is_none());
+//! ```
+
+use crate::http::security::authentication::{CredentialVerifier, Principal};
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, OnceLock};
+
+/// Errors raised while hashing a password.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordError {
+    /// The hashing parameters are out of range.
+    InvalidParameters(String),
+    /// The password could not be hashed.
+    Hashing(String),
+}
+
+impl Display for PasswordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::InvalidParameters(reason) => {
+                write!(f, "Invalid hashing parameters: {}", reason)
+            }
+            PasswordError::Hashing(reason) => write!(f, "Password hashing failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PasswordError {}
+
+/// Service hashing and verifying passwords.
+pub trait PasswordHasher: Send + Sync {
+    /// Hash `password` with a fresh random salt into a PHC string.
+    fn hash(&self, password: &str) -> Result<String, PasswordError>;
+
+    /// Check `password` against the PHC string `hash`.
+    ///
+    /// Malformed hashes never match.
+    fn verify(&self, password: &str, hash: &str) -> bool;
+
+    /// Returns `true` if `hash` was produced with another algorithm or other
+    /// parameters than the current ones and should be replaced once the
+    /// password is known.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+impl<H: PasswordHasher + ?Sized> PasswordHasher for Box<H> {
+    fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        (**self).hash(password)
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        (**self).verify(password, hash)
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        (**self).needs_rehash(hash)
+    }
+}
+
+impl<H: PasswordHasher + ?Sized> PasswordHasher for Arc<H> {
+    fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        (**self).hash(password)
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        (**self).verify(password, hash)
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        (**self).needs_rehash(hash)
+    }
+}
+
+/// Argon2id password hasher.
+///
+/// The default parameters follow the OWASP recommendation: 19 MiB of memory,
+/// 2 iterations and 1 degree of parallelism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Hasher {
+    /// Memory size in KiB.
+    pub memory_cost: u32,
+    /// Number of iterations.
+    pub time_cost: u32,
+    /// Degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Hasher {
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl Argon2Hasher {
+    /// Create a hasher with the given memory (KiB), time and parallelism costs.
+    pub fn new(memory_cost: u32, time_cost: u32, parallelism: u32) -> Self {
+        Self {
+            memory_cost,
+            time_cost,
+            parallelism,
+        }
+    }
+
+    fn context(&self) -> Result<Argon2<'static>, PasswordError> {
+        let params = Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+            .map_err(|e| PasswordError::InvalidParameters(e.to_string()))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+impl PasswordHasher for Argon2Hasher {
+    fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        let salt = SaltString::generate(&mut rand::rngs::OsRng);
+        self.context()?
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| PasswordError::Hashing(e.to_string()))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(hash) else {
+            return false;
+        };
+        // The parameters are read from the hash, so older hashes still verify.
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        if Algorithm::try_from(hash.algorithm) != Ok(Algorithm::Argon2id)
+            || hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Params::try_from(&hash) {
+            Ok(params) => {
+                params.m_cost() != self.memory_cost
+                    || params.t_cost() != self.time_cost
+                    || params.p_cost() != self.parallelism
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// [`CredentialVerifier`] checking passwords against stored hashes.
+///
+/// `users` returns the stored hash and the principal of a user name. Unknown
+/// users are checked against a dummy hash so that response times do not
+/// reveal which user names exist.
+pub struct PasswordVerifier<H, U> {
+    hasher: H,
+    users: U,
+    dummy: OnceLock<String>,
+}
+
+impl<H, U> PasswordVerifier<H, U>
+where
+    H: PasswordHasher,
+    U: Fn(&str) -> Option<(String, Principal)> + Send + Sync,
+{
+    /// Create a verifier hashing with `hasher` and looking up `users`.
+    pub fn new(hasher: H, users: U) -> Self {
+        Self {
+            hasher,
+            users,
+            dummy: OnceLock::new(),
+        }
+    }
+}
+
+impl<H, U> CredentialVerifier for PasswordVerifier<H, U>
+where
+    H: PasswordHasher,
+    U: Fn(&str) -> Option<(String, Principal)> + Send + Sync,
+{
+    fn verify(&self, username: &str, password: &str) -> Option<Principal> {
+        match (self.users)(username) {
+            Some((hash, principal)) => self.hasher.verify(password, &hash).then_some(principal),
+            None => {
+                let dummy = self
+                    .dummy
+                    .get_or_init(|| self.hasher.hash("").unwrap_or_default());
+                self.hasher.verify(password, dummy);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hasher() -> Argon2Hasher {
+        Argon2Hasher::new(1024, 1, 1)
+    }
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hasher = hasher();
+        let hash = hasher.hash("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert_ne!(hash, hasher.hash("correct horse").unwrap());
+        assert!(hasher.verify("correct horse", &hash));
+        assert!(!hasher.verify("battery staple", &hash));
+        assert!(!hasher.verify("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let hash = hasher().hash("pw").unwrap();
+        assert!(!hasher().needs_rehash(&hash));
+        let stronger = Argon2Hasher::new(2048, 1, 1);
+        assert!(stronger.needs_rehash(&hash));
+        // older hashes still verify after a parameter change
+        assert!(stronger.verify("pw", &hash));
+        assert!(hasher()
+            .needs_rehash("$argon2i$v=19$m=1024,t=1,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNoaGFzaA"));
+        assert!(hasher().needs_rehash("garbage"));
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert!(matches!(
+            Argon2Hasher::new(1, 1, 1).hash("pw"),
+            Err(PasswordError::InvalidParameters(_))
+        ));
+    }
+}
@@ -0,0 +1,608 @@
+//! Request rate limiting.
+//!
+//! The [`RateLimiter`] middleware counts requests per client with a
+//! [`Limit`] (token bucket or sliding window) and answers
+//! `429 Too Many Requests` with a `Retry-After` header once the limit is
+//! reached. Every response carries the `RateLimit-Limit`,
+//! `RateLimit-Remaining`, `RateLimit-Reset` and `RateLimit-Policy` headers.
+//!
+//! Clients are identified by a [`KeyBy`] strategy. The counters live in a
+//! [`RateLimitBackend`] registered in the [`Container`] as
+//! `Box<dyn RateLimitBackend>`; [`MemoryBackend`] keeps them in process and
+//! is used when no backend is registered.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::container::Container;
+//! use hermes::http::routing::router::{Route, Router};
+//! use hermes::http::security::rate_limit::{KeyBy, Limit, MemoryBackend, RateLimitBackend, RateLimiter};
+//! use hermes::http::{Headers, Method, MessageTrait, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, Uri, Version};
+//! use hermes::concepts::value::Value;
+//! use hermes::concepts::Parsable;
+//! use std::time::Duration;
+//!
+//! let mut container = Container::new();
+//! container.register::<Box<dyn RateLimitBackend>>(Box::new(MemoryBackend::new()));
+//!
+//! let mut router: Router<Container> = Router::new();
+//! router.add_route(
+//!     Route::new("/login", vec![Method::Post], Headers::new(), Box::new(
+//!         |_: &Container, _: &mut Request| ResponseFactory::version(Version::Http1_1).no_content(Headers::new()),
+//!     ))
+//!     .with_before(Box::new(RateLimiter::new("login", Limit::sliding_window(2, Duration::from_secs(60)), KeyBy::Ip))),
+//! );
+//!
+//! let factory = RequestFactory::version(Version::Http1_1);
+//! let mut statuses = Vec::new();
+//! for _ in 0..3 {
+//!     let mut req = factory
+//!         .post(Uri::parse("/login").unwrap().1, Headers::new(), "")
+//!         .with_attribute(Request::REMOTE_ADDR_ATTRIBUTE, Value::String("10.0.0.1".into()));
+//!     let resp = router.handle_request(&container, &mut req).unwrap();
+//!     statuses.push(resp.code());
+//!     if resp.code() == 429 {
+//!         assert!(resp.has_header("Retry-After"));
+//!     }
+//! }
+//! assert_eq!(statuses, vec![204, 204, 429]);
+//! ```
+
+use crate::concepts::value::Value;
+use crate::container::Container;
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::security::authentication::Principal;
+use crate::http::{Headers, MessageTrait, Request, Response, ResponseFactory, Status};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Throttling algorithm and its parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    /// Bucket of `capacity` tokens refilled at `refill` tokens per second.
+    /// Allows bursts up to `capacity`.
+    TokenBucket { capacity: u32, refill: f64 },
+    /// At most `limit` requests in any `window`, estimated from the counts
+    /// of the current and previous fixed windows.
+    SlidingWindow { limit: u32, window: Duration },
+}
+
+impl Limit {
+    /// Token bucket holding `capacity` tokens, fully refilled every `period`.
+    pub fn token_bucket(capacity: u32, period: Duration) -> Self {
+        Limit::TokenBucket {
+            capacity,
+            refill: capacity as f64 / period.as_secs_f64().max(f64::EPSILON),
+        }
+    }
+
+    /// Sliding window allowing `limit` requests per `window`.
+    pub fn sliding_window(limit: u32, window: Duration) -> Self {
+        Limit::SlidingWindow { limit, window }
+    }
+
+    /// Maximum number of requests.
+    pub fn quota(&self) -> u32 {
+        match self {
+            Limit::TokenBucket { capacity, .. } => *capacity,
+            Limit::SlidingWindow { limit, .. } => *limit,
+        }
+    }
+
+    /// Length of the window in seconds, as advertised in `RateLimit-Policy`.
+    pub fn window_seconds(&self) -> u64 {
+        match self {
+            Limit::TokenBucket { capacity, refill } => {
+                (*capacity as f64 / refill.max(f64::EPSILON)).ceil() as u64
+            }
+            Limit::SlidingWindow { window, .. } => window.as_secs_f64().ceil() as u64,
+        }
+    }
+}
+
+/// Outcome of a request against a [`Limit`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Requests still allowed right now.
+    pub remaining: u32,
+    /// Time until the quota is fully available again.
+    pub reset: Duration,
+    /// Time to wait before retrying when the request is denied.
+    pub retry_after: Option<Duration>,
+}
+
+/// Storage of the limiter state.
+///
+/// `now` is the time elapsed since the Unix epoch, given by the caller so
+/// several processes sharing a backend agree on time.
+pub trait RateLimitBackend: Send + Sync {
+    /// Count one request for `key` and decide whether it is allowed.
+    fn hit(&self, key: &str, limit: &Limit, now: Duration) -> Decision;
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Bucket {
+        tokens: f64,
+        updated: f64,
+    },
+    Window {
+        start: f64,
+        previous: u32,
+        current: u32,
+    },
+}
+
+/// State of a key and the time at which it is back to its initial value.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    state: State,
+    expires: f64,
+}
+
+/// In-process [`RateLimitBackend`].
+///
+/// Every [`MemoryBackend::PURGE_INTERVAL`] hits, the keys whose quota is
+/// fully available again are forgotten, so memory is bounded by the keys
+/// active within their window.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    states: Mutex<HashMap<String, Entry>>,
+    hits: AtomicU64,
+}
+
+impl MemoryBackend {
+    /// Number of hits between two automatic purges of the expired keys.
+    pub const PURGE_INTERVAL: u64 = 1024;
+
+    /// Create an empty backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of tracked keys.
+    pub fn len(&self) -> usize {
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Returns `true` if no key is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget keys without activity for `idle`.
+    pub fn purge(&self, idle: Duration, now: Duration) {
+        let threshold = now.as_secs_f64() - idle.as_secs_f64();
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, entry| match entry.state {
+                State::Bucket { updated, .. } => updated >= threshold,
+                State::Window { start, .. } => start >= threshold,
+            });
+    }
+
+    fn token_bucket(state: &mut State, capacity: u32, refill: f64, now: f64) -> Decision {
+        let capacity = capacity as f64;
+        let refill = refill.max(f64::EPSILON);
+        let (tokens, updated) = match state {
+            State::Bucket { tokens, updated } => (*tokens, *updated),
+            _ => (capacity, now),
+        };
+        let mut tokens = (tokens + (now - updated).max(0.0) * refill).min(capacity);
+        let allowed = tokens >= 1.0;
+        if allowed {
+            tokens -= 1.0;
+        }
+        *state = State::Bucket {
+            tokens,
+            updated: now,
+        };
+        Decision {
+            allowed,
+            remaining: tokens.floor() as u32,
+            reset: Duration::from_secs_f64((capacity - tokens) / refill),
+            retry_after: (!allowed).then(|| Duration::from_secs_f64((1.0 - tokens) / refill)),
+        }
+    }
+
+    fn sliding_window(state: &mut State, limit: u32, window: Duration, now: f64) -> Decision {
+        let size = window.as_secs_f64().max(f64::EPSILON);
+        let start = (now / size).floor() * size;
+        let (previous, mut current) = match *state {
+            State::Window {
+                start: s,
+                previous,
+                current,
+            } => {
+                if s == start {
+                    (previous, current)
+                } else if (start - s - size).abs() < size / 2.0 {
+                    (current, 0)
+                } else {
+                    (0, 0)
+                }
+            }
+            _ => (0, 0),
+        };
+        let elapsed = now - start;
+        let weight = 1.0 - elapsed / size;
+        let estimated = previous as f64 * weight + current as f64;
+        let allowed = estimated + 1.0 <= limit as f64;
+        if allowed {
+            current += 1;
+        }
+        *state = State::Window {
+            start,
+            previous,
+            current,
+        };
+        let used = previous as f64 * weight + current as f64;
+        let retry_after = (!allowed).then(|| {
+            // Wait until the weighted count leaves room for one request.
+            let room = limit as f64 - 1.0 - current as f64;
+            let seconds = if room >= 0.0 && previous > 0 {
+                size * (1.0 - room / previous as f64) - elapsed
+            } else {
+                let next = current.max(1) as f64;
+                (size - elapsed) + size * (1.0 - (limit as f64 - 1.0).max(0.0) / next)
+            };
+            Duration::from_secs_f64(seconds.max(0.0))
+        });
+        Decision {
+            allowed,
+            remaining: (limit as f64 - used).max(0.0).floor() as u32,
+            reset: Duration::from_secs_f64(size - elapsed),
+            retry_after,
+        }
+    }
+}
+
+impl RateLimitBackend for MemoryBackend {
+    fn hit(&self, key: &str, limit: &Limit, now: Duration) -> Decision {
+        let now = now.as_secs_f64();
+        let mut states = self.states.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.hits.fetch_add(1, Ordering::Relaxed) % Self::PURGE_INTERVAL
+            == Self::PURGE_INTERVAL - 1
+        {
+            states.retain(|_, entry| entry.expires > now);
+        }
+        let entry = states.entry(key.to_string()).or_insert_with(|| Entry {
+            state: match limit {
+                Limit::TokenBucket { capacity, .. } => State::Bucket {
+                    tokens: *capacity as f64,
+                    updated: now,
+                },
+                Limit::SlidingWindow { .. } => State::Window {
+                    start: f64::NEG_INFINITY,
+                    previous: 0,
+                    current: 0,
+                },
+            },
+            expires: now,
+        });
+        let decision = match limit {
+            Limit::TokenBucket { capacity, refill } => {
+                Self::token_bucket(&mut entry.state, *capacity, *refill, now)
+            }
+            Limit::SlidingWindow { limit, window } => {
+                Self::sliding_window(&mut entry.state, *limit, *window, now)
+            }
+        };
+        // A bucket is full again once `reset` elapsed; a window forgets its
+        // counts after two windows.
+        entry.expires = match (entry.state, limit) {
+            (State::Window { start, .. }, Limit::SlidingWindow { window, .. }) => {
+                start + 2.0 * window.as_secs_f64()
+            }
+            _ => now + decision.reset.as_secs_f64(),
+        };
+        decision
+    }
+}
+
+type KeyFn = Arc<dyn Fn(&Request) -> String + Send + Sync>;
+
+/// How clients are told apart.
+#[derive(Clone)]
+pub enum KeyBy {
+    /// Client address from [`Request::REMOTE_ADDR_ATTRIBUTE`]. Requests
+    /// without it have no key.
+    Ip,
+    /// Identifier of the authenticated [`Principal`], falling back to the
+    /// client address for anonymous requests.
+    User,
+    /// Request path: all clients share the quota of a route.
+    Route,
+    /// Value of a request header, e.g. an API key or `X-Forwarded-For` set
+    /// by a trusted proxy.
+    Header(String),
+    /// Custom key.
+    Custom(KeyFn),
+}
+
+impl Debug for KeyBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyBy::Ip => f.write_str("Ip"),
+            KeyBy::User => f.write_str("User"),
+            KeyBy::Route => f.write_str("Route"),
+            KeyBy::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            KeyBy::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl KeyBy {
+    /// Key of `req`, or `None` when the client address is needed but
+    /// missing.
+    pub fn key(&self, req: &Request) -> Option<String> {
+        let ip = || match req.attribute(Request::REMOTE_ADDR_ATTRIBUTE) {
+            Some(Value::String(addr)) => Some(format!("ip:{}", addr)),
+            _ => None,
+        };
+        Some(match self {
+            KeyBy::Ip => ip()?,
+            KeyBy::User => match Principal::from_request(req) {
+                Some(principal) => format!("user:{}", principal.identifier),
+                None => ip()?,
+            },
+            KeyBy::Route => format!("route:{} {}", req.method, req.target.path),
+            KeyBy::Header(name) => {
+                format!("header:{}", req.get_header_line(name).unwrap_or_default())
+            }
+            KeyBy::Custom(f) => format!("custom:{}", f(req)),
+        })
+    }
+}
+
+/// Middleware throttling requests.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    name: String,
+    limit: Limit,
+    key: KeyBy,
+    backend: Option<String>,
+    fallback: Arc<MemoryBackend>,
+}
+
+impl RateLimiter {
+    /// Create a limiter; `name` separates the counters of different limiters
+    /// sharing a backend.
+    pub fn new(name: &str, limit: Limit, key: KeyBy) -> Self {
+        Self {
+            name: name.to_string(),
+            limit,
+            key,
+            backend: None,
+            fallback: Arc::new(MemoryBackend::new()),
+        }
+    }
+
+    /// Resolve the backend registered under `name` instead of the default one.
+    pub fn with_backend(mut self, name: &str) -> Self {
+        self.backend = Some(name.to_string());
+        self
+    }
+
+    /// Count `req` at time `now` and return the decision, or `None` when
+    /// `req` has no [key](KeyBy::key).
+    pub fn check(&self, container: &Container, req: &Request, now: Duration) -> Option<Decision> {
+        let key = format!("{}:{}", self.name, self.key.key(req)?);
+        let backend = match &self.backend {
+            Some(name) => container.resolve_named::<Box<dyn RateLimitBackend>>(name),
+            None => container.resolve::<Box<dyn RateLimitBackend>>(),
+        };
+        Some(match backend {
+            Some(backend) => backend.hit(&key, &self.limit, now),
+            None => self.fallback.hit(&key, &self.limit, now),
+        })
+    }
+
+    fn headers(&self, decision: &Decision) -> Headers {
+        let mut headers = Headers::new();
+        let seconds = |d: Duration| d.as_secs_f64().ceil() as u64;
+        headers.set("RateLimit-Limit", &[&self.limit.quota().to_string()]);
+        headers.set("RateLimit-Remaining", &[&decision.remaining.to_string()]);
+        headers.set("RateLimit-Reset", &[&seconds(decision.reset).to_string()]);
+        headers.set(
+            "RateLimit-Policy",
+            &[&format!(
+                "{};w={}",
+                self.limit.quota(),
+                self.limit.window_seconds()
+            )],
+        );
+        if let Some(retry) = decision.retry_after {
+            headers.set("Retry-After", &[&seconds(retry).max(1).to_string()]);
+        }
+        headers
+    }
+}
+
+impl Middleware<Container, Request, Response> for RateLimiter {
+    fn handle(
+        &mut self,
+        context: &Container,
+        req: &mut Request,
+        next: &mut dyn Controller<Container, Request, Response>,
+    ) -> Response {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // Counting clients without an address together would let one of
+        // them throttle all the others.
+        let Some(decision) = self.check(context, req, now) else {
+            return ResponseFactory::version(req.protocol_version())
+                .with_status(Status::InternalServerError, Headers::new());
+        };
+        let headers = self.headers(&decision);
+        if !decision.allowed {
+            return ResponseFactory::version(req.protocol_version())
+                .with_status(Status::TooManyRequests, headers);
+        }
+        let mut res = next.handle(context, req);
+        for (name, values) in headers.iter() {
+            res.headers_mut().insert(name, values);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concepts::Parsable;
+    use crate::http::{RequestFactory, ResponseTrait, Uri, Version};
+
+    fn at(seconds: f64) -> Duration {
+        Duration::from_secs_f64(1_000_000.0 + seconds)
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let backend = MemoryBackend::new();
+        let limit = Limit::token_bucket(3, Duration::from_secs(3));
+        for remaining in [2, 1, 0] {
+            let d = backend.hit("k", &limit, at(0.0));
+            assert!(d.allowed);
+            assert_eq!(d.remaining, remaining);
+        }
+        let denied = backend.hit("k", &limit, at(0.5));
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after, Some(Duration::from_secs_f64(0.5)));
+        assert!(backend.hit("k", &limit, at(1.0)).allowed);
+        assert!(!backend.hit("k", &limit, at(1.0)).allowed);
+        // another key has its own bucket
+        assert!(backend.hit("other", &limit, at(1.0)).allowed);
+        assert_eq!(backend.len(), 2);
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let backend = MemoryBackend::new();
+        let limit = Limit::sliding_window(4, Duration::from_secs(10));
+        for _ in 0..4 {
+            assert!(backend.hit("k", &limit, at(5.0)).allowed);
+        }
+        let denied = backend.hit("k", &limit, at(6.0));
+        assert!(!denied.allowed);
+        assert_eq!(denied.remaining, 0);
+        // 4 requests in the previous window weigh 4 * 0.75 = 3 at 12.5s
+        let d = backend.hit("k", &limit, at(12.5));
+        assert!(d.allowed);
+        assert_eq!(d.remaining, 0);
+        assert!(!backend.hit("k", &limit, at(12.5)).allowed);
+        // far later, the history is forgotten
+        let d = backend.hit("k", &limit, at(100.0));
+        assert!(d.allowed);
+        assert_eq!(d.remaining, 3);
+    }
+
+    #[test]
+    fn test_retry_after_allows_request() {
+        let backend = MemoryBackend::new();
+        let limit = Limit::sliding_window(2, Duration::from_secs(10));
+        backend.hit("k", &limit, at(8.0));
+        backend.hit("k", &limit, at(9.0));
+        let denied = backend.hit("k", &limit, at(9.5));
+        let retry = denied.retry_after.unwrap().as_secs_f64();
+        assert!(!backend.hit("k", &limit, at(9.5 + retry - 0.1)).allowed);
+        assert!(backend.hit("k", &limit, at(9.5 + retry + 0.01)).allowed);
+    }
+
+    #[test]
+    fn test_purge() {
+        let backend = MemoryBackend::new();
+        let limit = Limit::token_bucket(1, Duration::from_secs(1));
+        backend.hit("a", &limit, at(0.0));
+        backend.hit("b", &limit, at(50.0));
+        backend.purge(Duration::from_secs(30), at(60.0));
+        assert_eq!(backend.len(), 1);
+    }
+
+    #[test]
+    fn test_automatic_purge() {
+        let backend = MemoryBackend::new();
+        let bucket = Limit::token_bucket(2, Duration::from_secs(2));
+        let window = Limit::sliding_window(2, Duration::from_secs(10));
+        backend.hit("bucket", &bucket, at(0.0));
+        backend.hit("window", &window, at(0.0));
+        for i in 2..MemoryBackend::PURGE_INTERVAL - 1 {
+            backend.hit(&format!("k{}", i), &bucket, at(0.0));
+        }
+        assert_eq!(backend.len() as u64, MemoryBackend::PURGE_INTERVAL - 1);
+        // The bucket is full again after 1s, the window is kept for 20s.
+        backend.hit("late", &bucket, at(15.0));
+        assert_eq!(backend.len(), 2);
+        assert!(backend.hit("window", &window, at(15.0)).allowed);
+    }
+
+    #[test]
+    fn test_keys() {
+        let req = RequestFactory::version(Version::Http1_1)
+            .get(Uri::parse("/a").unwrap().1, Headers::new())
+            .with_attribute(
+                Request::REMOTE_ADDR_ATTRIBUTE,
+                Value::String("1.2.3.4".into()),
+            );
+        let key = |key: KeyBy, req: &Request| key.key(req).unwrap_or_default();
+        assert_eq!(key(KeyBy::Ip, &req), "ip:1.2.3.4");
+        assert_eq!(key(KeyBy::User, &req), "ip:1.2.3.4");
+        assert_eq!(key(KeyBy::Route, &req), "route:GET /a");
+        let req = req.with_attribute(Principal::ATTRIBUTE, Principal::new("alice").to_value());
+        assert_eq!(key(KeyBy::User, &req), "user:alice");
+        let custom = KeyBy::Custom(Arc::new(|_: &Request| "x".to_string()));
+        assert_eq!(key(custom, &req), "custom:x");
+
+        let anonymous =
+            RequestFactory::version(Version::Http1_1).get(Uri::default(), Headers::new());
+        assert_eq!(KeyBy::Ip.key(&anonymous), None);
+        assert_eq!(KeyBy::User.key(&anonymous), None);
+    }
+
+    #[test]
+    fn test_middleware_headers() {
+        let container = Container::new();
+        let mut limiter = RateLimiter::new(
+            "api",
+            Limit::token_bucket(1, Duration::from_secs(60)),
+            KeyBy::Ip,
+        );
+        let mut next = |_: &Container, _: &mut Request| {
+            ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+        };
+        let mut req = RequestFactory::version(Version::Http1_1).get(Uri::default(), Headers::new());
+        // Without the client address, the request cannot be counted.
+        let res = limiter.handle(&container, &mut req, &mut next);
+        assert_eq!(res.status(), Status::InternalServerError);
+        let mut req = req.with_attribute(
+            Request::REMOTE_ADDR_ATTRIBUTE,
+            Value::String("1.2.3.4".into()),
+        );
+        let res = limiter.handle(&container, &mut req, &mut next);
+        assert_eq!(res.status(), Status::NoContent);
+        assert_eq!(
+            res.get_header_line("RateLimit-Limit"),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("RateLimit-Remaining"),
+            Some("0".to_string())
+        );
+        assert_eq!(
+            res.get_header_line("RateLimit-Policy"),
+            Some("1;w=60".to_string())
+        );
+        let res = limiter.handle(&container, &mut req, &mut next);
+        assert_eq!(res.status(), Status::TooManyRequests);
+        assert_eq!(res.get_header_line("Retry-After"), Some("60".to_string()));
+    }
+}
@@ -1,6 +1,5 @@
 use crate::concepts::value::json::JsonFormatter;
 use crate::concepts::value::{Value, ValueFormatter};
-use crate::concepts::Parsable;
 use crate::http::{Headers, Method, Request, RequestFactory, Response, Uri, Version};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
@@ -41,10 +40,8 @@ impl Client {
         let mut buf = Vec::new();
         self.stream.read_to_end(&mut buf).await?;
         let text = String::from_utf8_lossy(&buf);
-        let (_, response) = Response::parse(&text).map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid response")
-        })?;
-        Ok(response)
+        text.parse::<Response>()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
     }
 
     /// Send a [`Request`] over the wire and return the parsed [`Response`].
@@ -60,10 +57,11 @@ impl Client {
         headers: Headers,
         body: &str,
     ) -> std::io::Result<Response> {
-        let (_, uri) = Uri::parse(url)
-            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid url"))?;
+        let uri = url
+            .parse::<Uri>()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
         let mut headers = headers;
-        headers.insert("Host", &[uri.authority.host.clone()]);
+        headers.insert("Host", std::slice::from_ref(&uri.authority.host));
         let factory = RequestFactory::version(Version::Http1_1);
         let request = factory.build(method, uri, headers, body);
         let host = request.target.authority.host.clone();
@@ -1,25 +1,98 @@
-use crate::concepts::Parsable;
-use crate::http::{Headers, Request, ResponseFactory, Status, Version};
+use crate::concepts::lifecycle::{BoxFuture, Service, Shutdown};
+use crate::concepts::value::Value;
+use crate::http::{Headers, MessageTrait, ParseError, Request, ResponseFactory, Status, Version};
+use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio::time::{timeout_at, Instant};
+
+/// Pause before accepting connections again after a failure, such as
+/// running out of file descriptors.
+pub const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Bounds applied by the [`Server`] while reading requests.
+///
+/// Requests exceeding them are answered with `414 URI Too Long` (request
+/// line), `431 Request Header Fields Too Large` (headers) or
+/// `413 Request Entity Too Large` (body). Clients too slow to send the
+/// headers or the body receive `408 Request Timeout`. Malformed requests are
+/// answered with the [`ParseError::status`] of their parse error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum length of the request line, in bytes.
+    pub max_request_line: usize,
+    /// Maximum number of header lines.
+    pub max_header_count: usize,
+    /// Maximum length of a single header line, in bytes.
+    pub max_header_size: usize,
+    /// Maximum length of all the header lines, in bytes.
+    pub max_headers_size: usize,
+    /// Maximum body length, in bytes.
+    pub max_body_size: usize,
+    /// Time allowed to receive the request line and the headers.
+    pub header_timeout: Duration,
+    /// Time allowed to receive the body once the headers are read.
+    pub body_timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_request_line: 8 * 1024,
+            max_header_count: 100,
+            max_header_size: 8 * 1024,
+            max_headers_size: 64 * 1024,
+            max_body_size: 1024 * 1024,
+            header_timeout: Duration::from_secs(10),
+            body_timeout: Duration::from_secs(30),
+        }
+    }
+}
 
 /// Simple asynchronous TCP server handling HTTP requests.
 ///
+/// The server reads and validates requests against its [`Limits`] and
+/// answers `204 No Content`; it does not dispatch them to a router. The
+/// address of the client is stored in the
+/// [`Request::REMOTE_ADDR_ATTRIBUTE`] of the requests it reads.
+///
+/// The server is a [`Service`]: added to a
+/// [`Runtime`](crate::concepts::lifecycle::Runtime), it binds its address
+/// when started and, on shutdown, stops accepting connections and drains the
+/// requests in progress until the deadline. A clone of a started server is
+/// not bound.
+///
 /// # Examples
 ///
 /// ```no_run
-/// use hermes::http::services::server::Server;
+/// use hermes::http::services::server::{Limits, Server};
 ///
 /// # tokio_test::block_on(async {
-/// let server = Server::new("127.0.0.1:8080");
+/// let server = Server::new("127.0.0.1:8080").with_limits(Limits {
+///     max_body_size: 64 * 1024,
+///     ..Limits::default()
+/// });
 /// // This will block forever handling incoming connections
 /// // and therefore is marked as `no_run` in the documentation.
 /// // server.run().await.unwrap();
 /// # })
 /// ```
-#[derive(Clone)]
 pub struct Server {
     address: String,
+    limits: Limits,
+    listener: Option<TcpListener>,
+}
+
+impl Clone for Server {
+    fn clone(&self) -> Self {
+        Self {
+            address: self.address.clone(),
+            limits: self.limits.clone(),
+            listener: None,
+        }
+    }
 }
 
 impl Server {
@@ -27,33 +100,321 @@ impl Server {
     pub fn new(address: &str) -> Self {
         Self {
             address: address.to_string(),
+            limits: Limits::default(),
+            listener: None,
         }
     }
 
+    /// Replace the default [`Limits`].
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Limits applied to incoming requests.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Address the server is bound to, once started as a [`Service`].
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.as_ref()?.local_addr().ok()
+    }
+
     /// Start listening for connections and process them concurrently.
     pub async fn run(&self) -> std::io::Result<()> {
+        self.run_until(Shutdown::never()).await
+    }
+
+    /// Like [`run`](Server::run), until `shutdown` is triggered.
+    pub async fn run_until(&self, shutdown: Shutdown) -> std::io::Result<()> {
         let listener = TcpListener::bind(&self.address).await?;
-        loop {
-            let (stream, _) = listener.accept().await?;
-            let this = self.clone();
-            tokio::spawn(async move {
-                let _ = this.handle_connection(stream).await;
-            });
-        }
+        self.serve(listener, shutdown).await
     }
 
-    async fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
-        let mut buf = Vec::new();
-        stream.read_to_end(&mut buf).await?;
-        let request = String::from_utf8_lossy(&buf);
-        let _ = Request::parse(&request);
+    /// Process the connections accepted by `listener` concurrently until
+    /// `shutdown` is triggered.
+    ///
+    /// Failing to accept a connection does not stop the server: errors
+    /// other than a connection aborted by the client are reported on the
+    /// standard error and accepting is retried after [`ACCEPT_BACKOFF`].
+    ///
+    /// The listener is then closed and the connections in progress are
+    /// given until the [`Shutdown::deadline`] to complete; the remaining
+    /// ones are dropped.
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+        mut shutdown: Shutdown,
+    ) -> std::io::Result<()> {
+        let mut connections = JoinSet::new();
+        let deadline = loop {
+            tokio::select! {
+                deadline = shutdown.wait() => break deadline,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let this = self.clone();
+                        connections.spawn(async move {
+                            let _ = this.handle_connection(stream, peer).await;
+                        });
+                    }
+                    Err(error) if connection_error(&error) => {}
+                    Err(error) => {
+                        // Out of file descriptors or memory: wait for the
+                        // connections in progress to release some.
+                        eprintln!("hermes: cannot accept connections: {}", error);
+                        tokio::select! {
+                            deadline = shutdown.wait() => break deadline,
+                            _ = tokio::time::sleep(ACCEPT_BACKOFF) => {}
+                        }
+                    }
+                },
+                // Reap the finished connections.
+                Some(_) = connections.join_next() => {}
+            }
+        };
+        drop(listener);
+        while let Ok(Some(_)) = timeout_at(deadline, connections.join_next()).await {}
+        connections.shutdown().await;
+        Ok(())
+    }
+
+    async fn handle_connection(
+        &self,
+        mut stream: TcpStream,
+        peer: SocketAddr,
+    ) -> std::io::Result<()> {
+        let status = match self.read_request(&mut stream, peer).await {
+            Ok(_) => Status::NoContent,
+            Err(status) => status,
+        };
 
         let factory = ResponseFactory::version(Version::Http1_1);
         let mut headers = Headers::new();
         headers.insert("Content-Length", &["0".to_string()]);
-        let response = factory.with_status(Status::NoContent, headers);
+        if status != Status::NoContent {
+            headers.insert("Connection", &["close".to_string()]);
+        }
+        let response = factory.with_status(status, headers);
         stream.write_all(response.to_string().as_bytes()).await?;
         stream.shutdown().await?;
         Ok(())
     }
+
+    /// Read one request sent by `peer` on `stream`, enforcing the
+    /// [`Limits`]. The address of `peer` is stored in the
+    /// [`Request::REMOTE_ADDR_ATTRIBUTE`].
+    ///
+    /// Returns the status to answer with when the request is rejected.
+    async fn read_request(
+        &self,
+        stream: &mut TcpStream,
+        peer: SocketAddr,
+    ) -> Result<Request, Status> {
+        let limits = &self.limits;
+        let deadline = Instant::now() + limits.header_timeout;
+        let mut buf = Vec::new();
+        let head_end = loop {
+            if let Some(end) = find(&buf, b"\r\n\r\n") {
+                check_head(&buf[..end], limits).map_err(|e| e.status())?;
+                break end + 4;
+            }
+            check_head(&buf, limits).map_err(|e| e.status())?;
+            if read_some(stream, &mut buf, deadline).await? == 0 {
+                // Peer closed before finishing the headers.
+                return Err(Status::BadRequest);
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+        let request =
+            Request::parse_bounded(&head, limits.max_header_count, limits.max_headers_size)
+                .map_err(|e| e.status())?;
+        if request.has_header("Transfer-Encoding") {
+            return Err(Status::NotImplemented);
+        }
+        let length = match request.get_header_line("Content-Length") {
+            Some(value) => value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| Status::BadRequest)?,
+            None => 0,
+        };
+        if length > limits.max_body_size {
+            return Err(Status::RequestEntityTooLarge);
+        }
+
+        let deadline = Instant::now() + limits.body_timeout;
+        let mut body = buf.split_off(head_end);
+        while body.len() < length {
+            if read_some(stream, &mut body, deadline).await? == 0 {
+                return Err(Status::BadRequest);
+            }
+        }
+        body.truncate(length);
+        Ok(request.with_body_bytes(body).with_attribute(
+            Request::REMOTE_ADDR_ATTRIBUTE,
+            Value::String(peer.ip().to_string()),
+        ))
+    }
+}
+
+impl Service for Server {
+    fn name(&self) -> &str {
+        &self.address
+    }
+
+    fn start(&mut self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            let listener = TcpListener::bind(&self.address)
+                .await
+                .map_err(|e| e.to_string())?;
+            self.listener = Some(listener);
+            Ok(())
+        })
+    }
+
+    fn run(&mut self, shutdown: Shutdown) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            let listener = self.listener.take().ok_or("server not started")?;
+            self.serve(listener, shutdown)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// Read available bytes into `buf` before `deadline`.
+async fn read_some(
+    stream: &mut TcpStream,
+    buf: &mut Vec<u8>,
+    deadline: Instant,
+) -> Result<usize, Status> {
+    let mut chunk = [0u8; 4096];
+    match timeout_at(deadline, stream.read(&mut chunk)).await {
+        Ok(Ok(n)) => {
+            buf.extend_from_slice(&chunk[..n]);
+            Ok(n)
+        }
+        Ok(Err(_)) => Err(Status::BadRequest),
+        Err(_) => Err(Status::RequestTimeout),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Check the (possibly incomplete) request head against `limits`.
+fn check_head(head: &[u8], limits: &Limits) -> Result<(), ParseError> {
+    let mut lines = head.split(|b| *b == b'\n');
+    let request_line = lines.next().unwrap_or_default();
+    if request_line.len() > limits.max_request_line {
+        return Err(ParseError::RequestLineTooLong(limits.max_request_line));
+    }
+    if head.len() - request_line.len() > limits.max_headers_size {
+        return Err(ParseError::HeadersTooLarge(limits.max_headers_size));
+    }
+    let mut count = 0;
+    for line in lines {
+        if line.len() > limits.max_header_size {
+            return Err(ParseError::HeadersTooLarge(limits.max_header_size));
+        }
+        if !line.is_empty() && line != b"\r" {
+            count += 1;
+        }
+    }
+    if count > limits.max_header_count {
+        return Err(ParseError::TooManyHeaders(limits.max_header_count));
+    }
+    Ok(())
+}
+
+/// Whether `error` only concerns the connection being accepted.
+fn connection_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::Interrupted
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_errors() {
+        use std::io::{Error, ErrorKind};
+
+        assert!(connection_error(&Error::from(ErrorKind::ConnectionAborted)));
+        // EMFILE, running out of file descriptors.
+        assert!(!connection_error(&Error::from_raw_os_error(24)));
+    }
+
+    #[tokio::test]
+    async fn test_binary_body_is_kept() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut stream, peer) = listener.accept().await.unwrap();
+        client
+            .write_all(b"POST / HTTP/1.1\r\ncontent-length: 4\r\n\r\n\x89PNG")
+            .await
+            .unwrap();
+        let request = Server::new("")
+            .read_request(&mut stream, peer)
+            .await
+            .unwrap();
+        assert_eq!(request.body_bytes(), b"\x89PNG");
+        assert_eq!(request.raw_body.as_deref(), Some(&b"\x89PNG"[..]));
+    }
+
+    /// Clients connecting from different addresses get separate buckets.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_remote_addr_keys_rate_limits() {
+        use crate::container::Container;
+        use crate::http::routing::controller::Middleware;
+        use crate::http::security::rate_limit::{KeyBy, Limit, RateLimiter};
+        use crate::http::{Response, ResponseTrait};
+        use tokio::net::TcpSocket;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Server::new("");
+        let mut limiter = RateLimiter::new(
+            "api",
+            Limit::token_bucket(1, Duration::from_secs(60)),
+            KeyBy::Ip,
+        );
+        let mut next = |_: &Container, _: &mut Request| -> Response {
+            ResponseFactory::version(Version::Http1_1).no_content(Headers::new())
+        };
+        let mut statuses = Vec::new();
+        for client in ["127.0.0.2", "127.0.0.3", "127.0.0.2"] {
+            let socket = TcpSocket::new_v4().unwrap();
+            socket
+                .bind(format!("{}:0", client).parse().unwrap())
+                .unwrap();
+            let mut client = socket.connect(address).await.unwrap();
+            client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+            let (mut stream, peer) = listener.accept().await.unwrap();
+            let mut req = server.read_request(&mut stream, peer).await.unwrap();
+            let res = limiter.handle(&Container::new(), &mut req, &mut next);
+            statuses.push(res.status());
+        }
+        assert_eq!(
+            statuses,
+            [
+                Status::NoContent,
+                Status::NoContent,
+                Status::TooManyRequests
+            ]
+        );
+    }
 }
@@ -5,21 +5,22 @@
 //! as the default backend. Developers can implement [`SessionStore`] for their
 //! own storage solutions such as databases or key-value stores.
 
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
 
 use crate::concepts::value::json::JsonFormatter;
-use crate::concepts::value::{Value, ValueFormatter};
+use crate::concepts::value::{from_value, to_value, ConvertError, Value, ValueFormatter};
+use crate::concepts::Dictionary;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 /// Backend used to load and persist session data.
 pub trait SessionStore {
     /// Load all key/value pairs associated with `id`.
-    fn load(&self, id: &str) -> HashMap<String, Value>;
+    fn load(&self, id: &str) -> Dictionary<Value>;
     /// Persist all key/value pairs for `id`.
-    fn save(&self, id: &str, data: &HashMap<String, Value>);
+    fn save(&self, id: &str, data: &Dictionary<Value>);
     /// Remove all data associated with `id`.
     fn delete(&self, id: &str);
 }
@@ -42,6 +43,23 @@ pub fn generate_id() -> String {
 
 #[derive(Clone)]
 /// File-based session store saving each session in a separate file.
+///
+/// The data of a session is written as a single dictionary with the
+/// formatter, which may be a binary one. Files holding one `key=value` line
+/// per entry, as written by previous versions, are still loaded.
+///
+///
+/// ```
+/// use hermes::concepts::value::msgpack::MsgPackFormatter;
+/// use hermes::concepts::value::Value;
+/// use hermes::http::session::{FileStore, SessionStore};
+///
+/// let dir = std::env::temp_dir().join("hermes_session_msgpack_doc");
+/// let store = FileStore::with_formatter(&dir, MsgPackFormatter);
+/// store.save("doc", &[("n".to_string(), Value::Int(1))].into());
+/// assert_eq!(std::fs::read(dir.join("doc")).unwrap(), [0x81, 0xa1, b'n', 0x01]);
+/// assert_eq!(store.load("doc")["n"], Value::Int(1));
+/// ```
 pub struct FileStore<F: ValueFormatter + Clone = JsonFormatter> {
     root: PathBuf,
     formatter: F,
@@ -58,6 +76,17 @@ impl<F: ValueFormatter + Clone> FileStore<F> {
     fn path(&self, id: &str) -> PathBuf {
         self.root.join(id)
     }
+
+    /// Read the format written before 0.1.3: one `key=value` line per
+    /// entry, each value formatted on its own.
+    fn load_lines(&self, text: &str) -> Dictionary<Value> {
+        text.lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                Some((key.to_string(), self.formatter.parse(value)?))
+            })
+            .collect()
+    }
 }
 
 impl FileStore<JsonFormatter> {
@@ -68,31 +97,17 @@ impl FileStore<JsonFormatter> {
 }
 
 impl<F: ValueFormatter + Clone> SessionStore for FileStore<F> {
-    fn load(&self, id: &str) -> HashMap<String, Value> {
-        let path = self.path(id);
-        let mut data = HashMap::new();
-        if let Ok(mut f) = File::open(path) {
-            let mut buf = String::new();
-            if f.read_to_string(&mut buf).is_ok() {
-                for line in buf.lines() {
-                    if let Some((k, v)) = line.split_once('=') {
-                        if let Some(val) = self.formatter.parse(v) {
-                            data.insert(k.to_string(), val);
-                        }
-                    }
-                }
-            }
+    fn load(&self, id: &str) -> Dictionary<Value> {
+        let bytes = fs::read(self.path(id)).unwrap_or_default();
+        match self.formatter.parse_bytes(&bytes) {
+            Some(Value::Dictionary(data)) => data,
+            _ => self.load_lines(&String::from_utf8_lossy(&bytes)),
         }
-        data
     }
 
-    fn save(&self, id: &str, data: &HashMap<String, Value>) {
-        let path = self.path(id);
-        if let Ok(mut f) = File::create(path) {
-            for (k, v) in data {
-                let _ = writeln!(f, "{}={}", k, self.formatter.format(v.clone()));
-            }
-        }
+    fn save(&self, id: &str, data: &Dictionary<Value>) {
+        let bytes = self.formatter.format_bytes(Value::Dictionary(data.clone()));
+        let _ = fs::write(self.path(id), bytes);
     }
 
     fn delete(&self, id: &str) {
@@ -104,11 +119,14 @@ impl<F: ValueFormatter + Clone> SessionStore for FileStore<F> {
 /// In-memory representation of a session loaded from a store.
 pub struct Session<S: SessionStore + Clone> {
     id: String,
-    data: HashMap<String, Value>,
+    data: Dictionary<Value>,
     store: S,
 }
 
 impl<S: SessionStore + Clone> Session<S> {
+    /// Session key holding the flash values.
+    pub const FLASH_KEY: &'static str = "_flash";
+
     /// Create a session with the given identifier backed by `store`.
     pub fn new(id: impl Into<String>, store: S) -> Self {
         let id = id.into();
@@ -126,9 +144,78 @@ impl<S: SessionStore + Clone> Session<S> {
         self.data.insert(key.into(), value);
     }
 
+    /// Retrieve a value from the session, converted into `T`.
+    ///
+    /// Returns `None` if the key is missing or does not convert.
+    ///
+    /// # Examples
+    /// ```
+    /// use hermes::http::session::{FileStore, Session};
+    ///
+    /// let dir = std::env::temp_dir().join("hermes_session_typed_doc");
+    /// let mut session = Session::new("doc", FileStore::new(&dir));
+    /// session.insert_as("cart", &vec![3u32, 7]).unwrap();
+    /// assert_eq!(session.get_as::<Vec<u32>>("cart"), Some(vec![3, 7]));
+    /// assert_eq!(session.get_as::<String>("cart"), None);
+    /// ```
+    pub fn get_as<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.data
+            .get(key)
+            .and_then(|value| from_value(value.clone()).ok())
+    }
+
+    /// Insert or update a serializable value in the session.
+    pub fn insert_as<T: Serialize + ?Sized>(
+        &mut self,
+        key: impl Into<String>,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        self.insert(key, to_value(value)?);
+        Ok(())
+    }
+
     /// Remove a value from the session.
     pub fn remove(&mut self, key: &str) {
-        self.data.remove(key);
+        self.data.shift_remove(key);
+    }
+
+    /// Store a flash `value` under `key`, readable once with
+    /// [`Session::take_flash`], typically on the next request.
+    ///
+    /// # Examples
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::session::{FileStore, Session};
+    ///
+    /// let dir = std::env::temp_dir().join("hermes_flash_doc");
+    /// let mut session = Session::new("doc", FileStore::new(&dir));
+    /// session.flash("notice", Value::String("Saved".into()));
+    /// assert_eq!(session.take_flash("notice"), Some(Value::String("Saved".into())));
+    /// assert_eq!(session.take_flash("notice"), None);
+    /// ```
+    pub fn flash(&mut self, key: impl Into<String>, value: Value) {
+        let flashes = self
+            .data
+            .entry(Self::FLASH_KEY.to_string())
+            .or_insert_with(|| Value::Dictionary(Dictionary::new()));
+        if !matches!(flashes, Value::Dictionary(_)) {
+            *flashes = Value::Dictionary(Dictionary::new());
+        }
+        if let Value::Dictionary(flashes) = flashes {
+            flashes.insert(key.into(), value);
+        }
+    }
+
+    /// Remove and return the flash value stored under `key`.
+    pub fn take_flash(&mut self, key: &str) -> Option<Value> {
+        let Some(Value::Dictionary(flashes)) = self.data.get_mut(Self::FLASH_KEY) else {
+            return None;
+        };
+        let value = flashes.shift_remove(key);
+        if flashes.is_empty() {
+            self.data.shift_remove(Self::FLASH_KEY);
+        }
+        value
     }
 
     /// Persist the current session state using the underlying store.
@@ -152,7 +239,7 @@ mod tests {
     fn file_store_round_trip() {
         let dir = env::temp_dir().join("hermes_session_test");
         let store = FileStore::new(&dir);
-        let mut data = HashMap::new();
+        let mut data = Dictionary::new();
         data.insert("foo".to_string(), Value::String("bar".to_string()));
         store.save("s1", &data);
         let loaded = store.load("s1");
@@ -177,6 +264,45 @@ mod tests {
         fs::remove_dir_all(&dir).unwrap();
     }
 
+    #[test]
+    fn file_store_loads_lines() {
+        let dir = env::temp_dir().join("hermes_session_lines");
+        let store = FileStore::new(&dir);
+        fs::write(dir.join("s4"), "user={\"id\": 1}\nname=\"Ann\"\n").unwrap();
+        let loaded = store.load("s4");
+        assert_eq!(loaded.keys().collect::<Vec<_>>(), ["user", "name"]);
+        assert_eq!(loaded["name"], Value::String("Ann".to_string()));
+        store.save("s4", &loaded);
+        assert_eq!(store.load("s4"), loaded);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_keeps_key_order() {
+        let dir = env::temp_dir().join("hermes_session_order");
+        let store = FileStore::new(&dir);
+        let mut sess = Session::new("s3", store.clone());
+        let mut nested = Dictionary::new();
+        nested.insert("z".to_string(), Value::Int(1));
+        nested.insert("a".to_string(), Value::Int(2));
+        sess.insert("user", Value::Dictionary(nested));
+        sess.insert("cart", Value::String("line\nbreak".to_string()));
+        sess.persist();
+
+        let loaded = store.load("s3");
+        assert_eq!(loaded.keys().collect::<Vec<_>>(), ["user", "cart"]);
+        let Some(Value::Dictionary(user)) = loaded.get("user") else {
+            unreachable!()
+        };
+        assert_eq!(user.keys().collect::<Vec<_>>(), ["z", "a"]);
+        assert_eq!(
+            loaded.get("cart"),
+            Some(&Value::String("line\nbreak".to_string()))
+        );
+        store.delete("s3");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn generate_id_creates_unique_strings() {
         let a = generate_id();
@@ -0,0 +1,698 @@
+//! Declarative validation of form data.
+//!
+//! A [`Validator`] lists the [`Rule`]s of each field and checks a
+//! [`Value::Dictionary`], reporting failures as [`FieldErrors`] keyed by
+//! field name. Nested fields are addressed with dots (`address.city`,
+//! `tags.0`). Apart from [`Rule::required`] and [`Rule::equals`], rules
+//! ignore missing or empty values. [`Validator::validate_as`] also converts the data into a typed
+//! structure with [`FromValue`].
+//!
+//! [`FormState`] keeps the submitted values and the errors to re-render a
+//! form, and travels to the next request as a session flash value. CSRF
+//! tokens are checked with [`Csrf`](crate::http::security::csrf::Csrf),
+//! whose [`rule`](crate::http::security::csrf::Csrf::rule) plugs into a
+//! validator.
+//!
+//! JSON bodies are validated against a JSON
+//! [`Schema`](crate::concepts::value::schema::Schema) by the
+//! [`JsonValidator`] route middleware.
+//!
+//! # Example
+//!
+//! ```
+//! use hermes::concepts::value::Value;
+//! use hermes::http::validation::{Rule, Validator};
+//!
+//! let validator = Validator::new()
+//!     .field("name", [Rule::required(), Rule::length(2, 20)])
+//!     .field("email", [Rule::required(), Rule::email()])
+//!     .field("age", [Rule::range(18.0, 130.0)])
+//!     .field("password", [Rule::required(), Rule::length(8, 64)])
+//!     .field("confirm", [Rule::equals("password")]);
+//!
+//! let form = |pairs: &[(&str, &str)]| {
+//!     Value::Dictionary(
+//!         pairs
+//!             .iter()
+//!             .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+//!             .collect(),
+//!     )
+//! };
+//! let errors = validator
+//!     .validate(&form(&[
+//!         ("name", "A"),
+//!         ("email", "nope"),
+//!         ("age", "12"),
+//!         ("password", "correct horse"),
+//!         ("confirm", "incorrect horse"),
+//!     ]))
+//!     .unwrap_err();
+//! assert_eq!(errors.get("name"), ["must have between 2 and 20 characters"]);
+//! assert_eq!(errors.get("email"), ["must be a valid email address"]);
+//! assert_eq!(errors.get("age"), ["must be between 18 and 130"]);
+//! assert_eq!(errors.get("confirm"), ["must match password"]);
+//!
+//! assert!(validator
+//!     .validate(&form(&[
+//!         ("name", "Alice"),
+//!         ("email", "alice@example.com"),
+//!         ("password", "correct horse"),
+//!         ("confirm", "correct horse"),
+//!     ]))
+//!     .is_ok());
+//! ```
+
+use crate::concepts::value::schema::{Schema, Violation};
+use crate::concepts::value::Value;
+use crate::concepts::Dictionary;
+use crate::http::extract::{ExtractError, FieldErrors, FromValue};
+use crate::http::routing::controller::{Controller, Middleware};
+use crate::http::session::{Session, SessionStore};
+use crate::http::{MessageTrait, Request, Response, Status, Uri};
+use regex::Regex;
+use std::sync::Arc;
+
+type Check = dyn Fn(&Value, &Value) -> Result<(), String> + Send + Sync;
+
+/// A validation rule of a field.
+///
+/// Checks receive the value of the field and the whole data, and return the
+/// error message on failure.
+#[derive(Clone)]
+pub struct Rule {
+    check: Arc<Check>,
+    required: bool,
+    /// Missing or empty values are checked as [`Value::Null`].
+    check_empty: bool,
+    message: Option<String>,
+}
+
+impl Rule {
+    fn new(check: impl Fn(&Value, &Value) -> Result<(), String> + Send + Sync + 'static) -> Self {
+        Self {
+            check: Arc::new(check),
+            required: false,
+            check_empty: false,
+            message: None,
+        }
+    }
+
+    /// The field must be present and not empty.
+    pub fn required() -> Self {
+        Self {
+            required: true,
+            ..Self::new(|_, _| Ok(()))
+        }
+    }
+
+    /// Strings must have between `min` and `max` characters, arrays between
+    /// `min` and `max` items.
+    pub fn length(min: usize, max: usize) -> Self {
+        Self::new(move |value, _| {
+            let (length, unit) = match value {
+                Value::Array(items) => (items.len(), "items"),
+                value => (text(value).chars().count(), "characters"),
+            };
+            if (min..=max).contains(&length) {
+                Ok(())
+            } else {
+                Err(format!("must have between {} and {} {}", min, max, unit))
+            }
+        })
+    }
+
+    /// Numbers, or their textual form, must be between `min` and `max`.
+    pub fn range(min: f64, max: f64) -> Self {
+        Self::new(move |value, _| {
+            let number = match value {
+                Value::Int(i) => Some(*i as f64),
+                Value::Number(n) => Some(*n),
+                value => text(value).trim().parse::<f64>().ok(),
+            };
+            match number {
+                Some(n) if n.is_finite() && (min..=max).contains(&n) => Ok(()),
+                Some(n) if n.is_finite() => Err(format!("must be between {} and {}", min, max)),
+                _ => Err("must be a number".to_string()),
+            }
+        })
+    }
+
+    /// The textual value must match `pattern`.
+    ///
+    /// The pattern is not anchored: use `^` and `$` to match whole values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid regular expression.
+    pub fn regex(pattern: &str) -> Self {
+        let regex = Regex::new(pattern).expect("invalid validation pattern");
+        Self::new(move |value, _| match regex.is_match(&text(value)) {
+            true => Ok(()),
+            false => Err("has an invalid format".to_string()),
+        })
+    }
+
+    /// The value must look like an email address: `local@domain.tld`.
+    pub fn email() -> Self {
+        Self::new(|value, _| {
+            let value = text(value);
+            let valid = value.split_once('@').is_some_and(|(local, domain)| {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.contains('.')
+                    && !domain.starts_with('.')
+                    && !domain.ends_with('.')
+                    && !domain.contains("..")
+            }) && !value.chars().any(|c| c.is_whitespace() || c.is_control());
+            match valid {
+                true => Ok(()),
+                false => Err("must be a valid email address".to_string()),
+            }
+        })
+    }
+
+    /// The value must be an absolute `http` or `https` URL with a host.
+    pub fn url() -> Self {
+        Self::new(|value, _| {
+            let valid = text(value).parse::<Uri>().is_ok_and(|uri| {
+                matches!(uri.scheme.to_ascii_lowercase().as_str(), "http" | "https")
+                    && !uri.authority.host.is_empty()
+            });
+            match valid {
+                true => Ok(()),
+                false => Err("must be a valid URL".to_string()),
+            }
+        })
+    }
+
+    /// The value must equal the value of the `other` field.
+    ///
+    /// A missing or empty value only matches a missing or empty `other`
+    /// field.
+    pub fn equals(other: &str) -> Self {
+        let other = other.to_string();
+        Self {
+            check_empty: true,
+            ..Self::new(move |value, data| {
+                let value = Some(value).filter(|value| !is_empty(value));
+                match data.path(&other).filter(|expected| !is_empty(expected)) == value {
+                    true => Ok(()),
+                    false => Err(format!("must match {}", other)),
+                }
+            })
+        }
+    }
+
+    /// Check the value with `check`, returning the error message on failure.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::validation::{Rule, Validator};
+    ///
+    /// let validator = Validator::new().field(
+    ///     "username",
+    ///     [Rule::custom(|value| match value {
+    ///         Value::String(s) if s == "admin" => Err("is reserved".to_string()),
+    ///         _ => Ok(()),
+    ///     })],
+    /// );
+    /// let data = Value::Dictionary(
+    ///     [("username".to_string(), Value::String("admin".into()))].into(),
+    /// );
+    /// assert_eq!(validator.validate(&data).unwrap_err().get("username"), ["is reserved"]);
+    /// ```
+    pub fn custom(check: impl Fn(&Value) -> Result<(), String> + Send + Sync + 'static) -> Self {
+        Self::new(move |value, _| check(value))
+    }
+
+    /// Check the value with `check`, which also receives the whole data.
+    pub fn custom_with(
+        check: impl Fn(&Value, &Value) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(check)
+    }
+
+    /// Combine with `other`: the value must pass both rules.
+    pub fn and(self, other: Rule) -> Self {
+        let required = self.required || other.required;
+        let check_empty = self.check_empty || other.check_empty;
+        Self {
+            required,
+            check_empty,
+            ..Self::new(move |value, data| {
+                let value = Some(value).filter(|value| !is_empty(value));
+                self.check(value, data)?;
+                other.check(value, data)
+            })
+        }
+    }
+
+    /// Report `message` instead of the default message.
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Check `value`, which is `None` when missing or empty.
+    fn check(&self, value: Option<&Value>, data: &Value) -> Result<(), String> {
+        let result = match value {
+            None if self.required => Err("is required".to_string()),
+            None if self.check_empty => (self.check)(&Value::Null, data),
+            None => Ok(()),
+            Some(value) => (self.check)(value, data),
+        };
+        result.map_err(|message| self.message.clone().unwrap_or(message))
+    }
+}
+
+/// Text of a scalar value.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Int(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Returns `true` for values a form sends for an empty field.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.trim().is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Rules of the fields of a form.
+#[derive(Clone, Default)]
+pub struct Validator {
+    fields: Vec<(String, Vec<Rule>)>,
+}
+
+impl Validator {
+    /// Create a validator without rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `rules` to the (dotted) `field`.
+    pub fn field(mut self, field: &str, rules: impl IntoIterator<Item = Rule>) -> Self {
+        match self.fields.iter_mut().find(|(name, _)| name == field) {
+            Some((_, existing)) => existing.extend(rules),
+            None => self
+                .fields
+                .push((field.to_string(), rules.into_iter().collect())),
+        }
+        self
+    }
+
+    /// Check `data`, reporting every failing rule.
+    pub fn validate(&self, data: &Value) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        for (field, rules) in &self.fields {
+            let value = data.path(field).filter(|value| !is_empty(value));
+            for rule in rules {
+                if let Err(message) = rule.check(value, data) {
+                    errors.add(field, &message);
+                }
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Check `data`, then convert it into `T`.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::extract::{FieldErrors, Fields, FromValue};
+    /// use hermes::http::validation::{Rule, Validator};
+    ///
+    /// struct Signup {
+    ///     email: String,
+    ///     age: u8,
+    /// }
+    ///
+    /// impl FromValue for Signup {
+    ///     fn from_value(value: &Value) -> Result<Self, FieldErrors> {
+    ///         let mut fields = Fields::new(value)?;
+    ///         let signup = Signup {
+    ///             email: fields.required("email"),
+    ///             age: fields.required("age"),
+    ///         };
+    ///         fields.finish(signup)
+    ///     }
+    /// }
+    ///
+    /// let validator = Validator::new()
+    ///     .field("email", [Rule::required(), Rule::email()])
+    ///     .field("age", [Rule::required(), Rule::range(18.0, 130.0)]);
+    /// let data = Value::Dictionary(
+    ///     [
+    ///         ("email".to_string(), Value::String("bob@example.com".into())),
+    ///         ("age".to_string(), Value::String("42".into())),
+    ///     ]
+    ///     .into(),
+    /// );
+    /// let signup: Signup = validator.validate_as(&data).unwrap();
+    /// assert_eq!(signup.age, 42);
+    /// ```
+    pub fn validate_as<T: FromValue>(&self, data: &Value) -> Result<T, FieldErrors> {
+        self.validate(data)?;
+        T::from_value(data)
+    }
+}
+
+/// Submitted values and errors of a form, to re-render it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormState {
+    /// Submitted values.
+    pub values: Value,
+    /// Validation errors.
+    pub errors: FieldErrors,
+}
+
+impl FormState {
+    /// Session flash key of the form states.
+    const FLASH_KEY: &'static str = "form";
+
+    /// Create a state from the submitted `values` and their `errors`.
+    pub fn new(values: Value, errors: FieldErrors) -> Self {
+        Self { values, errors }
+    }
+
+    /// Submitted value of the (dotted) `field`, as text.
+    pub fn value(&self, field: &str) -> String {
+        self.values.path(field).map(text).unwrap_or_default()
+    }
+
+    /// Error messages of `field`.
+    pub fn errors(&self, field: &str) -> &[String] {
+        self.errors.get(field)
+    }
+
+    /// Returns `true` if `field` has errors.
+    pub fn has_error(&self, field: &str) -> bool {
+        !self.errors(field).is_empty()
+    }
+
+    /// Keep the state for the next request, typically before redirecting
+    /// back to the form. Fields listed in `omit` (passwords, CSRF tokens)
+    /// are not stored.
+    ///
+    /// ```
+    /// use hermes::concepts::value::Value;
+    /// use hermes::http::extract::FieldErrors;
+    /// use hermes::http::session::{FileStore, Session};
+    /// use hermes::http::validation::FormState;
+    ///
+    /// let dir = std::env::temp_dir().join("hermes_form_state_doc");
+    /// let mut session = Session::new("doc", FileStore::new(&dir));
+    /// let values = Value::Dictionary(
+    ///     [
+    ///         ("email".to_string(), Value::String("bob@".into())),
+    ///         ("password".to_string(), Value::String("secret".into())),
+    ///     ]
+    ///     .into(),
+    /// );
+    /// let mut errors = FieldErrors::new();
+    /// errors.add("email", "must be a valid email address");
+    /// FormState::new(values, errors).flash(&mut session, &["password"]);
+    ///
+    /// let state = FormState::take(&mut session).unwrap();
+    /// assert_eq!(state.value("email"), "bob@");
+    /// assert_eq!(state.value("password"), "");
+    /// assert!(state.has_error("email"));
+    /// assert!(FormState::take(&mut session).is_none());
+    /// ```
+    pub fn flash<S: SessionStore + Clone>(&self, session: &mut Session<S>, omit: &[&str]) {
+        let mut values = self.values.clone();
+        if let Value::Dictionary(entries) = &mut values {
+            for field in omit {
+                entries.shift_remove(*field);
+            }
+        }
+        let mut state = Dictionary::new();
+        state.insert("values".to_string(), values);
+        state.insert("errors".to_string(), self.errors.to_value());
+        session.flash(Self::FLASH_KEY, Value::Dictionary(state));
+    }
+
+    /// Take the state kept by [`FormState::flash`].
+    pub fn take<S: SessionStore + Clone>(session: &mut Session<S>) -> Option<Self> {
+        let Value::Dictionary(mut state) = session.take_flash(Self::FLASH_KEY)? else {
+            return None;
+        };
+        let values = state.shift_remove("values").unwrap_or(Value::Null);
+        let mut errors = FieldErrors::new();
+        if let Some(Value::Dictionary(fields)) = state.shift_remove("errors") {
+            for (field, messages) in fields {
+                if let Value::Array(messages) = messages {
+                    for message in messages {
+                        errors.add(&field, &text(&message));
+                    }
+                }
+            }
+        }
+        Some(Self { values, errors })
+    }
+}
+
+/// Schema violations, keyed by the JSON Pointer of the invalid values.
+impl From<Vec<Violation>> for FieldErrors {
+    fn from(violations: Vec<Violation>) -> Self {
+        let mut errors = FieldErrors::new();
+        for violation in violations {
+            errors.add(&violation.instance, &violation.message);
+        }
+        errors
+    }
+}
+
+/// Middleware validating JSON request bodies against a [`Schema`].
+///
+/// Requests with a safe method are passed on. The others must send a JSON
+/// body matching the schema, or are answered by
+/// [`ExtractError::to_response`]: `415 Unsupported Media Type` for another
+/// media type, `400 Bad Request` for invalid JSON and `422 Unprocessable
+/// Entity` for violations, listed by JSON Pointer in the `fields` member.
+///
+/// ```
+/// use hermes::concepts::value::schema::Schema;
+/// use hermes::concepts::value::yaml::YamlFormatter;
+/// use hermes::concepts::Parsable;
+/// use hermes::http::routing::router::{Route, Router};
+/// use hermes::http::validation::JsonValidator;
+/// use hermes::http::{Headers, MessageTrait, Method, Request, RequestFactory, Response, ResponseFactory, ResponseTrait, Uri, Version};
+///
+/// let schema = Schema::load(&YamlFormatter, "
+/// type: object
+/// required: [title]
+/// properties:
+///   title: {type: string, maxLength: 80}
+/// ").unwrap();
+/// let mut router: Router<()> = Router::new();
+/// router.add_route(
+///     Route::new("/posts", vec![Method::Post], Headers::new(), Box::new(
+///         |_: &(), _: &mut Request| -> Response {
+///             ResponseFactory::version(Version::Http1_1).ok(Headers::new(), "created".into())
+///         },
+///     ))
+///     .with_before(Box::new(JsonValidator::new(schema))),
+/// );
+///
+/// let factory = RequestFactory::version(Version::Http1_1);
+/// let mut headers = Headers::new();
+/// headers.set("Content-Type", &["application/json"]);
+/// let uri = Uri::parse("/posts").unwrap().1;
+/// let mut req = factory.post(uri.clone(), headers.clone(), r#"{"title": "Hello"}"#);
+/// assert_eq!(router.handle_request(&(), &mut req).unwrap().body(), "created");
+///
+/// let mut req = factory.post(uri, headers, r#"{"title": 1}"#);
+/// let resp = router.handle_request(&(), &mut req).unwrap();
+/// assert_eq!(resp.code(), 422);
+/// assert!(resp.body().contains(r#""fields": {"/title": ["must be of type string"]}"#));
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonValidator {
+    schema: Arc<Schema>,
+}
+
+impl JsonValidator {
+    /// Validate bodies against `schema`.
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema: Arc::new(schema),
+        }
+    }
+}
+
+impl<Ctx> Middleware<Ctx, Request, Response> for JsonValidator {
+    fn handle(
+        &mut self,
+        context: &Ctx,
+        req: &mut Request,
+        next: &mut dyn Controller<Ctx, Request, Response>,
+    ) -> Response {
+        if req.method.is_safe() {
+            return next.handle(context, req);
+        }
+        let result = req.json().and_then(|body| {
+            self.schema.validate(&body).map_err(|violations| {
+                ExtractError::Fields(Status::UnprocessableEntity, violations.into())
+            })
+        });
+        match result {
+            Ok(()) => next.handle(context, req),
+            Err(error) => error.to_response(req.protocol_version()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Value {
+        let mut address = Dictionary::new();
+        address.insert("city".to_string(), Value::String("Paris".into()));
+        let mut data = Dictionary::new();
+        data.insert("address".to_string(), Value::Dictionary(address));
+        data.insert(
+            "tags".to_string(),
+            Value::Array(vec![Value::String("a".into()), Value::String("b".into())]),
+        );
+        data.insert("count".to_string(), Value::Int(3));
+        data.insert("blank".to_string(), Value::String("  ".into()));
+        data.insert("site".to_string(), Value::String("https://x.org/a".into()));
+        Value::Dictionary(data)
+    }
+
+    #[test]
+    fn test_nested_fields_and_empty_values() {
+        let validator = Validator::new()
+            .field("address.city", [Rule::required(), Rule::length(1, 3)])
+            .field("tags", [Rule::length(1, 1)])
+            .field("tags.1", [Rule::regex("^[a-z]$")])
+            .field("count", [Rule::range(0.0, 2.0)])
+            .field("blank", [Rule::email(), Rule::url()])
+            .field("blank", [Rule::required().message("cannot be blank")])
+            .field("site", [Rule::url()])
+            .field("missing", [Rule::required()]);
+        let errors = validator.validate(&data()).unwrap_err();
+        assert_eq!(
+            errors.get("address.city"),
+            ["must have between 1 and 3 characters"]
+        );
+        assert_eq!(errors.get("tags"), ["must have between 1 and 1 items"]);
+        assert!(errors.get("tags.1").is_empty());
+        assert_eq!(errors.get("count"), ["must be between 0 and 2"]);
+        assert_eq!(errors.get("blank"), ["cannot be blank"]);
+        assert!(errors.get("site").is_empty());
+        assert_eq!(errors.get("missing"), ["is required"]);
+        assert_eq!(errors.len(), 5);
+    }
+
+    #[test]
+    fn test_formats() {
+        let check = |rule: Rule, value: &str| {
+            rule.check(Some(&Value::String(value.to_string())), &Value::Null)
+                .is_ok()
+        };
+        assert!(check(Rule::email(), "a.b+c@example.co.uk"));
+        for invalid in ["a@b", "@b.c", "a@@b.c", "a b@c.d", "a@.b", "a@b..c"] {
+            assert!(!check(Rule::email(), invalid), "{}", invalid);
+        }
+        assert!(check(Rule::url(), "http://localhost:8080/x?y#z"));
+        for invalid in ["ftp://x.org", "http://", "/relative", "https://a b"] {
+            assert!(!check(Rule::url(), invalid), "{}", invalid);
+        }
+        assert!(check(Rule::range(-1.5, 1.5), " -1.5 "));
+        assert!(!check(Rule::range(0.0, 1.0), "NaN"));
+        assert!(check(Rule::length(2, 2), "éé"));
+        assert!(check(Rule::regex(r"^\d{3}$"), "123"));
+        assert!(!check(Rule::regex(r"^\d{3}$"), "1234"));
+    }
+
+    #[test]
+    fn test_equals_and_custom_with() {
+        let validator = Validator::new()
+            .field("tags.0", [Rule::equals("address.city")])
+            .field(
+                "count",
+                [Rule::custom_with(|value, data| match data.path("tags") {
+                    Some(Value::Array(tags)) if Value::Int(tags.len() as i64) == *value => Ok(()),
+                    _ => Err("must count the tags".to_string()),
+                })],
+            );
+        let errors = validator.validate(&data()).unwrap_err();
+        assert_eq!(errors.get("tags.0"), ["must match address.city"]);
+        assert_eq!(errors.get("count"), ["must count the tags"]);
+
+        let validator = Validator::new()
+            .field("confirm", [Rule::equals("password")])
+            .field("again", [Rule::length(8, 64).and(Rule::equals("password"))]);
+        let form = |pairs: &[(&str, &str)]| {
+            Value::Dictionary(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                    .collect(),
+            )
+        };
+        let errors = validator
+            .validate(&form(&[("password", "secret"), ("again", " ")]))
+            .unwrap_err();
+        assert_eq!(errors.get("confirm"), ["must match password"]);
+        assert_eq!(errors.get("again"), ["must match password"]);
+        assert!(validator.validate(&form(&[])).is_ok());
+        assert!(validator
+            .validate(&form(&[("password", " "), ("confirm", "")]))
+            .is_ok());
+        let errors = validator
+            .validate(&form(&[("confirm", "secret")]))
+            .unwrap_err();
+        assert_eq!(errors.get("confirm"), ["must match password"]);
+    }
+
+    #[test]
+    fn test_json_validator() {
+        use crate::concepts::Parsable;
+        use crate::http::{
+            Headers, Method, RequestFactory, ResponseFactory, ResponseTrait, Version,
+        };
+
+        let schema = Schema::new(Value::from([("type", "array")])).unwrap();
+        let mut validator = JsonValidator::new(schema);
+        let mut ok = |_: &(), _: &mut Request| -> Response {
+            ResponseFactory::version(Version::Http1_1).ok(Headers::new(), "ok".into())
+        };
+        let factory = RequestFactory::version(Version::Http1_0);
+        let uri = Uri::parse("/").unwrap().1;
+        let mut json = Headers::new();
+        json.set("Content-Type", &["application/json"]);
+
+        let mut req = factory.get(uri.clone(), Headers::new());
+        assert_eq!(validator.handle(&(), &mut req, &mut ok).body(), "ok");
+        let mut req = factory.build(Method::Put, uri.clone(), json.clone(), "[]");
+        assert_eq!(validator.handle(&(), &mut req, &mut ok).body(), "ok");
+        let mut req = factory.post(uri.clone(), Headers::new(), "[]");
+        let resp = validator.handle(&(), &mut req, &mut ok);
+        assert_eq!(resp.status(), Status::UnsupportedMediaType);
+        assert_eq!(resp.protocol_version(), Version::Http1_0);
+        let mut req = factory.post(uri.clone(), json.clone(), "[");
+        let resp = validator.handle(&(), &mut req, &mut ok);
+        assert_eq!(resp.status(), Status::BadRequest);
+        let mut req = factory.post(uri, json, "{}");
+        let resp = validator.handle(&(), &mut req, &mut ok);
+        assert_eq!(resp.status(), Status::UnprocessableEntity);
+        assert!(resp
+            .body()
+            .contains(r#""fields": {"": ["must be of type array"]}"#));
+    }
+}
@@ -1,3 +1,4 @@
+use hermes::concepts::Dictionary;
 use hermes::container::Container;
 use hermes::http::routing::router::{Route, Router};
 use hermes::http::{
@@ -39,6 +40,8 @@ fn controller_uses_container() {
         method: Method::Get,
         target: uri,
         message: Message::v1_1(Headers::new(), String::new()),
+        attributes: Dictionary::new(),
+        raw_body: None,
     };
 
     let resp = router.handle_request(&container, &mut req).unwrap();
@@ -0,0 +1,48 @@
+//! Kept in its own test binary: the signal is sent to the whole process.
+
+use hermes::concepts::lifecycle::{BoxFuture, Runtime, Service, Shutdown};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Service signalling once running, then recording its finalization.
+struct Worker(Option<oneshot::Sender<()>>, Arc<AtomicBool>);
+
+impl Service for Worker {
+    fn run(&mut self, mut shutdown: Shutdown) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            if let Some(ready) = self.0.take() {
+                let _ = ready.send(());
+            }
+            shutdown.wait().await;
+            Ok(())
+        })
+    }
+
+    fn finalize(&mut self) -> BoxFuture<'_, Result<(), String>> {
+        self.1.store(true, Ordering::SeqCst);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_sigterm_shuts_down() {
+    let finalized = Arc::new(AtomicBool::new(false));
+    let (ready, started) = oneshot::channel();
+    let worker = Worker(Some(ready), finalized.clone());
+    let running = tokio::spawn(Runtime::new().service(worker).run());
+    // The runtime listens for signals before its service tasks get to run.
+    started.await.unwrap();
+    assert!(!running.is_finished());
+
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+    assert_eq!(result.unwrap().unwrap(), Ok(()));
+    assert!(finalized.load(Ordering::SeqCst));
+}
@@ -0,0 +1,148 @@
+use hermes::concepts::Parsable;
+use hermes::http::security::oauth::{code_challenge, OAuthClient, OAuthError};
+use hermes::http::session::{FileStore, Session};
+use hermes::http::{MessageTrait, Request, ResponseTrait, Status, Uri};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Minimal token endpoint: checks the PKCE verifier against the challenge
+/// sent in the authorization request and issues tokens.
+async fn token_server(challenge: Arc<Mutex<String>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            socket.read_to_end(&mut buf).await.unwrap();
+            let text = String::from_utf8_lossy(&buf).to_string();
+            let (_, request) = Request::parse(&text).unwrap();
+            let body = request.body();
+            let param = |name: &str| {
+                body.split('&')
+                    .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+                    .map(str::to_string)
+            };
+            let (status, json) = match param("grant_type").as_deref() {
+                Some("authorization_code")
+                    if param("code").as_deref() == Some("the-code")
+                        && param("code_verifier")
+                            .map(|v| code_challenge(&v) == *challenge.lock().unwrap())
+                            .unwrap_or(false) =>
+                {
+                    (
+                        "200 OK",
+                        r#"{"access_token":"access-1","token_type":"Bearer","expires_in":3600,"refresh_token":"refresh-1"}"#,
+                    )
+                }
+                Some("refresh_token") if param("refresh_token").as_deref() == Some("refresh-1") => {
+                    ("200 OK", r#"{"access_token":"access-2","expires_in":3600}"#)
+                }
+                _ => ("400 Bad Request", r#"{"error":"invalid_grant"}"#),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                status,
+                json.len(),
+                json
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+        }
+    });
+    format!("http://{}/token", address)
+}
+
+fn callback_request(query: &str) -> Request {
+    let text = format!(
+        "GET /callback?{} HTTP/1.1\r\nHost: app.local\r\n\r\n",
+        query
+    );
+    Request::parse(&text).unwrap().1
+}
+
+#[tokio::test]
+async fn test_authorization_code_flow() {
+    let challenge = Arc::new(Mutex::new(String::new()));
+    let token_url = token_server(challenge.clone()).await;
+    let client = OAuthClient::new(
+        "app",
+        "http://auth.local/authorize",
+        &token_url,
+        "http://app.local/callback",
+    )
+    .unwrap();
+    let dir = std::env::temp_dir().join("hermes_oauth_flow");
+    let mut session = Session::new("flow", FileStore::new(&dir));
+
+    let response = client.authorize(&mut session);
+    assert_eq!(response.status(), Status::Found);
+    let location = response.headers().get("Location").unwrap()[0].clone();
+    let (_, location) = Uri::parse(&location).unwrap();
+    let state = location.query().get("state").unwrap().clone();
+    *challenge.lock().unwrap() = location.query().get("code_challenge").unwrap().clone();
+
+    let tokens = client
+        .callback(
+            &callback_request(&format!("code=the-code&state={}", state)),
+            &mut session,
+        )
+        .await
+        .unwrap();
+    assert_eq!(tokens.access_token, "access-1");
+    assert_eq!(client.tokens(&session), Some(tokens));
+    assert!(session.get("oauth.state").is_none());
+
+    let refreshed = client.refresh(&mut session).await.unwrap();
+    assert_eq!(refreshed.access_token, "access-2");
+    assert_eq!(refreshed.refresh_token.as_deref(), Some("refresh-1"));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_callback_errors() {
+    let challenge = Arc::new(Mutex::new(String::new()));
+    let token_url = token_server(challenge).await;
+    let client = OAuthClient::new(
+        "app",
+        "http://auth.local/authorize",
+        &token_url,
+        "http://app.local/callback",
+    )
+    .unwrap();
+    let dir = std::env::temp_dir().join("hermes_oauth_errors");
+    let mut session = Session::new("errors", FileStore::new(&dir));
+
+    client.authorize(&mut session);
+    let result = client
+        .callback(
+            &callback_request("code=the-code&state=forged"),
+            &mut session,
+        )
+        .await;
+    assert_eq!(result, Err(OAuthError::StateMismatch));
+
+    client.authorize(&mut session);
+    let result = client
+        .callback(&callback_request("error=access_denied"), &mut session)
+        .await;
+    assert!(matches!(result, Err(OAuthError::Provider { error, .. }) if error == "access_denied"));
+
+    // The verifier does not match the (empty) registered challenge.
+    client.authorize(&mut session);
+    let Some(hermes::concepts::value::Value::String(state)) = session.get("oauth.state").cloned()
+    else {
+        panic!("state not stored");
+    };
+    let result = client
+        .callback(
+            &callback_request(&format!("code=the-code&state={}", state)),
+            &mut session,
+        )
+        .await;
+    assert!(matches!(result, Err(OAuthError::Provider { error, .. }) if error == "invalid_grant"));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,133 @@
+use hermes::concepts::lifecycle::Shutdown;
+use hermes::http::services::server::{Limits, Server};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Start a server with `limits` on a free port and return its address.
+async fn start(limits: Limits) -> (String, tokio::task::JoinHandle<()>) {
+    // Bound before serving: connections queue until they are accepted.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = Server::new(&address).with_limits(limits);
+    let handle = tokio::spawn(async move {
+        let _ = server.serve(listener, Shutdown::never()).await;
+    });
+    (address, handle)
+}
+
+/// Send `raw` without closing the connection and return the status line.
+async fn status_of(address: &str, raw: &[u8]) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    // The server may answer and close before everything is written.
+    let _ = stream.write_all(raw).await;
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf).await;
+    let text = String::from_utf8_lossy(&buf);
+    text.lines().next().unwrap_or_default().to_string()
+}
+
+fn limits() -> Limits {
+    Limits {
+        max_request_line: 64,
+        max_header_count: 3,
+        max_header_size: 32,
+        max_headers_size: 80,
+        max_body_size: 16,
+        header_timeout: Duration::from_millis(300),
+        body_timeout: Duration::from_millis(300),
+    }
+}
+
+#[tokio::test]
+async fn test_valid_request_with_body() {
+    let (address, handle) = start(limits()).await;
+    let status = status_of(
+        &address,
+        b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhello",
+    )
+    .await;
+    assert_eq!(status, "HTTP/1.1 204 No Content");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_request_line_too_long() {
+    let (address, handle) = start(limits()).await;
+    let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(100));
+    assert!(status_of(&address, raw.as_bytes()).await.contains(" 414 "));
+    // Rejected before the end of the line arrives.
+    let raw = format!("GET /{}", "a".repeat(100));
+    assert!(status_of(&address, raw.as_bytes()).await.contains(" 414 "));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_headers_too_large() {
+    let (address, handle) = start(limits()).await;
+    let too_many = "GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\nD: 4\r\n\r\n";
+    assert!(status_of(&address, too_many.as_bytes())
+        .await
+        .contains(" 431 "));
+    let too_long = format!("GET / HTTP/1.1\r\nA: {}\r\n\r\n", "x".repeat(40));
+    assert!(status_of(&address, too_long.as_bytes())
+        .await
+        .contains(" 431 "));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_conflicting_content_length() {
+    let (address, handle) = start(limits()).await;
+    let raw = b"POST / HTTP/1.1\r\nContent-Length: 0\r\nContent-Length: 5\r\n\r\nhello";
+    assert!(status_of(&address, raw).await.contains(" 400 "));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_body_too_large() {
+    let (address, handle) = start(limits()).await;
+    let raw = "POST / HTTP/1.1\r\nContent-Length: 17\r\n\r\n";
+    assert!(status_of(&address, raw.as_bytes()).await.contains(" 413 "));
+    let raw = "POST / HTTP/1.1\r\nContent-Length: nope\r\n\r\n";
+    assert!(status_of(&address, raw.as_bytes()).await.contains(" 400 "));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_slow_clients_time_out() {
+    let (address, handle) = start(limits()).await;
+    // Headers never finished.
+    assert!(status_of(&address, b"GET / HTTP/1.1\r\nHost: a\r\n")
+        .await
+        .contains(" 408 "));
+    // Body shorter than announced.
+    let raw = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc";
+    assert!(status_of(&address, raw).await.contains(" 408 "));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_malformed_requests() {
+    let (address, handle) = start(limits()).await;
+    let status = status_of(&address, b"GET / HTTP/3.7\r\n\r\n").await;
+    assert!(status.contains(" 505 "), "{}", status);
+    let status = status_of(&address, b"GET / HTTP/1.1\r\nBad Header: x\r\n\r\n").await;
+    assert!(status.contains(" 400 "), "{}", status);
+    let status = status_of(&address, b"GET /\r\n\r\n").await;
+    assert!(status.contains(" 400 "), "{}", status);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_header_names_ignore_case() {
+    let (address, handle) = start(limits()).await;
+    let raw = "POST / HTTP/1.1\r\ncontent-length: 17\r\n\r\n";
+    assert!(status_of(&address, raw.as_bytes()).await.contains(" 413 "));
+    // The announced body is awaited rather than dropped.
+    let raw = b"POST / HTTP/1.1\r\nCONTENT-LENGTH: 10\r\n\r\nabc";
+    assert!(status_of(&address, raw).await.contains(" 408 "));
+    let raw = b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+    assert!(status_of(&address, raw).await.contains(" 501 "));
+    handle.abort();
+}
@@ -0,0 +1,123 @@
+use hermes::concepts::lifecycle::{BoxFuture, Runtime, Service, Shutdown};
+use hermes::http::services::server::Server;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// Service signalling once `S` is started.
+struct Ready<S>(S, Option<oneshot::Sender<()>>);
+
+impl<S: Service> Service for Ready<S> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn start(&mut self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            self.0.start().await?;
+            if let Some(ready) = self.1.take() {
+                let _ = ready.send(());
+            }
+            Ok(())
+        })
+    }
+
+    fn run(&mut self, shutdown: Shutdown) -> BoxFuture<'_, Result<(), String>> {
+        self.0.run(shutdown)
+    }
+
+    fn finalize(&mut self) -> BoxFuture<'_, Result<(), String>> {
+        self.0.finalize()
+    }
+}
+
+const HEAD: &[u8] = b"GET / HTTP/1.1\r\nHost: a\r\n";
+
+async fn read_all(stream: &mut TcpStream) -> String {
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// Answer a complete request on a new connection: as connections are
+/// accepted in order, the ones opened before are accepted too.
+async fn request(address: SocketAddr) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream.write_all(HEAD).await.unwrap();
+    stream.write_all(b"\r\n").await.unwrap();
+    read_all(&mut stream).await
+}
+
+#[tokio::test]
+async fn test_drains_requests_in_progress() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, shutdown) = Shutdown::channel();
+    let serving = tokio::spawn(async move { Server::new("").serve(listener, shutdown).await });
+
+    // A request is in progress when the shutdown is triggered.
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream.write_all(HEAD).await.unwrap();
+    assert!(request(address).await.starts_with("HTTP/1.1 204 "));
+    sender.send(Duration::from_secs(5));
+
+    // New connections are refused once the listener is closed, the pending
+    // request is still answered.
+    while TcpStream::connect(address).await.is_ok() {
+        tokio::task::yield_now().await;
+    }
+    assert!(!serving.is_finished());
+    stream.write_all(b"\r\n").await.unwrap();
+    assert!(read_all(&mut stream).await.starts_with("HTTP/1.1 204 "));
+    serving.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_drops_requests_at_deadline() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, shutdown) = Shutdown::channel();
+    let serving = tokio::spawn(async move { Server::new("").serve(listener, shutdown).await });
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream.write_all(HEAD).await.unwrap();
+    assert!(request(address).await.starts_with("HTTP/1.1 204 "));
+    sender.send(Duration::from_millis(100));
+    serving.await.unwrap().unwrap();
+    assert_eq!(read_all(&mut stream).await, "");
+}
+
+#[tokio::test]
+async fn test_runtime_service() {
+    // The address is taken: the server fails to start.
+    let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = taken.local_addr().unwrap().to_string();
+    let errors = Runtime::new()
+        .signals(false)
+        .service(Server::new(&address))
+        .run()
+        .await
+        .unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]
+        .to_string()
+        .starts_with(&format!("service {} failed to start: ", address)));
+    drop(taken);
+
+    let (ready, started) = oneshot::channel();
+    let runtime = Runtime::new()
+        .signals(false)
+        .service(Ready(Server::new(&address), Some(ready)));
+    let handle = runtime.handle();
+    let running = tokio::spawn(runtime.run());
+    started.await.unwrap();
+    let mut stream = TcpStream::connect(&address).await.unwrap();
+    stream.write_all(HEAD).await.unwrap();
+    stream.write_all(b"\r\n").await.unwrap();
+    assert!(read_all(&mut stream).await.starts_with("HTTP/1.1 204 "));
+    handle.stop();
+    assert_eq!(running.await.unwrap(), Ok(()));
+    assert!(TcpStream::connect(&address).await.is_err());
+}